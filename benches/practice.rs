@@ -0,0 +1,78 @@
+//! Latency of the practice on a 10k-character challenge, run with
+//! `cargo bench --no-default-features`.
+//!
+//! The GTK interface lays out the challenge once then only colours the
+//! touches in view, the terminal interface walks the whole practice on
+//! each frame: both must stay well below a frame at 60 Hz.
+
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use touchtype::model::{Challenge, Practice, Touch};
+
+const CHARS: usize = 10_000;
+const RUNS: usize = 50;
+
+/// Returns a challenge of about `CHARS` touches.
+fn challenge() -> Challenge {
+    let words = [
+        "the", "quick", "brown", "fox", "jumps", "over", "lazy", "dog",
+    ];
+    let text: Vec<&str> = words.iter().cycle().take(CHARS / 5).copied().collect();
+    Challenge::from_str(&text.join(" ")).unwrap()
+}
+
+/// Prints the median and the worst of `durations`.
+fn report(name: &str, mut durations: Vec<Duration>) {
+    durations.sort();
+    let median = durations[durations.len() / 2];
+    let worst = durations[durations.len() - 1];
+    println!(
+        "{:<40} median {:>10.1?}  worst {:>10.1?}",
+        name, median, worst
+    );
+}
+
+/// Times `f` `RUNS` times.
+fn bench<T>(name: &str, mut f: impl FnMut() -> T) {
+    let durations = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            std::hint::black_box(f());
+            start.elapsed()
+        })
+        .collect();
+    report(name, durations);
+}
+
+fn main() {
+    let challenge = challenge();
+    println!("challenge of {} touches", challenge.len());
+
+    bench("walk the challenge", || challenge.iter().count());
+    bench("search each touch with expected_at", || {
+        (0..challenge.len())
+            .filter_map(|i| challenge.expected_at(i))
+            .count()
+    });
+
+    let mut practice = Practice::new(challenge.clone(), "bench".to_string());
+    let touches: Vec<Touch> = challenge.iter().map(|(t, _)| t).collect();
+    let now = Instant::now();
+    let presses = touches
+        .iter()
+        .map(|t| {
+            let start = Instant::now();
+            practice.press(t, now);
+            start.elapsed()
+        })
+        .collect();
+    report("press a key", presses);
+
+    let mut halfway = Practice::new(challenge.clone(), "bench".to_string());
+    for t in &touches[..touches.len() / 2] {
+        halfway.press(t, now);
+    }
+    bench("walk the practice typed halfway", || halfway.iter().count());
+    bench("walk the practice typed", || practice.iter().count());
+}
@@ -0,0 +1,156 @@
+//! Confusion matrix of expected versus typed touches.
+//!
+//! Only wrong touches are counted: the matrix tells which keys are
+//! substituted for which, the layout tells why it probably happened.
+
+use anyhow::{Context, Result};
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+use crate::layout::{ErrorKind, Layout};
+use crate::model::Touch;
+use crate::session::{History, Session};
+
+/// How many times each touch was typed instead of another.
+#[derive(Clone, Debug, Default)]
+pub struct ConfusionMatrix {
+    /// How many times `typed` was typed instead of `expected`.
+    counts: BTreeMap<(Touch, Touch), usize>,
+}
+
+/// A cell of the matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Substitution {
+    /// The touch expected.
+    pub expected: Touch,
+    /// The touch typed instead.
+    pub typed: Touch,
+    /// How many times.
+    pub count: usize,
+    /// The likely cause, from the layout.
+    pub kind: ErrorKind,
+}
+
+impl From<&Session> for ConfusionMatrix {
+    fn from(session: &Session) -> Self {
+        let mut matrix = ConfusionMatrix::default();
+        matrix.add_session(session);
+        matrix
+    }
+}
+
+impl From<&History> for ConfusionMatrix {
+    fn from(history: &History) -> Self {
+        let mut matrix = ConfusionMatrix::default();
+        for session in history.sessions() {
+            matrix.add_session(session);
+        }
+        matrix
+    }
+}
+
+impl ConfusionMatrix {
+    /// Counts the wrong touches of `session`.
+    pub fn add_session(&mut self, session: &Session) {
+        for k in session.keystrokes.iter().filter(|k| !k.is_success()) {
+            *self.counts.entry((k.expected, k.typed)).or_default() += 1;
+        }
+    }
+
+    /// Returns the touches that were expected at least once, in order.
+    pub fn expected(&self) -> Vec<Touch> {
+        let mut touches: Vec<Touch> = self.counts.keys().map(|(e, _)| *e).collect();
+        touches.dedup();
+        touches
+    }
+
+    /// Returns the touches that were typed at least once, in order.
+    pub fn typed(&self) -> Vec<Touch> {
+        let mut touches: Vec<Touch> = self.counts.keys().map(|(_, t)| *t).collect();
+        touches.sort();
+        touches.dedup();
+        touches
+    }
+
+    /// Returns how many times `typed` was typed instead of `expected`.
+    pub fn count(&self, expected: Touch, typed: Touch) -> usize {
+        self.counts.get(&(expected, typed)).copied().unwrap_or(0)
+    }
+
+    /// Returns every substitution, most frequent first.
+    pub fn substitutions(&self, layout: &Layout) -> Vec<Substitution> {
+        let mut substitutions: Vec<Substitution> = self
+            .counts
+            .iter()
+            .map(|((expected, typed), count)| Substitution {
+                expected: *expected,
+                typed: *typed,
+                count: *count,
+                kind: layout.classify(expected, typed),
+            })
+            .collect();
+        substitutions.sort_by_key(|s| std::cmp::Reverse(s.count));
+        substitutions
+    }
+
+    /// Returns the matrix as csv, a row per expected touch and a column
+    /// per typed touch.
+    pub fn to_csv(&self) -> String {
+        let typed = self.typed();
+        let field = |t: &Touch| match t {
+            Touch::Char('"') => "\"\"\"\"".to_string(),
+            Touch::Char(',') => "\",\"".to_string(),
+            Touch::Char(c) => c.to_string(),
+            Touch::Space => "space".to_string(),
+        };
+        let mut csv = String::from("expected");
+        for t in &typed {
+            write!(csv, ",{}", field(t)).expect("write to a string");
+        }
+        for e in self.expected() {
+            write!(csv, "\n{}", field(&e)).expect("write to a string");
+            for t in &typed {
+                write!(csv, ",{}", self.count(e, *t)).expect("write to a string");
+            }
+        }
+        csv.push('\n');
+        csv
+    }
+
+    /// Writes the matrix as csv at `path`.
+    pub fn export(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_csv()).context(format!("cannot create file at {:?}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ConfusionMatrix;
+    use crate::layout::{ErrorKind, Layout};
+    use crate::model::{Keystroke, Touch};
+    use crate::session::Session;
+
+    #[test]
+    pub fn it_counts_substitutions() {
+        let keystrokes = "the rhe thr tge"
+            .chars()
+            .zip("the the the the".chars())
+            .map(|(t, e)| Keystroke::new(Touch::from(e), Touch::from(t), Duration::ZERO))
+            .collect();
+        let session = Session {
+            name: "test".to_string(),
+            timestamp: 0,
+            text: "the the the the".to_string(),
+            keystrokes,
+            ..Session::default()
+        };
+        let matrix = ConfusionMatrix::from(&session);
+        let substitutions = matrix.substitutions(&Layout::qwerty());
+        assert_eq!(substitutions.len(), 3);
+        assert!(substitutions
+            .iter()
+            .all(|s| s.count == 1 && s.kind == ErrorKind::Neighbour));
+        assert_eq!(matrix.to_csv(), "expected,g,r\ne,0,1\nh,1,0\nt,0,1\n");
+    }
+}
@@ -0,0 +1,240 @@
+//! How long the keys are held and the flights between them, from the key
+//! releases recorded in the sessions.
+//!
+//! Histogram: durations counted in buckets of equal width.
+//! KeyDwell: how long a key was held, and how often too long: beginners
+//! often hold a key until they see its letter.
+//! DwellStats: the dwell of each key, the flights from a release to the next
+//! press and the rollovers, when the next key is pressed before the
+//! previous one is released, as fast typists do.
+
+use std::{collections::HashMap, time::Duration};
+
+use crate::model::Touch;
+use crate::session::{History, Session};
+
+/// A key held longer is held too long.
+pub const LONG_HOLD: Duration = Duration::from_millis(250);
+const BUCKET: Duration = Duration::from_millis(20);
+/// The last bucket counts the longer durations too.
+const BUCKETS: usize = 20;
+
+/// Durations counted by bucket.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    /// Width of a bucket.
+    pub bucket: Duration,
+    /// `counts[i]` durations were between `i` and `i + 1` buckets.
+    pub counts: Vec<usize>,
+}
+
+/// How long a key was held.
+#[derive(Clone, Debug)]
+pub struct KeyDwell {
+    /// The key.
+    pub touch: Touch,
+    /// Sorted.
+    dwells: Vec<Duration>,
+    /// How many times it was held longer than `LONG_HOLD`.
+    pub long_holds: usize,
+}
+
+/// The dwells and flights of the sessions.
+#[derive(Clone, Debug, Default)]
+pub struct DwellStats {
+    keys: HashMap<Touch, KeyDwell>,
+    /// How long the keys were held.
+    pub dwell: Histogram,
+    /// The flights of the keys pressed after the previous one was released.
+    pub flight: Histogram,
+    /// How many keys were pressed before the previous one was released.
+    pub rollovers: usize,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram {
+            bucket: BUCKET,
+            counts: vec![0; BUCKETS],
+        }
+    }
+}
+
+impl Histogram {
+    fn add(&mut self, d: Duration) {
+        let i = (d.as_millis() / self.bucket.as_millis()) as usize;
+        let last = self.counts.len() - 1;
+        self.counts[i.min(last)] += 1;
+    }
+
+    /// Returns the number of durations counted.
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the largest count of a bucket.
+    pub fn max(&self) -> usize {
+        self.counts.iter().copied().max().unwrap_or_default()
+    }
+}
+
+impl KeyDwell {
+    fn new(touch: Touch) -> Self {
+        KeyDwell {
+            touch,
+            dwells: vec![],
+            long_holds: 0,
+        }
+    }
+
+    fn add(&mut self, dwell: Duration) {
+        let i = self.dwells.partition_point(|d| *d <= dwell);
+        self.dwells.insert(i, dwell);
+        if dwell > LONG_HOLD {
+            self.long_holds += 1;
+        }
+    }
+
+    /// How many times the key was released.
+    pub fn count(&self) -> usize {
+        self.dwells.len()
+    }
+
+    /// Returns the median time the key was held, None if never released.
+    pub fn median(&self) -> Option<Duration> {
+        self.dwells.get(self.dwells.len() / 2).copied()
+    }
+
+    /// Ratio of the presses held too long, between 0 and 1.
+    pub fn long_hold_rate(&self) -> f32 {
+        if self.dwells.is_empty() {
+            0.0
+        } else {
+            self.long_holds as f32 / self.dwells.len() as f32
+        }
+    }
+}
+
+impl From<&Session> for DwellStats {
+    fn from(session: &Session) -> Self {
+        let mut stats = DwellStats::default();
+        stats.add_session(session);
+        stats
+    }
+}
+
+impl From<&History> for DwellStats {
+    fn from(history: &History) -> Self {
+        let mut stats = DwellStats::default();
+        for session in history.sessions() {
+            stats.add_session(session);
+        }
+        stats
+    }
+}
+
+impl DwellStats {
+    /// Counts the dwells and the flights of `session`.
+    pub fn add_session(&mut self, session: &Session) {
+        let mut previous: Option<(Duration, Duration)> = None;
+        for k in &session.keystrokes {
+            if let Some(dwell) = k.dwell {
+                self.dwell.add(dwell);
+                self.keys
+                    .entry(k.typed)
+                    .or_insert_with(|| KeyDwell::new(k.typed))
+                    .add(dwell);
+            }
+            match (previous, k.flight) {
+                // released after this key was pressed
+                (Some((at, dwell)), Some(_)) if at + dwell > k.at => self.rollovers += 1,
+                (_, Some(flight)) => self.flight.add(flight),
+                _ => (),
+            }
+            previous = k.dwell.map(|dwell| (k.at, dwell));
+        }
+    }
+
+    /// Returns the keys typed, the longest held first.
+    pub fn keys(&self) -> Vec<&KeyDwell> {
+        let mut keys: Vec<&KeyDwell> = self.keys.values().collect();
+        keys.sort_by(|a, b| b.median().cmp(&a.median()).then(a.touch.cmp(&b.touch)));
+        keys
+    }
+
+    /// Returns the keys held too long at least once, the most often first.
+    pub fn long_holds(&self) -> Vec<&KeyDwell> {
+        let mut keys: Vec<&KeyDwell> = self.keys.values().filter(|k| k.long_holds > 0).collect();
+        keys.sort_by(|a, b| {
+            b.long_hold_rate()
+                .total_cmp(&a.long_hold_rate())
+                .then(a.touch.cmp(&b.touch))
+        });
+        keys
+    }
+
+    /// Ratio of the keys pressed before the previous one was released,
+    /// between 0 and 1.
+    pub fn rollover_rate(&self) -> f32 {
+        let flights = self.flight.total() + self.rollovers;
+        if flights == 0 {
+            0.0
+        } else {
+            self.rollovers as f32 / flights as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::DwellStats;
+    use crate::model::{Keystroke, Touch};
+    use crate::session::Session;
+
+    #[test]
+    pub fn it_finds_long_holds_and_rollovers() {
+        let ms = Duration::from_millis;
+        // typed at, held for
+        let keystrokes = [
+            ('a', 0, 80),
+            ('b', 200, 400),
+            ('a', 400, 90),
+            ('b', 450, 100),
+        ]
+        .iter()
+        .map(|(c, at, dwell)| Keystroke {
+            dwell: Some(ms(*dwell)),
+            ..Keystroke::new(Touch::Char(*c), Touch::Char(*c), ms(*at))
+        })
+        .collect::<Vec<Keystroke>>();
+        let mut session = Session {
+            name: "test".to_string(),
+            timestamp: 0,
+            text: "abab".to_string(),
+            keystrokes,
+            ..Session::default()
+        };
+        // as the practice records them
+        let flights = [None, Some(120), Some(0), Some(0)];
+        for (k, flight) in session.keystrokes.iter_mut().zip(flights) {
+            k.flight = flight.map(ms);
+        }
+
+        let stats = DwellStats::from(&session);
+        assert_eq!(stats.dwell.total(), 4);
+        assert_eq!(stats.dwell.counts[4], 2);
+        // the longer dwells are in the last bucket
+        assert_eq!(stats.dwell.counts[19], 1);
+        assert_eq!(stats.flight.total(), 1);
+        assert_eq!(stats.flight.counts[6], 1);
+        assert_eq!(stats.rollovers, 2);
+        assert!((stats.rollover_rate() - 2.0 / 3.0).abs() < 1e-6);
+
+        let long: Vec<Touch> = stats.long_holds().iter().map(|k| k.touch).collect();
+        assert_eq!(long, vec![Touch::Char('b')]);
+        assert_eq!(stats.keys()[0].touch, Touch::Char('b'));
+        assert_eq!(stats.keys()[0].median(), Some(ms(400)));
+    }
+}
@@ -0,0 +1,284 @@
+//! Per-key and per-bigram statistics.
+//!
+//! Gram: an expected touch or a pair of consecutive expected touches.
+//! GramStats: how a gram was typed, its errors and latencies.
+//! KeyStats: the statistics of every gram of one or many sessions.
+//!
+//! The latency of a keystroke is the time elapsed since the previous one,
+//! the first keystroke of a session has none.
+
+use std::{collections::HashMap, fmt::Display, time::Duration};
+
+use crate::model::Touch;
+use crate::session::{History, Session};
+
+/// What the statistics are about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Gram {
+    /// A single expected touch.
+    Key(Touch),
+    /// A touch expected after another.
+    Bigram(Touch, Touch),
+}
+
+/// How a gram was typed.
+#[derive(Clone, Debug)]
+pub struct GramStats {
+    /// The gram.
+    pub gram: Gram,
+    /// How many times the gram was expected.
+    pub count: usize,
+    /// How many times it was not typed as expected.
+    pub errors: usize,
+    /// Sorted latencies.
+    latencies: Vec<Duration>,
+    /// What was typed instead, and how many times.
+    substitutions: HashMap<Touch, usize>,
+}
+
+/// A column of the statistics, to sort them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    /// The gram itself.
+    Gram,
+    /// How many times it was expected.
+    Count,
+    /// The ratio of errors.
+    ErrorRate,
+    /// The mean latency.
+    Mean,
+    /// The median latency.
+    Median,
+    /// The 90th percentile of the latencies.
+    P90,
+    /// What was most commonly typed instead.
+    Substitution,
+}
+
+/// The statistics of every gram.
+#[derive(Clone, Debug, Default)]
+pub struct KeyStats {
+    grams: HashMap<Gram, GramStats>,
+}
+
+impl Display for Gram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Gram::Key(t) => write!(f, "{}", t),
+            Gram::Bigram(a, b) => write!(f, "{}{}", a, b),
+        }
+    }
+}
+
+impl GramStats {
+    fn new(gram: Gram) -> Self {
+        GramStats {
+            gram,
+            count: 0,
+            errors: 0,
+            latencies: vec![],
+            substitutions: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, typed: Touch, expected: Touch, latency: Option<Duration>) {
+        self.count += 1;
+        if typed != expected {
+            self.errors += 1;
+            *self.substitutions.entry(typed).or_default() += 1;
+        }
+        if let Some(latency) = latency {
+            let i = self.latencies.partition_point(|l| *l <= latency);
+            self.latencies.insert(i, latency);
+        }
+    }
+
+    fn merge(&mut self, other: &GramStats) {
+        self.count += other.count;
+        self.errors += other.errors;
+        self.latencies.extend(&other.latencies);
+        self.latencies.sort();
+        for (t, n) in &other.substitutions {
+            *self.substitutions.entry(*t).or_default() += n;
+        }
+    }
+
+    /// Ratio of errors, between 0 and 1.
+    pub fn error_rate(&self) -> f32 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.errors as f32 / self.count as f32
+        }
+    }
+
+    /// Returns the mean latency, None without any.
+    pub fn mean(&self) -> Option<Duration> {
+        let n = u32::try_from(self.latencies.len())
+            .ok()
+            .filter(|n| *n > 0)?;
+        Some(self.latencies.iter().sum::<Duration>() / n)
+    }
+
+    /// Returns the median latency, None without any.
+    pub fn median(&self) -> Option<Duration> {
+        self.percentile(0.5)
+    }
+
+    /// Returns the 90th percentile of the latencies, None without any.
+    pub fn p90(&self) -> Option<Duration> {
+        self.percentile(0.9)
+    }
+
+    /// Returns the latency below which `p` of the latencies are, using the
+    /// nearest rank.
+    fn percentile(&self, p: f32) -> Option<Duration> {
+        let rank = (p * self.latencies.len() as f32).ceil() as usize;
+        self.latencies.get(rank.max(1) - 1).copied()
+    }
+
+    /// Returns what was most commonly typed instead of the gram.
+    pub fn substitution(&self) -> Option<(Touch, usize)> {
+        self.substitutions
+            .iter()
+            .max_by_key(|(t, n)| (**n, std::cmp::Reverse(**t)))
+            .map(|(t, n)| (*t, *n))
+    }
+}
+
+impl From<&Session> for KeyStats {
+    fn from(session: &Session) -> Self {
+        let mut stats = KeyStats::default();
+        stats.add_session(session);
+        stats
+    }
+}
+
+impl From<&History> for KeyStats {
+    /// Aggregates every session of the history.
+    fn from(history: &History) -> Self {
+        let mut stats = KeyStats::default();
+        for session in history.sessions() {
+            stats.merge(&KeyStats::from(session));
+        }
+        stats
+    }
+}
+
+impl KeyStats {
+    /// Counts the keystrokes of `session`.
+    pub fn add_session(&mut self, session: &Session) {
+        let mut previous: Option<(Touch, Duration)> = None;
+        for k in &session.keystrokes {
+            let expected = k.expected;
+            let latency = previous.map(|(_, at)| k.at.saturating_sub(at));
+            self.gram_mut(Gram::Key(expected))
+                .add(k.typed, expected, latency);
+            if let Some((before, _)) = previous {
+                self.gram_mut(Gram::Bigram(before, expected))
+                    .add(k.typed, expected, latency);
+            }
+            previous = Some((expected, k.at));
+        }
+    }
+
+    /// Adds the statistics of `other`.
+    pub fn merge(&mut self, other: &KeyStats) {
+        for (gram, stats) in &other.grams {
+            self.gram_mut(*gram).merge(stats);
+        }
+    }
+
+    /// Returns the statistics of `gram`, None if it was never expected.
+    pub fn get(&self, gram: Gram) -> Option<&GramStats> {
+        self.grams.get(&gram)
+    }
+
+    fn gram_mut(&mut self, gram: Gram) -> &mut GramStats {
+        self.grams
+            .entry(gram)
+            .or_insert_with(|| GramStats::new(gram))
+    }
+
+    /// Returns the statistics of single keys if `bigrams` is false,
+    /// otherwise of bigrams, sorted by `column`.
+    pub fn sorted(&self, bigrams: bool, column: Column, descending: bool) -> Vec<&GramStats> {
+        let mut rows: Vec<&GramStats> = self
+            .grams
+            .values()
+            .filter(|s| matches!(s.gram, Gram::Bigram(_, _)) == bigrams)
+            .collect();
+        rows.sort_by(|a, b| {
+            let order = match column {
+                Column::Gram => a.gram.cmp(&b.gram),
+                Column::Count => a.count.cmp(&b.count),
+                Column::ErrorRate => a.error_rate().total_cmp(&b.error_rate()),
+                Column::Mean => a.mean().cmp(&b.mean()),
+                Column::Median => a.median().cmp(&b.median()),
+                Column::P90 => a.p90().cmp(&b.p90()),
+                Column::Substitution => a.substitution().cmp(&b.substitution()),
+            };
+            // ties are always in the order of the grams
+            let order = if descending { order.reverse() } else { order };
+            order.then(a.gram.cmp(&b.gram))
+        });
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Column, Gram, KeyStats};
+    use crate::model::{Keystroke, Touch};
+    use crate::session::Session;
+
+    #[test]
+    pub fn it_computes_key_and_bigram_stats() {
+        let keystrokes = [('a', 0), ('b', 100), ('a', 300), ('a', 400), ('b', 1000)]
+            .iter()
+            .zip("ab ab".chars())
+            .map(|((c, ms), e)| {
+                Keystroke::new(Touch::from(e), Touch::from(*c), Duration::from_millis(*ms))
+            })
+            .collect();
+        let session = Session {
+            name: "test".to_string(),
+            timestamp: 0,
+            text: "ab ab".to_string(),
+            keystrokes,
+            ..Session::default()
+        };
+        let stats = KeyStats::from(&session);
+        let b = stats.get(Gram::Key(Touch::Char('b'))).unwrap();
+        assert_eq!(b.count, 2);
+        assert_eq!(b.errors, 0);
+        assert_eq!(b.mean(), Some(Duration::from_millis(350)));
+        assert_eq!(b.median(), Some(Duration::from_millis(100)));
+        assert_eq!(b.p90(), Some(Duration::from_millis(600)));
+
+        let space = stats.get(Gram::Key(Touch::Space)).unwrap();
+        assert_eq!(space.error_rate(), 1.0);
+        assert_eq!(space.substitution(), Some((Touch::Char('a'), 1)));
+
+        let ab = stats
+            .get(Gram::Bigram(Touch::Char('a'), Touch::Char('b')))
+            .unwrap();
+        assert_eq!(ab.count, 2);
+
+        let keys: Vec<Gram> = stats
+            .sorted(false, Column::Count, true)
+            .iter()
+            .map(|s| s.gram)
+            .collect();
+        assert_eq!(
+            keys,
+            &[
+                Gram::Key(Touch::Char('a')),
+                Gram::Key(Touch::Char('b')),
+                Gram::Key(Touch::Space)
+            ]
+        );
+    }
+}
@@ -0,0 +1,6 @@
+//! Analyses of the keystrokes recorded in sessions.
+
+pub mod confusion;
+pub mod dwell;
+pub mod keys;
+pub mod rhythm;
@@ -0,0 +1,219 @@
+//! Typing rhythm, from the intervals between keystrokes.
+//!
+//! Beat: a keystroke with the interval since the previous one, the first
+//! keystroke of a session has none.
+//! Hesitation: an interval much longer than usual, before a touch of a word.
+//! Burst: consecutive intervals much shorter than usual.
+//! Rhythm: how steady the intervals of a session are, where the typist
+//! hesitated and where they typed in bursts.
+
+use std::{collections::HashMap, ops::Range, time::Duration};
+
+use crate::model::{Touch, Word};
+use crate::session::{self, Session};
+
+/// An interval that many times longer than the median is a hesitation.
+const HESITATION_RATIO: f32 = 2.5;
+/// An interval that many times shorter than the median belongs to a burst.
+const BURST_RATIO: f32 = 0.7;
+/// The fewest keystrokes in a burst.
+const BURST_LEN: usize = 4;
+
+/// How a keystroke fits the rhythm of the session.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BeatKind {
+    /// Typed at the usual pace.
+    #[default]
+    Steady,
+    /// Typed after a hesitation.
+    Hesitation,
+    /// Typed in a burst.
+    Burst,
+}
+
+/// A keystroke and when it was typed.
+#[derive(Clone, Copy, Debug)]
+pub struct Beat {
+    /// The touch typed.
+    pub typed: Touch,
+    /// The touch typed was the one expected.
+    pub success: bool,
+    /// Elapsed time since the first keystroke.
+    pub at: Duration,
+    /// Time since the previous keystroke, None for the first one.
+    pub interval: Option<Duration>,
+    /// How it fits the rhythm.
+    pub kind: BeatKind,
+}
+
+/// A touch typed after an interval much longer than the median.
+#[derive(Clone, Debug)]
+pub struct Hesitation {
+    /// Index of the keystroke typed after hesitating.
+    pub at: usize,
+    /// The touch expected then.
+    pub touch: Touch,
+    /// The word it belongs to.
+    pub word: Word,
+    /// The interval before the touch.
+    pub interval: Duration,
+}
+
+/// The rhythm of a session.
+#[derive(Clone, Debug, Default)]
+pub struct Rhythm {
+    /// A beat per keystroke.
+    pub beats: Vec<Beat>,
+    /// The median interval.
+    pub median: Duration,
+    /// 1 minus the coefficient of variation of the intervals, between 0
+    /// and 1.
+    pub consistency: f32,
+    /// The hesitations, in order.
+    pub hesitations: Vec<Hesitation>,
+    /// Ranges of keystrokes typed in a burst.
+    pub bursts: Vec<Range<usize>>,
+}
+
+/// Returns the median of `intervals`, which are sorted in place.
+fn median(intervals: &mut [Duration]) -> Duration {
+    intervals.sort();
+    intervals
+        .get(intervals.len() / 2)
+        .copied()
+        .unwrap_or_default()
+}
+
+impl From<&Session> for Rhythm {
+    fn from(session: &Session) -> Self {
+        let challenge = session.challenge();
+        let mut beats: Vec<Beat> = vec![];
+        for k in &session.keystrokes {
+            beats.push(Beat {
+                typed: k.typed,
+                success: k.is_success(),
+                at: k.at,
+                interval: beats.last().map(|b| k.at.saturating_sub(b.at)),
+                kind: BeatKind::Steady,
+            });
+        }
+        let intervals: Vec<Duration> = beats.iter().filter_map(|b| b.interval).collect();
+        let median = median(&mut intervals.clone());
+        let seconds: Vec<f32> = intervals.iter().map(Duration::as_secs_f32).collect();
+        let consistency = session::consistency(&seconds);
+
+        let mut hesitations = vec![];
+        for (i, (beat, k)) in beats.iter_mut().zip(&session.keystrokes).enumerate() {
+            let Some(interval) = beat.interval else {
+                continue;
+            };
+            if interval.as_secs_f32() > median.as_secs_f32() * HESITATION_RATIO {
+                beat.kind = BeatKind::Hesitation;
+                let Some(word) = challenge.word_index_at(i) else {
+                    continue;
+                };
+                hesitations.push(Hesitation {
+                    at: i,
+                    touch: k.expected,
+                    word: challenge.words()[word].clone(),
+                    interval,
+                });
+            }
+        }
+
+        let fast: Vec<bool> = beats
+            .iter()
+            .map(|b| {
+                b.interval
+                    .is_some_and(|i| i.as_secs_f32() < median.as_secs_f32() * BURST_RATIO)
+            })
+            .collect();
+        let mut bursts = vec![];
+        let mut start = 0;
+        for (i, fast) in fast.iter().chain([&false]).enumerate() {
+            if !fast {
+                if i - start >= BURST_LEN {
+                    bursts.push(start..i);
+                }
+                start = i + 1;
+            }
+        }
+        for burst in &bursts {
+            for beat in &mut beats[burst.clone()] {
+                beat.kind = BeatKind::Burst;
+            }
+        }
+
+        Rhythm {
+            beats,
+            median,
+            consistency,
+            hesitations,
+            bursts,
+        }
+    }
+}
+
+impl Rhythm {
+    /// Returns the touches hesitated before and how many times, the most
+    /// frequent first.
+    pub fn hesitant_touches(&self) -> Vec<(Touch, usize)> {
+        let mut counts: HashMap<Touch, usize> = HashMap::new();
+        for h in &self.hesitations {
+            *counts.entry(h.touch).or_default() += 1;
+        }
+        let mut touches: Vec<(Touch, usize)> = counts.into_iter().collect();
+        touches.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        touches
+    }
+
+    /// Returns the words hesitated in, once each, in order.
+    pub fn hesitant_words(&self) -> Vec<&Word> {
+        let mut words: Vec<&Word> = vec![];
+        for h in &self.hesitations {
+            if words.iter().all(|w| w.as_str() != h.word.as_str()) {
+                words.push(&h.word);
+            }
+        }
+        words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{BeatKind, Rhythm};
+    use crate::model::{Keystroke, Touch};
+    use crate::session::Session;
+
+    #[test]
+    pub fn it_finds_hesitations_and_bursts() {
+        let ms = [0, 200, 400, 600, 800, 900, 1000, 1100, 1200, 2000, 2200];
+        let text = "abcd efghij";
+        let keystrokes = text
+            .chars()
+            .zip(ms)
+            .map(|(c, ms)| {
+                Keystroke::new(Touch::from(c), Touch::from(c), Duration::from_millis(ms))
+            })
+            .collect();
+        let session = Session {
+            name: "test".to_string(),
+            timestamp: 0,
+            text: text.to_string(),
+            keystrokes,
+            ..Session::default()
+        };
+        let rhythm = Rhythm::from(&session);
+        assert_eq!(rhythm.median, Duration::from_millis(200));
+        assert_eq!(rhythm.beats[0].interval, None);
+        assert_eq!(rhythm.hesitations.len(), 1);
+        assert_eq!(rhythm.hesitations[0].at, 9);
+        assert_eq!(rhythm.hesitations[0].word.as_str(), "efghij");
+        assert_eq!(rhythm.hesitant_touches(), vec![(Touch::Char('i'), 1)]);
+        assert_eq!(rhythm.bursts, vec![5..9]);
+        assert_eq!(rhythm.beats[6].kind, BeatKind::Burst);
+        assert!(rhythm.consistency > 0.0 && rhythm.consistency < 1.0);
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::{Duration, Instant};
+
+/// How long the caret takes to slide to the next touch.
+const SLIDE: Duration = Duration::from_millis(90);
+/// The caret blinks after that long without a touch.
+const BLINK_AFTER: Duration = Duration::from_secs(1);
+/// How long the caret stays shown, then hidden, when blinking.
+const BLINK_PERIOD: Duration = Duration::from_millis(530);
+
+/// The box of a touch drawn in the practice, `y` is the baseline.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Place {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) width: f64,
+    pub(crate) ascent: f64,
+    pub(crate) descent: f64,
+}
+
+/// The caret, sliding from a place to the next and blinking while no touch
+/// is typed.
+pub(crate) struct Caret {
+    from: Place,
+    to: Place,
+    moved: Instant,
+    touched: Instant,
+    /// Whether it was shown when last drawn.
+    shown: bool,
+}
+
+impl Place {
+    fn lerp(&self, to: &Place, t: f64) -> Place {
+        let mix = |a: f64, b: f64| a + (b - a) * t;
+        Place {
+            x: mix(self.x, to.x),
+            y: mix(self.y, to.y),
+            width: mix(self.width, to.width),
+            ascent: mix(self.ascent, to.ascent),
+            descent: mix(self.descent, to.descent),
+        }
+    }
+}
+
+impl Caret {
+    pub(crate) fn new(now: Instant) -> Caret {
+        Caret {
+            from: Place::default(),
+            to: Place::default(),
+            moved: now,
+            touched: now,
+            shown: true,
+        }
+    }
+
+    /// Moves the caret to `place`, sliding from where it is if `smooth`.
+    pub(crate) fn move_to(&mut self, place: Place, smooth: bool, now: Instant) {
+        if place == self.to {
+            return;
+        }
+        // the first place is not slid to
+        self.from = if smooth && self.to != Place::default() {
+            self.position(now)
+        } else {
+            place
+        };
+        self.to = place;
+        self.moved = now;
+    }
+
+    /// A touch was typed at `now`, the caret stops blinking.
+    pub(crate) fn touch(&mut self, now: Instant) {
+        self.touched = now;
+    }
+
+    fn position(&self, now: Instant) -> Place {
+        let t = (now.saturating_duration_since(self.moved).as_secs_f64() / SLIDE.as_secs_f64())
+            .min(1.0);
+        // slows down when arriving
+        self.from.lerp(&self.to, 1.0 - (1.0 - t).powi(3))
+    }
+
+    fn is_sliding(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.moved) < SLIDE
+    }
+
+    fn is_shown(&self, now: Instant, blink: bool) -> bool {
+        let idle = now.saturating_duration_since(self.touched);
+        if !blink || idle < BLINK_AFTER {
+            return true;
+        }
+        let phase = (idle - BLINK_AFTER).as_millis() / BLINK_PERIOD.as_millis();
+        phase % 2 == 1
+    }
+
+    /// Returns where to draw the caret at `now`, None while a blink hides
+    /// it.
+    pub(crate) fn place(&mut self, now: Instant, blink: bool) -> Option<Place> {
+        self.shown = self.is_shown(now, blink);
+        self.shown.then(|| self.position(now))
+    }
+
+    /// Returns when the caret stops sliding, if it slides at `now`.
+    pub(crate) fn sliding_until(&self, now: Instant) -> Option<Instant> {
+        (self.from != self.to && self.is_sliding(now)).then_some(self.moved + SLIDE)
+    }
+
+    /// Returns how long until the caret blinks next, if it does.
+    pub(crate) fn next_blink(&self, now: Instant, blink: bool) -> Option<Duration> {
+        if !blink {
+            return None;
+        }
+        let idle = now.saturating_duration_since(self.touched);
+        if idle < BLINK_AFTER {
+            return Some(BLINK_AFTER - idle);
+        }
+        let phase = (idle - BLINK_AFTER).as_millis() % BLINK_PERIOD.as_millis();
+        Some(BLINK_PERIOD - Duration::from_millis(phase as u64))
+    }
+
+    /// Returns whether the caret looks different at `now` than when it was
+    /// last drawn.
+    pub(crate) fn changed(&self, now: Instant, blink: bool) -> bool {
+        self.is_sliding(now) || self.shown != self.is_shown(now, blink)
+    }
+}
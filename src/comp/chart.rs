@@ -0,0 +1,240 @@
+use gtk::prelude::*;
+use relm4::gtk;
+use relm4::{drawing::DrawHandler, ComponentParts, ComponentSender, SimpleComponent};
+
+use crate::theme::{Color, Theme};
+use crate::utils::Themed;
+
+const MARGIN: f64 = 30.0;
+const MARKER_SIZE: f64 = 4.0;
+
+/// Values plotted as a line, one per point of the chart.
+#[derive(Debug, Clone)]
+pub(crate) struct Series {
+    pub(crate) name: &'static str,
+    pub(crate) unit: &'static str,
+    pub(crate) color: Color,
+    pub(crate) values: Vec<f32>,
+    /// Top of the scale, the largest value if None.
+    pub(crate) max: Option<f32>,
+}
+
+/// Equally spaced points, each with a label and a value per series.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Chart {
+    /// Names each point in the tooltip.
+    pub(crate) labels: Vec<String>,
+    pub(crate) series: Vec<Series>,
+    /// Positions of the events drawn as crosses, in points: `1.5` is
+    /// halfway between the second and the third point.
+    pub(crate) markers: Vec<f32>,
+    /// What a marker means in the tooltip.
+    pub(crate) marker_name: &'static str,
+}
+
+#[derive(Debug)]
+pub(crate) enum ChartMsg {
+    Plot(Chart),
+    Theme(Box<Theme>),
+    /// The pointer moved to this horizontal position.
+    Hover(f64),
+    Leave,
+    Redraw,
+}
+
+pub(crate) struct ChartComp {
+    chart: Chart,
+    /// The point under the pointer.
+    hover: Option<usize>,
+    theme: Theme,
+    handler: DrawHandler,
+}
+
+impl Series {
+    fn top(&self) -> f32 {
+        self.max
+            .unwrap_or_else(|| self.values.iter().copied().fold(0.0, f32::max))
+    }
+}
+
+impl ChartComp {
+    fn len(&self) -> usize {
+        self.chart.labels.len()
+    }
+
+    fn plot_size(&self) -> (f64, f64) {
+        let area = self.handler.drawing_area();
+        (
+            area.width() as f64 - 2.0 * MARGIN,
+            area.height() as f64 - 2.0 * MARGIN,
+        )
+    }
+
+    /// Returns the horizontal position of the point `i`.
+    fn x(&self, i: f32) -> f64 {
+        let (width, _) = self.plot_size();
+        MARGIN + width * i as f64 / (self.len().max(2) - 1) as f64
+    }
+
+    /// Returns the point nearest to the horizontal position `x`.
+    fn point_at(&self, x: f64) -> Option<usize> {
+        let (width, _) = self.plot_size();
+        if self.len() == 0 || width <= 0.0 {
+            return None;
+        }
+        let i = ((x - MARGIN) / width * (self.len().max(2) - 1) as f64).round();
+        Some((i.max(0.0) as usize).min(self.len() - 1))
+    }
+
+    fn tooltip(&self, i: usize) -> String {
+        let mut lines = vec![self.chart.labels[i].clone()];
+        for s in &self.chart.series {
+            if let Some(v) = s.values.get(i) {
+                lines.push(format!("{}: {:.1}{}", s.name, v, s.unit));
+            }
+        }
+        let markers = self
+            .chart
+            .markers
+            .iter()
+            .filter(|m| m.floor() as usize == i)
+            .count();
+        if markers > 0 {
+            lines.push(format!("{} {}", markers, self.chart.marker_name));
+        }
+        lines.join("\n")
+    }
+
+    fn draw(&mut self) {
+        let cx = self.handler.get_context();
+        let theme = self.theme.chart();
+        cx.set_color(&self.theme.background);
+        cx.paint().expect("paint the background");
+        let (width, height) = self.plot_size();
+        if self.len() < 2 || width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        let bottom = MARGIN + height;
+
+        // axes
+        cx.set_color(&theme.axes);
+        cx.set_line_width(1.0);
+        cx.move_to(MARGIN, MARGIN);
+        cx.line_to(MARGIN, bottom);
+        cx.line_to(MARGIN + width, bottom);
+        cx.stroke().expect("draw the axes");
+
+        // scale of each series, on the left then on the right
+        cx.set_font_size(10.0);
+        for (s, x) in self.chart.series.iter().zip([2.0, MARGIN + width + 2.0]) {
+            cx.set_color(&s.color);
+            cx.move_to(x, MARGIN - 5.0);
+            cx.show_text(format!("{:.0}{}", s.top(), s.unit).as_str())
+                .expect("draw the scale");
+        }
+
+        if let Some(i) = self.hover {
+            cx.set_color(&theme.axes);
+            cx.set_dash(&[2.0, 2.0], 0.0);
+            cx.move_to(self.x(i as f32), MARGIN);
+            cx.line_to(self.x(i as f32), bottom);
+            cx.stroke().expect("draw the hovered point");
+            cx.set_dash(&[], 0.0);
+        }
+
+        cx.set_line_width(2.0);
+        for s in &self.chart.series {
+            let top = s.top() as f64;
+            if top <= 0.0 {
+                continue;
+            }
+            let y = |v: f32| bottom - height * v as f64 / top;
+            cx.set_color(&s.color);
+            for (i, v) in s.values.iter().enumerate() {
+                if i == 0 {
+                    cx.move_to(self.x(0.0), y(*v));
+                } else {
+                    cx.line_to(self.x(i as f32), y(*v));
+                }
+            }
+            cx.stroke().expect("draw the series");
+        }
+
+        cx.set_color(&theme.errors);
+        cx.set_line_width(1.5);
+        for m in &self.chart.markers {
+            let x = self.x(*m);
+            let y = bottom - MARKER_SIZE;
+            cx.move_to(x - MARKER_SIZE, y - MARKER_SIZE);
+            cx.line_to(x + MARKER_SIZE, y + MARKER_SIZE);
+            cx.move_to(x - MARKER_SIZE, y + MARKER_SIZE);
+            cx.line_to(x + MARKER_SIZE, y - MARKER_SIZE);
+        }
+        cx.stroke().expect("draw the markers");
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for ChartComp {
+    type Init = ();
+    type Input = ChartMsg;
+    type Output = ();
+
+    view! {
+        gtk::Box {
+            #[local_ref]
+            area -> gtk::DrawingArea {
+                set_vexpand: true,
+                set_hexpand: true,
+                set_has_tooltip: true,
+                connect_resize[sender] => move |_, _, _| sender.input(ChartMsg::Redraw),
+                add_controller = gtk::EventControllerMotion {
+                    connect_motion[sender] => move |_, x, _| sender.input(ChartMsg::Hover(x)),
+                    connect_leave[sender] => move |_| sender.input(ChartMsg::Leave),
+                },
+            },
+        }
+    }
+
+    fn init(
+        _init: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = ChartComp {
+            chart: Chart::default(),
+            hover: None,
+            theme: Theme::default(),
+            handler: DrawHandler::new(),
+        };
+        let area = model.handler.drawing_area();
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        match message {
+            ChartMsg::Plot(chart) => {
+                self.chart = chart;
+                self.hover = None;
+            }
+            ChartMsg::Theme(theme) => self.theme = *theme,
+            ChartMsg::Hover(x) => {
+                let hover = self.point_at(x);
+                if hover == self.hover {
+                    return;
+                }
+                self.hover = hover;
+                let text = hover.map(|i| self.tooltip(i));
+                self.handler
+                    .drawing_area()
+                    .set_tooltip_text(text.as_deref());
+            }
+            ChartMsg::Leave => self.hover = None,
+            ChartMsg::Redraw => (),
+        }
+        self.draw();
+    }
+}
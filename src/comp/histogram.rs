@@ -0,0 +1,136 @@
+use gtk::prelude::*;
+use relm4::gtk;
+use relm4::{drawing::DrawHandler, ComponentParts, ComponentSender, SimpleComponent};
+use std::time::Duration;
+
+use crate::analysis::dwell::Histogram;
+use crate::utils::{Clear, HasDrawHandler};
+
+const MARGIN: f64 = 30.0;
+const TITLE_Y: f64 = 16.0;
+const TOP: f64 = 26.0;
+/// Below the bars, for the durations.
+const AXIS_HEIGHT: f64 = 20.0;
+/// A duration is written under the bars every this many milliseconds.
+const TICK: u128 = 100;
+
+#[derive(Debug)]
+pub(crate) enum HistogramMsg {
+    Show(Histogram),
+    Redraw,
+}
+
+/// Durations as bars, one per bucket, with a mark at a threshold.
+pub(crate) struct HistogramComp {
+    title: &'static str,
+    /// Durations longer are drawn in orange.
+    threshold: Option<Duration>,
+    histogram: Histogram,
+    handler: DrawHandler,
+}
+
+impl<'a> HasDrawHandler<'a> for HistogramComp {
+    fn draw_handler_mut(&'a mut self) -> &'a mut DrawHandler {
+        &mut self.handler
+    }
+}
+
+impl HistogramComp {
+    fn draw(&mut self) {
+        let cx = self.handler.get_context();
+        self.clear();
+        let area = self.handler.drawing_area();
+        let width = area.width() as f64 - 2.0 * MARGIN;
+        let height = area.height() as f64 - TOP - AXIS_HEIGHT;
+        let buckets = self.histogram.counts.len();
+        if width <= 0.0 || height <= 0.0 || buckets == 0 {
+            return;
+        }
+        cx.select_font_face(
+            "Sans",
+            gtk::cairo::FontSlant::Normal,
+            gtk::cairo::FontWeight::Normal,
+        );
+        cx.set_font_size(12.0);
+        cx.set_source_rgb(0.0, 0.0, 0.0);
+        cx.move_to(MARGIN, TITLE_Y);
+        let total = self.histogram.total();
+        cx.show_text(format!("{} ({})", self.title, total).as_str())
+            .expect("draw the title");
+
+        let bar = width / buckets as f64;
+        let bucket = self.histogram.bucket;
+        let max = self.histogram.max().max(1) as f64;
+        let bottom = TOP + height;
+        for (i, count) in self.histogram.counts.iter().enumerate() {
+            let x = MARGIN + bar * i as f64;
+            let from = bucket * i as u32;
+            match self.threshold {
+                Some(t) if from >= t => cx.set_source_rgb(0.96, 0.65, 0.14),
+                _ => cx.set_source_rgb(0.2, 0.6, 0.86),
+            }
+            let h = height * *count as f64 / max;
+            cx.rectangle(x + 1.0, bottom - h, bar - 2.0, h);
+            cx.fill().expect("draw a bar");
+
+            if from.as_millis() % TICK == 0 {
+                cx.set_source_rgb(0.4, 0.4, 0.4);
+                cx.move_to(x, bottom + AXIS_HEIGHT - 4.0);
+                cx.show_text(format!("{}", from.as_millis()).as_str())
+                    .expect("draw a duration");
+            }
+        }
+        cx.set_source_rgb(0.4, 0.4, 0.4);
+        cx.move_to(MARGIN + width - 20.0, bottom + AXIS_HEIGHT - 4.0);
+        cx.show_text("ms").expect("draw the unit");
+        cx.set_line_width(1.0);
+        cx.move_to(MARGIN, bottom + 0.5);
+        cx.line_to(MARGIN + width, bottom + 0.5);
+        cx.stroke().expect("draw the axis");
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for HistogramComp {
+    /// The title, and the threshold over which durations are marked.
+    type Init = (&'static str, Option<Duration>);
+    type Input = HistogramMsg;
+    type Output = ();
+
+    view! {
+        gtk::Box {
+            #[local_ref]
+            area -> gtk::DrawingArea {
+                set_hexpand: true,
+                set_content_height: 160,
+                connect_resize[sender] => move |_, _, _| sender.input(HistogramMsg::Redraw),
+            },
+        }
+    }
+
+    fn init(
+        (title, threshold): Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = HistogramComp {
+            title,
+            threshold,
+            histogram: Histogram::default(),
+            handler: DrawHandler::new(),
+        };
+        let area = model.handler.drawing_area();
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        match message {
+            HistogramMsg::Show(histogram) => self.histogram = histogram,
+            HistogramMsg::Redraw => (),
+        }
+        self.draw();
+    }
+}
@@ -1,107 +1,57 @@
 use gtk::prelude::*;
 use relm4::gtk;
 use relm4::gtk::gdk::Key;
-use relm4::prelude::*;
 use relm4::{drawing::DrawHandler, ComponentParts, ComponentSender, SimpleComponent};
 
+use crate::layout::Layout;
+use crate::model::Touch;
 use crate::msg::Msg;
+use crate::theme::Theme;
+use crate::utils::Themed;
 
 const UNIT: f64 = 30.0;
-// TODO should be parsed from some resource files
-const QWERTY: &'static [(&str, f64)] = &[
-    ("`", 1.0),
-    ("1", 1.0),
-    ("2", 1.0),
-    ("3", 1.0),
-    ("4", 1.0),
-    ("5", 1.0),
-    ("6", 1.0),
-    ("7", 1.0),
-    ("8", 1.0),
-    ("9", 1.0),
-    ("0", 1.0),
-    ("-", 1.0),
-    ("=", 1.0),
-    ("del", 1.5), // line 1
-    ("tab", 1.5),
-    ("Q", 1.0),
-    ("W", 1.0),
-    ("E", 1.0),
-    ("R", 1.0),
-    ("T", 1.0),
-    ("Y", 1.0),
-    ("U", 1.0),
-    ("I", 1.0),
-    ("O", 1.0),
-    ("P", 1.0),
-    ("[", 1.0),
-    ("]", 1.0),
-    ("\\", 1.0), // line 2
-    ("caps", 2.0),
-    ("A", 1.0),
-    ("S", 1.0),
-    ("D", 1.0),
-    ("F", 1.0),
-    ("G", 1.0),
-    ("H", 1.0),
-    ("J", 1.0),
-    ("K", 1.0),
-    ("L", 1.0),
-    (";", 1.0),
-    ("'", 1.0),
-    ("enter", 2.0), // line 3
-    ("shift", 3.0),
-    ("Z", 1.0),
-    ("X", 1.0),
-    ("C", 1.0),
-    ("V", 1.0),
-    ("B", 1.0),
-    ("N", 1.0),
-    ("M", 1.0),
-    (",", 1.0),
-    (".", 1.0),
-    ("/", 1.0),
-    ("shift", 3.0), // line 4
-    ("space", 10.0),
-];
-const LAYOUT: &'static [usize] = &[14, 14, 13, 12, 1];
 const HSTART: f64 = 100.0;
 const VSTART: f64 = 100.0;
 
 pub(crate) struct KeyboardComp {
+    layout: Layout,
     handler: DrawHandler,
+    theme: Theme,
 }
 
 impl KeyboardComp {
-    fn draw(&mut self, k: Option<Key>) {
+    /// Draws the layout, highlighting the key typing `touch`, or the key
+    /// named like `k` when it types nothing.
+    fn draw(&mut self, k: Option<(Key, Touch)>) {
         let cx = self.handler.get_context();
-        cx.select_font_face(
-            "Arial Black",
-            gtk::cairo::FontSlant::Normal,
-            gtk::cairo::FontWeight::Bold,
-        );
-
-        cx.set_source_rgb(0.0, 0.0, 0.0);
-        cx.set_font_size(18.0);
-        let mut x = VSTART;
+        let theme = &self.theme.keyboard;
+        cx.set_color(&theme.background);
+        cx.paint().expect("paint the background");
+        cx.set_font(&theme.font);
         let mut y = HSTART;
-        let mut iter = QWERTY.iter();
-        for row in LAYOUT {
-            for _ in 0..*row {
-                cx.set_source_rgb(0.0, 0.0, 0.0);
-                if let Some((cell, size)) = iter.next() {
-                    match k.and_then(|k| k.name()) {
-                        Some(x) if x.eq_ignore_ascii_case(*cell) => {
-                            cx.set_source_rgb(0.0, 1.0, 0.0)
-                        }
-                        _ => (),
-                    };
-                    cx.move_to(x, y);
-                    cx.show_text(cell).expect("should display this char");
-                    x += UNIT * size;
+        for row in &self.layout.rows {
+            let mut x = VSTART;
+            for key in row {
+                x += UNIT * key.gap;
+                let pressed = k.is_some_and(|(k, touch)| {
+                    key.types(&touch)
+                        || k.name().is_some_and(|n| n.eq_ignore_ascii_case(&key.label))
+                });
+                if let Some(color) = self.theme.finger(key.finger) {
+                    cx.set_color(&color);
+                    // behind the label, whose baseline is at y
+                    cx.rectangle(x - 4.0, y - UNIT * 0.75, UNIT * key.width - 2.0, UNIT - 2.0);
+                    cx.fill().expect("draw the finger colour");
+                }
+                if pressed {
+                    cx.set_color(&theme.pressed);
+                } else {
+                    cx.set_color(&theme.label);
                 }
+                cx.move_to(x, y);
+                cx.show_text(&key.label).expect("should display this char");
+                x += UNIT * key.width;
             }
-            x = VSTART;
             y += UNIT;
         }
     }
@@ -109,7 +59,7 @@ impl KeyboardComp {
 
 #[relm4::component(pub)]
 impl SimpleComponent for KeyboardComp {
-    type Init = ();
+    type Init = Layout;
     type Input = Msg;
     type Output = ();
 
@@ -119,19 +69,22 @@ impl SimpleComponent for KeyboardComp {
                 area -> gtk::DrawingArea {
                     set_vexpand: true,
                     set_hexpand: true,
-                    inline_css: "border: 2px solid blue",
                 },
             },
     }
 
     fn init(
-        _init: Self::Init,
+        layout: Self::Init,
         root: &Self::Root,
         _sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let handler = DrawHandler::new();
 
-        let model = KeyboardComp { handler };
+        let model = KeyboardComp {
+            layout,
+            handler,
+            theme: Theme::default(),
+        };
         let area = model.handler.drawing_area();
 
         let widgets = view_output!();
@@ -141,7 +94,11 @@ impl SimpleComponent for KeyboardComp {
 
     fn update(&mut self, _message: Self::Input, _sender: ComponentSender<Self>) {
         match _message {
-            Msg::KeyPressed(k, _, _, _) => self.draw(Some(k)),
+            Msg::KeyPressed(k, touch, _, _, _) => self.draw(Some((k, touch))),
+            Msg::Theme(theme) => {
+                self.theme = *theme;
+                self.draw(None);
+            }
             _ => self.draw(None),
         };
     }
@@ -1,3 +1,12 @@
+pub mod caret;
+pub mod chart;
+pub mod histogram;
 pub mod keyboard;
 pub mod practice;
+pub mod replay;
+pub mod results;
+pub mod settings;
+pub mod statistics;
 pub mod stats;
+pub mod text;
+pub mod timeline;
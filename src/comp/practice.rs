@@ -1,123 +1,394 @@
 use gtk::prelude::*;
 use relm4::gtk;
-use relm4::prelude::*;
 use relm4::{drawing::DrawHandler, ComponentParts, ComponentSender, SimpleComponent};
+use std::cell::Cell;
+use std::ops::Range;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
+use crate::comp::caret::{Caret, Place};
+use crate::comp::text::TextLayout;
+use crate::driver::Driver;
+use crate::flow::Event;
+use crate::ghost::Ghost;
+use crate::latency::{Latency, LatencyStats};
 use crate::model::Practice;
-use crate::model::Touch;
-use crate::model::TouchState;
 use crate::msg::Msg;
-use crate::run_enumerate::run_enumerate_with;
-use crate::utils::{Clear, HasDrawHandler};
+use crate::settings::{CaretStyle, Settings};
+use crate::theme::Theme;
+use crate::utils::{event_age, font_description, foreground, Themed};
 
-const UNIT: f64 = 30.0;
 const WORDS_PER_LINE: usize = 5;
-// TODO should be parsed from some resource files
+/// Lines displayed at once, the text scrolls to keep the cursor in view.
+const VISIBLE_LINES: usize = 8;
 const HSTART: f64 = 100.0;
 const VSTART: f64 = 100.0;
+/// How often the ghost caret moves.
+const GHOST_TICK: Duration = Duration::from_millis(50);
+/// Repainted around a touch typed, for the glyphs overflowing their box.
+const GLYPH_MARGIN: f64 = 2.0;
+/// Repainted around a caret, for the initials above the opponents.
+const MARK_MARGIN: f64 = 12.0;
+/// The latency overlay shows the median of that many keystrokes.
+const LATENCY_SAMPLES: usize = 50;
+/// Height of the latency overlay on top.
+const OVERLAY_HEIGHT: f64 = 16.0;
+
+/// A rectangle as x, y, width and height.
+type Rect = (f64, f64, f64, f64);
+
+/// What the last frame drew, to repaint only what changed since.
+struct Frame {
+    /// Size of the surface drawn on, a new one is blank.
+    size: (i32, i32),
+    lines: Range<usize>,
+    cursor: usize,
+    /// Where the carets were drawn, to erase them.
+    marks: Vec<Place>,
+}
 
 pub(crate) struct PracticeComp {
-    practice: Practice,
+    driver: Driver,
     handler: DrawHandler,
-    saved: bool,
+    /// The previous session raced, drawn as a second caret.
+    ghost: Option<Ghost>,
+    /// Redraws the ghost while the practice goes on.
+    ticker: Option<gtk::glib::SourceId>,
+    /// Name and position of the other players, when racing.
+    opponents: Vec<(String, usize)>,
+    theme: Theme,
+    settings: Settings,
+    caret: Caret,
+    /// The challenge laid out in lines.
+    text: TextLayout,
+    /// The last frame drawn, None to draw everything again.
+    frame: Option<Frame>,
+    /// The keystrokes drawn but not painted yet, with the time of their
+    /// key event.
+    measuring: Vec<(u32, Latency)>,
+    /// Tells the frame clock to report the next paint.
+    painting: Rc<Cell<bool>>,
+    /// Until when the caret is redrawn on every frame, while it slides.
+    /// Emptied once the frames stop.
+    sliding: Rc<Cell<Option<Instant>>>,
+    /// Redraws the caret when it blinks next, emptied once it fired.
+    blinker: Rc<Cell<Option<gtk::glib::SourceId>>>,
 }
 
-impl<'a> HasDrawHandler<'a> for PracticeComp {
-    fn draw_handler_mut(&'a mut self) -> &'a mut DrawHandler {
-        &mut self.handler
+impl PracticeComp {
+    /// Lays out the challenge again, when it or the font changed.
+    fn layout(&mut self) {
+        let font = match &self.settings.font {
+            Some(font) => gtk::pango::FontDescription::from_string(font),
+            None => font_description(&self.theme.font),
+        };
+        self.text = TextLayout::new(
+            self.handler.drawing_area(),
+            self.driver.practice().challenge(),
+            &font,
+            WORDS_PER_LINE,
+        );
+        self.frame = None;
     }
-}
 
-impl PracticeComp {
-    fn draw(&mut self, t: Option<Touch>) {
+    /// Draws the practice. Only the regions that changed since the last
+    /// frame are repainted, unless the text scrolled or was laid out again.
+    /// This saves the drawing on the offscreen surface only: the drawing
+    /// handler still copies the whole surface to the screen every frame.
+    fn draw(&mut self) {
+        let now = Instant::now();
         let cx = self.handler.get_context();
+        let size = (self.handler.width(), self.handler.height());
+        let theme = &self.theme;
+        let text = &self.text;
 
-        // TODO we can also clear just one rectangle
-        self.clear();
-        cx.select_font_face(
-            "Courier New",
-            gtk::cairo::FontSlant::Normal,
-            gtk::cairo::FontWeight::Normal,
-        );
-        cx.set_source_rgb(0.0, 0.0, 0.0);
-        cx.set_font_size(10.0);
-        cx.move_to(10.0, 10.0);
-        let debug_text = format!("{:?}", self.practice);
-        cx.show_text(debug_text.as_str())
-            .expect("display some debug");
-        // the real text
-        cx.select_font_face(
-            "Arial Black",
-            gtk::cairo::FontSlant::Normal,
-            gtk::cairo::FontWeight::Bold,
-        );
-        cx.set_source_rgb(0.0, 0.0, 0.0);
-        cx.set_font_size(18.0);
-
-        let mut x = VSTART;
-        let mut y = HSTART;
-        let mut cw = 0;
-        for (w, (c, state, _)) in
-            run_enumerate_with(&mut self.practice.iter(), |x| x.2 / WORDS_PER_LINE)
-        {
-            // if the current word has changed reset x and go down
-            if w != cw {
-                x = VSTART;
-                y += UNIT;
-            }
-            cw = w;
-            if c == Touch::Space {
-                x += 7.0;
-            }
-            cx.move_to(x, y);
-            // reset
-            cx.set_source_rgb(0.0, 0.0, 0.0);
-            // setup the color and any needed decoration that is function
-            // of the state of the practice for each touch
-            match state {
-                TouchState::Next => {
-                    // display an underline for the next char
-                    cx.move_to(x, y + UNIT / 5.0);
-                    cx.show_text("_").expect("underline");
-                    cx.move_to(x, y);
-                }
-                TouchState::Current(is_first) => {
-                    if is_first {
-                        cx.move_to(x, y + UNIT / 5.0);
-                        cx.show_text("_").expect("underline");
-                        cx.move_to(x, y);
-                    }
-                    if let Some(t) = t {
-                        if self.practice.check(&t) == Some(true) {
-                            cx.set_source_rgb(0.0, 1.0, 0.0);
-                        } else {
-                            cx.set_source_rgb(1.0, 0.0, 0.0);
-                        }
-                    }
+        // keep the line before the cursor in view
+        let practice = self.driver.practice();
+        let keystrokes = practice.keystrokes();
+        let cursor = keystrokes.len();
+        let cursor_line = text
+            .place(cursor)
+            .map_or(text.lines.len().saturating_sub(1), |(l, _)| l);
+        let lines =
+            cursor_line.saturating_sub(1)..(cursor_line + VISIBLE_LINES).min(text.lines.len());
+        let top = |l: usize| VSTART + (l - lines.start) as f64 * text.line_height;
+        // where a touch is drawn, if in view
+        let place = |i: usize| {
+            let (l, place) = text.place(i).filter(|(l, _)| lines.contains(l))?;
+            Some(Place {
+                x: HSTART + place.x,
+                y: top(l) + place.y,
+                ..place
+            })
+        };
+
+        // the caret is on the next touch to type
+        if let Some(place) = place(cursor) {
+            self.caret.move_to(place, self.settings.smooth_caret, now);
+        }
+        let caret = self
+            .caret
+            .place(now, self.settings.blink)
+            .filter(|_| self.settings.caret != CaretStyle::Off);
+        let ghost = self
+            .ghost
+            .as_ref()
+            .filter(|_| practice.is_started())
+            .and_then(|g| place(g.position_at(practice.elapsed(now))));
+        let opponents: Vec<(&String, Place)> = self
+            .opponents
+            .iter()
+            .filter_map(|(name, position)| Some((name, place(*position)?)))
+            .collect();
+        let marks: Vec<Place> = caret
+            .iter()
+            .chain(&ghost)
+            .chain(opponents.iter().map(|(_, p)| p))
+            .copied()
+            .collect();
+
+        // the touches typed since the last frame, the carets where they
+        // were and where they are, and the latency overlay
+        let dirty = match &self.frame {
+            Some(frame) if frame.size == size && frame.lines == lines => {
+                let touches =
+                    frame.cursor.min(cursor).saturating_sub(1)..frame.cursor.max(cursor) + 1;
+                let mut dirty: Vec<Rect> = touches
+                    .filter_map(place)
+                    .map(|p| bounds(&p, GLYPH_MARGIN))
+                    .collect();
+                dirty.extend(
+                    frame
+                        .marks
+                        .iter()
+                        .chain(&marks)
+                        .map(|p| bounds(p, MARK_MARGIN)),
+                );
+                if self.settings.latency_overlay {
+                    dirty.push((0.0, 0.0, size.0 as f64, OVERLAY_HEIGHT));
                 }
+                Some(dirty)
+            }
+            _ => None,
+        };
+        let in_dirty = |y: f64, height: f64| {
+            dirty
+                .as_ref()
+                .is_none_or(|d| d.iter().any(|r| r.1 < y + height && y < r.1 + r.3))
+        };
 
-                TouchState::Attempted(true) => {
-                    cx.set_source_rgb(0.5, 0.5, 0.5);
+        cx.save().expect("save the clip");
+        if let Some(dirty) = &dirty {
+            for (x, y, width, height) in dirty {
+                cx.rectangle(*x, *y, *width, *height);
+            }
+            cx.clip();
+        }
+        cx.set_color(&theme.background);
+        cx.paint().expect("paint the background");
+        cx.set_font(&theme.font);
+        if self.settings.latency_overlay {
+            cx.set_color(&theme.text);
+            cx.set_font_size(10.0);
+            let latencies = practice.latencies();
+            let recent = &latencies[latencies.len().saturating_sub(LATENCY_SAMPLES)..];
+            let overlay = LatencyStats::new(recent)
+                .map_or("latency: type to measure".to_string(), |s| s.to_string());
+            let width = cx
+                .text_extents(&overlay)
+                .expect("measure the latency")
+                .width();
+            cx.move_to(size.0 as f64 - width - 10.0, 10.0);
+            cx.show_text(&overlay).expect("display the latency");
+        }
+
+        // the real text, colored by how each touch was typed
+        for l in lines.clone() {
+            if !in_dirty(top(l) - MARK_MARGIN, text.line_height + 2.0 * MARK_MARGIN) {
+                continue;
+            }
+            let line = &text.lines[l];
+            let attrs = gtk::pango::AttrList::new();
+            let typed = line.touches.start.min(cursor)..line.touches.end.min(cursor);
+            for (i, k) in typed.clone().zip(&keystrokes[typed]) {
+                let success = k.is_success();
+                let color = match (i + 1 == cursor, success) {
+                    (true, true) => &theme.correct,
+                    (true, false) => &theme.wrong,
+                    (false, true) => &theme.typed,
+                    (false, false) => &theme.mistyped,
+                };
+                let Some(bytes) = line.bytes(i) else {
+                    continue;
+                };
+                let mut attr = foreground(color);
+                attr.set_start_index(bytes.start);
+                attr.set_end_index(bytes.end);
+                attrs.insert(attr);
+            }
+            line.layout.set_attributes(Some(&attrs));
+            cx.set_color(&theme.text);
+            cx.move_to(HSTART, top(l));
+            cx.show_layout(&line.layout);
+        }
+
+        if let Some(place) = &ghost {
+            cx.set_color(&theme.ghost);
+            bar(&cx, place);
+        }
+        for (name, place) in &opponents {
+            cx.set_color(&theme.opponent);
+            bar(&cx, place);
+            // the initial above the caret tells who it is
+            cx.set_font_size(9.0);
+            cx.move_to(place.x - 3.0, place.y - place.ascent - 2.0);
+            let initial: String = name.chars().take(1).collect();
+            cx.show_text(initial.as_str()).expect("name an opponent");
+        }
+        if let Some(place) = &caret {
+            self.draw_caret(&cx, place);
+        }
+        cx.restore().expect("restore the clip");
+
+        self.frame = Some(Frame {
+            size,
+            lines,
+            cursor,
+            marks,
+        });
+    }
+
+    /// Draws the caret in the current style at `place`.
+    fn draw_caret(&self, cx: &gtk::cairo::Context, place: &Place) {
+        cx.set_color(&self.theme.caret);
+        let top = place.y - place.ascent;
+        match self.settings.caret {
+            CaretStyle::Block => {
+                let (r, g, b) = self.theme.caret.rgb();
+                cx.set_source_rgba(r, g, b, 0.35);
+                cx.rectangle(place.x, top, place.width, place.ascent + place.descent);
+            }
+            CaretStyle::Line => {
+                cx.rectangle(place.x - 1.0, top, 2.0, place.ascent + place.descent);
+            }
+            CaretStyle::Underline => {
+                cx.rectangle(place.x, place.y + place.descent / 2.0, place.width, 2.0);
+            }
+            CaretStyle::Off => return,
+        }
+        cx.fill().expect("draw the caret");
+    }
+}
+
+/// Returns the rectangle around `place`, `margin` wider on each side.
+fn bounds(place: &Place, margin: f64) -> Rect {
+    (
+        place.x - margin,
+        place.y - place.ascent - margin,
+        place.width + 2.0 * margin,
+        place.ascent + place.descent + 2.0 * margin,
+    )
+}
+
+/// Draws a thin caret before the touch at `place`, for the ghost and the
+/// opponents.
+fn bar(cx: &gtk::cairo::Context, place: &Place) {
+    cx.rectangle(
+        place.x - 1.0,
+        place.y - place.ascent,
+        2.0,
+        place.ascent + place.descent,
+    );
+    cx.fill().expect("draw a caret");
+}
+
+impl PracticeComp {
+    /// Hands `event` to the driver, and the events it returns to the
+    /// application.
+    fn handle(&mut self, event: Event, sender: &ComponentSender<Self>) {
+        let events = self
+            .driver
+            .handle(event)
+            .expect("handle the practice event");
+        for event in events {
+            match event {
+                Event::Grow(missing) => sender
+                    .output(Msg::PracticeGrow(missing))
+                    .expect("should output Grow event"),
+                Event::End(practice) => {
+                    sender
+                        .output(Msg::PracticeEnd(practice))
+                        .expect("should output End event");
+                    self.stop_ghost();
                 }
-                TouchState::Attempted(false) => {
-                    cx.set_source_rgb(0.8, 0.5, 0.5);
+                Event::Next => sender
+                    .output(Msg::PracticeNext)
+                    .expect("should output Next event"),
+                Event::Start(practice) => sender
+                    .output(Msg::PracticeStart(practice))
+                    .expect("should output Start event"),
+                Event::Wake(after) => {
+                    let sender = sender.clone();
+                    let name = self.driver.practice().name().to_string();
+                    gtk::glib::timeout_add_local_once(after, move || {
+                        sender.input(Msg::PracticeTimeUp(name))
+                    });
                 }
-                TouchState::Future => {}
+                _ => (),
             }
-            // draws the char itself
-            match c {
-                Touch::Space => {
-                    cx.show_text(".").expect("print the char");
-                    x += cx.text_extents(".").unwrap().x_advance() + 7.0;
-                }
-                Touch::Char(c) => {
-                    let text = c.to_string();
+        }
+    }
 
-                    cx.show_text(text.as_str()).expect("prints the char");
-                    x += cx.text_extents(text.as_str()).unwrap().x_advance(); // + char_adjust_width(c);
-                }
+    /// Redraws the caret on every frame while it slides, and when it
+    /// blinks. Nothing runs while the caret stays still.
+    fn animate(&self, sender: &ComponentSender<Self>) {
+        let now = Instant::now();
+        if let Some(until) = self.caret.sliding_until(now) {
+            if self.sliding.replace(Some(until)).is_none() {
+                let sliding = self.sliding.clone();
+                let sender = sender.clone();
+                self.handler.drawing_area().add_tick_callback(move |_, _| {
+                    sender.input(Msg::CaretFrame);
+                    let slides = sliding.get().is_some_and(|until| Instant::now() < until);
+                    if !slides {
+                        sliding.set(None);
+                    }
+                    gtk::glib::Continue(slides)
+                });
             }
         }
+        if let Some(id) = self.blinker.take() {
+            id.remove();
+        }
+        if let Some(after) = self.caret.next_blink(now, self.settings.blink) {
+            let blinker = self.blinker.clone();
+            let sender = sender.clone();
+            let id = gtk::glib::timeout_add_local_once(after, move || {
+                // a timer that fired cannot be removed
+                blinker.take();
+                sender.input(Msg::CaretFrame);
+            });
+            self.blinker.set(Some(id));
+        }
+    }
+
+    /// Redraws regularly to move the ghost, from the first keystroke.
+    fn start_ghost(&mut self, sender: &ComponentSender<Self>) {
+        if self.ghost.is_none() {
+            return;
+        }
+        let sender = sender.clone();
+        let name = self.driver.practice().name().to_string();
+        self.ticker = Some(gtk::glib::timeout_add_local(GHOST_TICK, move || {
+            sender.input(Msg::GhostTick(name.clone()));
+            gtk::glib::Continue(true)
+        }));
+    }
+
+    fn stop_ghost(&mut self) {
+        if let Some(ticker) = self.ticker.take() {
+            ticker.remove();
+        }
     }
 }
 
@@ -132,49 +403,143 @@ impl SimpleComponent for PracticeComp {
                 area -> gtk::DrawingArea {
                     set_vexpand: true,
                     set_hexpand: true,
-                    inline_css: "border: 2px solid red",
                 },
             }
     }
     fn init(
         practice: Self::Init,
         root: &Self::Root,
-        _sender: ComponentSender<Self>,
+        sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let handler = DrawHandler::new();
+        let theme = Theme::default();
+        let text = TextLayout::new(
+            handler.drawing_area(),
+            practice.challenge(),
+            &font_description(&theme.font),
+            WORDS_PER_LINE,
+        );
 
         let model = PracticeComp {
-            practice,
+            driver: Driver::new(practice),
             handler,
-            saved: false,
+            ghost: None,
+            ticker: None,
+            opponents: vec![],
+            theme,
+            settings: Settings::default(),
+            caret: Caret::new(Instant::now()),
+            text,
+            frame: None,
+            measuring: vec![],
+            painting: Rc::new(Cell::new(false)),
+            sliding: Rc::new(Cell::new(None)),
+            blinker: Rc::new(Cell::new(None)),
         };
         let area = model.handler.drawing_area();
 
         let widgets = view_output!();
+        // the frame clock exists once the area is realized
+        let painting = model.painting.clone();
+        area.connect_realize(move |area| {
+            let Some(clock) = area.frame_clock() else {
+                return;
+            };
+            let painting = painting.clone();
+            let sender = sender.clone();
+            clock.connect_after_paint(move |_| {
+                if painting.replace(false) {
+                    let now = (gtk::glib::monotonic_time() / 1000) as u32;
+                    sender.input(Msg::Painted(now));
+                }
+            });
+        });
 
         ComponentParts { model, widgets }
     }
 
     fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
-            Msg::KeyPressed(_, t, _, _) if !self.saved => {
-                self.draw(Some(t));
-                if self.practice.press(&t).is_none() {
-                    let p = self.practice.clone();
-                    println!("practice saved to {}", p.name());
-                    sender
-                        .output(Msg::PracticeEnd(p))
-                        .expect("should output End event");
-                    self.saved = true;
+            Msg::KeyPressed(_, t, _, ts, time) => {
+                let handled = event_age(time);
+                let at = self.driver.practice().keystrokes().len();
+                self.handle(Event::Press(t, ts), &sender);
+                if self.driver.practice().keystrokes().len() > at {
+                    self.caret.touch(ts);
+                    self.draw();
+                    if let (Some(handled), Some(drawn)) = (handled, event_age(time)) {
+                        let latency = Latency {
+                            at,
+                            handled,
+                            drawn,
+                            ..Latency::default()
+                        };
+                        self.measuring.push((time, latency));
+                        self.painting.set(true);
+                    }
+                    if at == 0 {
+                        self.start_ghost(&sender);
+                    }
                 }
             }
+            Msg::KeyReleased(t, ts) => self.handle(Event::Release(t, ts), &sender),
             Msg::PracticeStart(practice) => {
-                println!("[PracticeComp] received a new practice");
-                self.saved = false;
-                self.practice = practice;
-                self.draw(None);
+                self.stop_ghost();
+                self.handle(Event::Start(practice), &sender);
+                self.measuring.clear();
+                self.layout();
+                self.draw();
+            }
+            Msg::GhostRace(ghost) => self.ghost = ghost,
+            Msg::Theme(theme) => {
+                self.theme = *theme;
+                self.layout();
+                self.draw();
+            }
+            Msg::RaceOpponents(opponents) => {
+                self.opponents = opponents;
+                self.draw();
+            }
+            Msg::GhostTick(name)
+                if !self.driver.is_ended() && name == self.driver.practice().name() =>
+            {
+                self.draw();
+            }
+            Msg::Settings(settings) => {
+                self.settings = settings;
+                self.layout();
+                self.draw();
+            }
+            Msg::CaretFrame if self.caret.changed(Instant::now(), self.settings.blink) => {
+                self.draw();
+            }
+            Msg::Painted(now) => {
+                for (time, mut latency) in self.measuring.drain(..) {
+                    latency.painted = Duration::from_millis(now.wrapping_sub(time) as u64);
+                    self.driver.record_latency(latency);
+                }
+                if self.settings.latency_overlay {
+                    self.draw();
+                }
+            }
+            Msg::PracticeExtend(words) => {
+                self.handle(Event::Extend(words), &sender);
+                self.text.extend(
+                    self.handler.drawing_area(),
+                    self.driver.practice().challenge(),
+                );
+                // the appended touches may be in view
+                self.frame = None;
+            }
+            Msg::PracticeTimeUp(name) if name == self.driver.practice().name() => {
+                self.handle(Event::Tick(Instant::now()), &sender);
             }
+            Msg::PracticePause(ts) => self.handle(Event::Pause(ts), &sender),
+            Msg::PracticeResume(ts) => self.handle(Event::Resume(ts), &sender),
+            Msg::PracticeRetry => self.handle(Event::Retry, &sender),
+            Msg::PracticeAbandon => self.handle(Event::Abandon, &sender),
             _ => (),
         };
+        self.animate(&sender);
     }
 }
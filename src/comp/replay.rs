@@ -0,0 +1,184 @@
+use gtk::prelude::*;
+use relm4::gtk;
+use relm4::gtk::gdk::{Key, ModifierType};
+use relm4::{ComponentParts, ComponentSender, SimpleComponent};
+use std::time::Instant;
+
+use crate::model::Touch;
+use crate::msg::Msg;
+use crate::replay::Replay;
+
+/// Speeds offered, in the order of the drop down.
+const SPEEDS: &[f32] = &[0.5, 1.0, 2.0];
+const DEFAULT_SPEED: u32 = 1;
+
+#[derive(Debug)]
+pub(crate) enum ReplayMsg {
+    /// Plays if paused, pauses otherwise.
+    Toggle,
+    /// Index of the speed in `SPEEDS`.
+    Speed(u32),
+    /// Moves to the given keystroke.
+    Seek(usize),
+    /// Replays the next keystroke, if scheduled by the current generation.
+    Step(u64),
+}
+
+/// Controls to play, pause and scrub a replayed session.
+///
+/// The keystrokes are sent to the parent as `Msg::Replayed` key presses.
+pub(crate) struct ReplayComp {
+    replay: Option<Replay>,
+    /// Bumped at every pause, seek or change of speed so that the steps
+    /// already scheduled are dropped.
+    generation: u64,
+}
+
+/// Returns the key press replaying `touch`.
+fn key_pressed(touch: Touch, ts: Instant) -> Msg {
+    let (key, c) = match touch {
+        Touch::Space => (Key::space, ' '),
+        // only letters and digits are named after themselves
+        Touch::Char(c) => (Key::from_name(c.to_string()).unwrap_or(Key::VoidSymbol), c),
+    };
+    let state = if c.is_uppercase() {
+        ModifierType::SHIFT_MASK
+    } else {
+        ModifierType::empty()
+    };
+    Msg::Replayed(Box::new(Msg::KeyPressed(key, touch, state, ts, 0)))
+}
+
+impl ReplayComp {
+    fn position(&self) -> usize {
+        self.replay.as_ref().map_or(0, Replay::position)
+    }
+
+    fn len(&self) -> usize {
+        self.replay.as_ref().map_or(0, Replay::len)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.replay.as_ref().is_none_or(|r| r.paused)
+    }
+
+    /// Schedules the next keystroke unless paused.
+    fn schedule(&self, sender: &ComponentSender<Self>) {
+        let Some(delay) = self
+            .replay
+            .as_ref()
+            .filter(|r| !r.paused)
+            .and_then(Replay::delay)
+        else {
+            return;
+        };
+        let sender = sender.clone();
+        let generation = self.generation;
+        gtk::glib::timeout_add_local_once(delay, move || sender.input(ReplayMsg::Step(generation)));
+    }
+
+    /// Starts the practice again and replays at once up to `position`.
+    fn seek(&mut self, position: usize, sender: &ComponentSender<Self>) {
+        let Some(replay) = self.replay.as_mut() else {
+            return;
+        };
+        let practice = replay.practice();
+        sender
+            .output(Msg::Replayed(Box::new(Msg::PracticeStart(practice))))
+            .expect("should output Replayed event");
+        for (touch, ts) in replay.seek(position) {
+            sender
+                .output(key_pressed(touch, ts))
+                .expect("should output Replayed event");
+        }
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for ReplayComp {
+    type Init = Option<Replay>;
+    type Input = ReplayMsg;
+    type Output = Msg;
+
+    view! {
+        gtk::Box {
+            set_orientation: gtk::Orientation::Horizontal,
+            set_spacing: 10,
+            gtk::Button {
+                #[watch]
+                set_label: if model.is_paused() { "Play" } else { "Pause" },
+                // typing must not click it
+                set_focus_on_click: false,
+                set_can_focus: false,
+                connect_clicked => ReplayMsg::Toggle,
+            },
+            gtk::DropDown::from_strings(&["0.5x", "1x", "2x"]) {
+                set_selected: DEFAULT_SPEED,
+                set_can_focus: false,
+                connect_selected_notify[sender] => move |d| {
+                    sender.input(ReplayMsg::Speed(d.selected()))
+                },
+            },
+            gtk::Scale {
+                set_hexpand: true,
+                set_can_focus: false,
+                set_digits: 0,
+                set_range: (0.0, model.len().max(1) as f64),
+                #[watch]
+                set_value: model.position() as f64,
+                connect_change_value[sender] => move |_, _, value| {
+                    sender.input(ReplayMsg::Seek(value.round().max(0.0) as usize));
+                    gtk::Inhibit(false)
+                },
+            },
+            gtk::Label {
+                #[watch]
+                set_label: &format!("{}/{}", model.position(), model.len()),
+            },
+        }
+    }
+
+    fn init(
+        replay: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = ReplayComp {
+            replay,
+            generation: 0,
+        };
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+        let Some(replay) = self.replay.as_mut() else {
+            return;
+        };
+        match message {
+            ReplayMsg::Toggle => {
+                replay.paused = !replay.paused;
+                if !replay.paused && replay.is_over() {
+                    self.seek(0, &sender);
+                }
+            }
+            ReplayMsg::Speed(i) => {
+                replay.speed = SPEEDS.get(i as usize).copied().unwrap_or(1.0);
+            }
+            ReplayMsg::Seek(position) => self.seek(position, &sender),
+            ReplayMsg::Step(generation) if generation == self.generation => {
+                if let Some((touch, ts)) = replay.step() {
+                    sender
+                        .output(key_pressed(touch, ts))
+                        .expect("should output Replayed event");
+                }
+                replay.paused |= replay.is_over();
+                self.schedule(&sender);
+                return;
+            }
+            ReplayMsg::Step(_) => return,
+        }
+        self.generation += 1;
+        self.schedule(&sender);
+    }
+}
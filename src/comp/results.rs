@@ -0,0 +1,315 @@
+use gtk::prelude::*;
+use relm4::gtk;
+use relm4::prelude::*;
+use relm4::{ComponentParts, ComponentSender, SimpleComponent};
+
+use crate::comp::chart::{Chart, ChartComp, ChartMsg, Series};
+use crate::comp::timeline::{TimelineComp, TimelineMsg};
+use crate::msg::Msg;
+use crate::race::{Rank, ServerMsg};
+use crate::session::Report;
+use crate::theme::Theme;
+
+pub(crate) struct ResultsComp {
+    report: Option<Report>,
+    /// Speed over time in the session.
+    session_chart: Controller<ChartComp>,
+    /// Speed and accuracy over the days.
+    history_chart: Controller<ChartComp>,
+    /// The rhythm of the session.
+    timeline: Controller<TimelineComp>,
+    /// The final ranking, after a race.
+    ranking: Option<Vec<Rank>>,
+    theme: Theme,
+}
+
+impl ResultsComp {
+    fn title(&self) -> String {
+        match &self.report {
+            Some(report) if report.summary.incomplete => {
+                format!("{:.0} wpm (abandoned)", report.summary.wpm)
+            }
+            Some(report) => format!("{:.0} wpm", report.summary.wpm),
+            None => String::new(),
+        }
+    }
+
+    fn details(&self) -> String {
+        match &self.report {
+            Some(report) => {
+                let summary = &report.summary;
+                let mut details = format!(
+                    "accuracy {:.1}%    consistency {:.1}%    rhythm {:.0}%",
+                    summary.accuracy * 100.0,
+                    summary.consistency * 100.0,
+                    summary.rhythm.consistency * 100.0
+                );
+                if summary.idle_s > 0.0 {
+                    details += &format!("    {:.0} s idle left out", summary.idle_s);
+                }
+                if summary.suspicious {
+                    details += "    (long pause)";
+                }
+                details
+            }
+            None => String::new(),
+        }
+    }
+
+    fn comparison(&self) -> String {
+        let Some(report) = &self.report else {
+            return String::new();
+        };
+        let wpm = report.summary.wpm;
+        let best = match report.best_wpm {
+            Some(best) if wpm > best => format!("new personal best! (was {:.0} wpm)", best),
+            Some(best) => format!("personal best {:.0} wpm ({:+.0})", best, wpm - best),
+            None => "first session".to_string(),
+        };
+        let mut comparison = match report.recent_wpm {
+            Some(recent) => format!(
+                "{}    last 10 average {:.0} wpm ({:+.0})",
+                best,
+                recent,
+                wpm - recent
+            ),
+            None => best,
+        };
+        if let Some(lesson) = &report.unlocked {
+            comparison += &format!("    lesson unlocked: {}", lesson);
+        }
+        comparison
+    }
+
+    fn hesitations(&self) -> String {
+        let Some(report) = &self.report else {
+            return String::new();
+        };
+        let rhythm = &report.summary.rhythm;
+        let touches: Vec<String> = rhythm
+            .hesitant_touches()
+            .iter()
+            .map(|(t, n)| format!("{} ({})", t, n))
+            .collect();
+        if touches.is_empty() {
+            return "no hesitation".to_string();
+        }
+        let words: Vec<&str> = rhythm.hesitant_words().iter().map(|w| w.as_str()).collect();
+        format!(
+            "hesitated before {}    in {}",
+            touches.join(", "),
+            words.join(", ")
+        )
+    }
+
+    fn mistakes(&self) -> String {
+        match &self.report {
+            Some(report) if report.summary.mistakes.is_empty() => "no mistakes".to_string(),
+            Some(report) => report
+                .summary
+                .mistakes
+                .iter()
+                .map(|m| format!("{} → {}", m.word.as_str(), m.typed))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            None => String::new(),
+        }
+    }
+
+    fn ranking(&self) -> String {
+        let Some(ranks) = &self.ranking else {
+            return String::new();
+        };
+        ranks
+            .iter()
+            .enumerate()
+            .map(|(i, r)| match r.time_ms {
+                Some(ms) => format!("{}. {}  {:.1} s", i + 1, r.name, ms as f32 / 1000.0),
+                None => format!("{}. {}  (left)", i + 1, r.name),
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn has_mistakes(&self) -> bool {
+        self.report
+            .as_ref()
+            .is_some_and(|r| !r.summary.mistakes.is_empty())
+    }
+
+    fn plot(&self) {
+        let Some(report) = &self.report else {
+            return;
+        };
+        let summary = &report.summary;
+        let colors = self.theme.chart();
+        self.timeline
+            .emit(TimelineMsg::Show(summary.rhythm.clone()));
+        self.session_chart.emit(ChartMsg::Plot(Chart {
+            labels: (0..summary.speed.len())
+                .map(|i| format!("{}s", i + 1))
+                .collect(),
+            series: vec![Series {
+                name: "speed",
+                unit: " wpm",
+                color: colors.speed,
+                values: summary.speed.clone(),
+                max: None,
+            }],
+            markers: summary.errors.clone(),
+            marker_name: "errors",
+        }));
+        self.history_chart.emit(ChartMsg::Plot(Chart {
+            labels: report
+                .daily
+                .iter()
+                .map(|d| format!("{} ({} sessions)", d.date(), d.sessions))
+                .collect(),
+            series: vec![
+                Series {
+                    name: "speed",
+                    unit: " wpm",
+                    color: colors.speed,
+                    values: report.daily.iter().map(|d| d.wpm).collect(),
+                    max: None,
+                },
+                Series {
+                    name: "accuracy",
+                    unit: "%",
+                    color: colors.accuracy,
+                    values: report.daily.iter().map(|d| d.accuracy * 100.0).collect(),
+                    max: Some(100.0),
+                },
+            ],
+            markers: vec![],
+            marker_name: "",
+        }));
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for ResultsComp {
+    type Init = ();
+    type Input = Msg;
+    type Output = Msg;
+
+    view! {
+        gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+            set_spacing: 10,
+            set_margin_all: 20,
+            gtk::Label {
+                add_css_class: "title-1",
+                #[watch]
+                set_label: &model.title(),
+            },
+            gtk::Label {
+                #[watch]
+                set_label: &model.details(),
+            },
+            gtk::Label {
+                set_opacity: 0.7,
+                #[watch]
+                set_label: &model.comparison(),
+            },
+            #[local_ref]
+            timeline -> gtk::Box {},
+            gtk::Label {
+                set_opacity: 0.7,
+                #[watch]
+                set_label: &model.hesitations(),
+            },
+            gtk::Label {
+                add_css_class: "title-4",
+                #[watch]
+                set_visible: model.ranking.is_some(),
+                #[watch]
+                set_label: &model.ranking(),
+            },
+            gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_spacing: 10,
+                set_homogeneous: true,
+                set_vexpand: true,
+                #[local_ref]
+                session_chart -> gtk::Box {},
+                #[local_ref]
+                history_chart -> gtk::Box {},
+            },
+            gtk::ScrolledWindow {
+                set_min_content_height: 100,
+                gtk::Label {
+                    set_selectable: true,
+                    #[watch]
+                    set_label: &model.mistakes(),
+                },
+            },
+            gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_spacing: 10,
+                set_halign: gtk::Align::Center,
+                gtk::Button {
+                    set_label: "Retry",
+                    connect_clicked[sender] => move |_| {
+                        sender.output(Msg::PracticeRetry).expect("should output Retry event");
+                    },
+                },
+                gtk::Button {
+                    set_label: "Practise missed words",
+                    #[watch]
+                    set_sensitive: model.has_mistakes(),
+                    connect_clicked[sender] => move |_| {
+                        sender.output(Msg::PracticeMissed).expect("should output Missed event");
+                    },
+                },
+                gtk::Button {
+                    set_label: "Continue",
+                    connect_clicked[sender] => move |_| {
+                        sender.output(Msg::PracticeNext).expect("should output Next event");
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        _init: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = ResultsComp {
+            report: None,
+            session_chart: ChartComp::builder().launch(()).detach(),
+            history_chart: ChartComp::builder().launch(()).detach(),
+            timeline: TimelineComp::builder().launch(()).detach(),
+            ranking: None,
+            theme: Theme::default(),
+        };
+        let timeline = model.timeline.widget();
+        let session_chart = model.session_chart.widget();
+        let history_chart = model.history_chart.widget();
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        match message {
+            Msg::PracticeReport(report) => {
+                self.report = Some(report);
+                self.ranking = None;
+                self.plot();
+            }
+            Msg::Race(ServerMsg::Ranking { ranks }) => self.ranking = Some(ranks),
+            Msg::Theme(theme) => {
+                for chart in [&self.session_chart, &self.history_chart] {
+                    chart.emit(ChartMsg::Theme(theme.clone()));
+                }
+                self.theme = *theme;
+                self.plot();
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use gtk::prelude::*;
+use relm4::gtk;
+use relm4::prelude::*;
+use relm4::{ComponentParts, ComponentSender, SimpleComponent};
+
+use crate::msg::Msg;
+use crate::session::data_dir;
+use crate::settings::{CaretStyle, Settings};
+
+#[derive(Debug)]
+pub(crate) enum SettingsMsg {
+    /// The caret style at this index of `CaretStyle::ALL`.
+    Caret(u32),
+    SmoothCaret(bool),
+    Blink(bool),
+    /// A Pango font description, the font of the theme if None.
+    Font(Option<String>),
+    LatencyOverlay(bool),
+}
+
+/// A window to change the settings, they are saved and applied as soon as
+/// changed.
+pub(crate) struct SettingsComp {
+    settings: Settings,
+}
+
+impl SettingsComp {
+    fn caret_names() -> Vec<&'static str> {
+        CaretStyle::ALL.iter().map(CaretStyle::name).collect()
+    }
+
+    fn caret_index(&self) -> u32 {
+        CaretStyle::ALL
+            .iter()
+            .position(|s| *s == self.settings.caret)
+            .unwrap_or_default() as u32
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for SettingsComp {
+    type Init = Settings;
+    type Input = SettingsMsg;
+    type Output = Msg;
+
+    view! {
+        gtk::Window {
+            set_title: Some("Settings"),
+            set_hide_on_close: true,
+            gtk::Grid {
+                set_margin_all: 20,
+                set_column_spacing: 20,
+                set_row_spacing: 10,
+                attach[0, 0, 1, 1] = &gtk::Label {
+                    set_label: "Caret",
+                    set_xalign: 0.0,
+                },
+                attach[1, 0, 1, 1] = &gtk::DropDown::from_strings(&Self::caret_names()) {
+                    set_selected: model.caret_index(),
+                    connect_selected_notify[sender] => move |d| {
+                        sender.input(SettingsMsg::Caret(d.selected()))
+                    },
+                },
+                attach[0, 1, 1, 1] = &gtk::Label {
+                    set_label: "Slide the caret",
+                    set_xalign: 0.0,
+                },
+                attach[1, 1, 1, 1] = &gtk::Switch {
+                    set_halign: gtk::Align::End,
+                    set_active: model.settings.smooth_caret,
+                    connect_active_notify[sender] => move |s| {
+                        sender.input(SettingsMsg::SmoothCaret(s.is_active()))
+                    },
+                },
+                attach[0, 2, 1, 1] = &gtk::Label {
+                    set_label: "Blink when idle",
+                    set_xalign: 0.0,
+                },
+                attach[1, 2, 1, 1] = &gtk::Switch {
+                    set_halign: gtk::Align::End,
+                    set_active: model.settings.blink,
+                    connect_active_notify[sender] => move |s| {
+                        sender.input(SettingsMsg::Blink(s.is_active()))
+                    },
+                },
+                attach[0, 3, 1, 1] = &gtk::Label {
+                    set_label: "Font",
+                    set_xalign: 0.0,
+                },
+                attach[1, 3, 1, 1] = &gtk::Box {
+                    set_spacing: 10,
+                    gtk::FontButton {
+                        set_use_font: true,
+                        set_font: model.settings.font.as_deref().unwrap_or("Monospace 20"),
+                        connect_font_set[sender] => move |b| {
+                            sender.input(SettingsMsg::Font(b.font().map(String::from)))
+                        },
+                    },
+                    gtk::Button {
+                        set_label: "Theme font",
+                        set_tooltip_text: Some("Use the font of the theme"),
+                        #[watch]
+                        set_sensitive: model.settings.font.is_some(),
+                        connect_clicked => SettingsMsg::Font(None),
+                    },
+                },
+                attach[0, 4, 1, 1] = &gtk::Label {
+                    set_label: "Show the input latency",
+                    set_xalign: 0.0,
+                },
+                attach[1, 4, 1, 1] = &gtk::Switch {
+                    set_halign: gtk::Align::End,
+                    set_active: model.settings.latency_overlay,
+                    connect_active_notify[sender] => move |s| {
+                        sender.input(SettingsMsg::LatencyOverlay(s.is_active()))
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        settings: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = SettingsComp { settings };
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+        match message {
+            SettingsMsg::Caret(i) => {
+                let Some(caret) = CaretStyle::ALL.get(i as usize) else {
+                    return;
+                };
+                self.settings.caret = *caret;
+            }
+            SettingsMsg::SmoothCaret(smooth) => self.settings.smooth_caret = smooth,
+            SettingsMsg::Blink(blink) => self.settings.blink = blink,
+            SettingsMsg::Font(font) => self.settings.font = font,
+            SettingsMsg::LatencyOverlay(overlay) => self.settings.latency_overlay = overlay,
+        }
+        if let Err(e) = self.settings.save(data_dir().as_path()) {
+            sender
+                .output(Msg::Warning(format!("cannot save the settings: {:#}", e)))
+                .expect("should output Warning event");
+        }
+        sender
+            .output(Msg::Settings(self.settings.clone()))
+            .expect("should output Settings event");
+    }
+}
@@ -0,0 +1,405 @@
+use gtk::prelude::*;
+use relm4::gtk;
+use relm4::prelude::*;
+use relm4::{ComponentParts, ComponentSender, SimpleComponent};
+use std::time::Duration;
+
+use crate::analysis::confusion::{ConfusionMatrix, Substitution};
+use crate::analysis::dwell::{DwellStats, LONG_HOLD};
+use crate::analysis::keys::{Column, GramStats, KeyStats};
+use crate::comp::histogram::{HistogramComp, HistogramMsg};
+use crate::layout::{ErrorKind, Layout};
+use crate::session::{data_dir, History};
+
+/// Bigrams are many, only the first rows are shown.
+const MAX_ROWS: usize = 200;
+/// The keys held too long the most often that are named.
+const MAX_LONG_HOLDS: usize = 8;
+const COLUMNS: &[(Column, &str)] = &[
+    (Column::Gram, "key"),
+    (Column::Count, "count"),
+    (Column::ErrorRate, "errors"),
+    (Column::Mean, "mean"),
+    (Column::Median, "median"),
+    (Column::P90, "p90"),
+    (Column::Substitution, "typed instead"),
+];
+const KINDS: &[(ErrorKind, &str, &str)] = &[
+    (ErrorKind::Neighbour, "neighbour key", "#f5a623"),
+    (ErrorKind::SameFinger, "same finger", "#9b59b6"),
+    (ErrorKind::SameHand, "same hand", "#3498db"),
+    (ErrorKind::OtherHand, "other hand", "#e74c3c"),
+    (ErrorKind::Unknown, "not on the layout", "#95a5a6"),
+];
+
+#[derive(Debug)]
+pub(crate) enum StatisticsMsg {
+    Show(History),
+    /// Only the last session if true, otherwise all of them.
+    LastOnly(bool),
+    Bigrams(bool),
+    Sort(Column),
+    /// Writes the confusion matrix as csv.
+    Export,
+}
+
+/// A window with the statistics of each key or bigram in a table, the
+/// confusion matrix, and how long the keys are held.
+pub(crate) struct StatisticsComp {
+    history: History,
+    layout: Layout,
+    stats: KeyStats,
+    confusion: ConfusionMatrix,
+    dwell: DwellStats,
+    dwell_histogram: Controller<HistogramComp>,
+    flight_histogram: Controller<HistogramComp>,
+    last_only: bool,
+    bigrams: bool,
+    column: Column,
+    descending: bool,
+    grid: gtk::Grid,
+    matrix: gtk::Grid,
+    /// Where the matrix was exported or why it failed.
+    exported: String,
+}
+
+fn ms(d: Option<Duration>) -> String {
+    d.map(|d| format!("{} ms", d.as_millis()))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn kind(kind: ErrorKind) -> (&'static str, &'static str) {
+    KINDS
+        .iter()
+        .find(|(k, _, _)| *k == kind)
+        .map(|(_, name, color)| (*name, *color))
+        .expect("every kind has a name and a color")
+}
+
+fn legend() -> String {
+    KINDS
+        .iter()
+        .map(|(_, name, color)| format!("<span background=\"{}\">  </span> {}", color, name))
+        .collect::<Vec<String>>()
+        .join("    ")
+}
+
+fn clear(grid: &gtk::Grid) {
+    while let Some(child) = grid.first_child() {
+        grid.remove(&child);
+    }
+}
+
+fn cells(s: &GramStats) -> [String; 7] {
+    [
+        s.gram.to_string(),
+        s.count.to_string(),
+        format!("{:.1}%", s.error_rate() * 100.0),
+        ms(s.mean()),
+        ms(s.median()),
+        ms(s.p90()),
+        s.substitution()
+            .map(|(t, n)| format!("{} ({})", t, n))
+            .unwrap_or_default(),
+    ]
+}
+
+impl StatisticsComp {
+    /// Tells how many sessions are flagged for a long pause.
+    fn flagged(&self) -> String {
+        match self.history.suspicious().count() {
+            0 => String::new(),
+            1 => "1 session with a long pause".to_string(),
+            n => format!("{} sessions with a long pause", n),
+        }
+    }
+
+    /// Tells the keys held too long and how often the keys roll over.
+    fn holds(&self) -> String {
+        if self.dwell.dwell.total() == 0 {
+            return "No key release recorded yet.".to_string();
+        }
+        let rollovers = format!(
+            "Next key pressed before releasing the previous one: {:.0}%",
+            self.dwell.rollover_rate() * 100.0
+        );
+        let long_holds = self.dwell.long_holds();
+        if long_holds.is_empty() {
+            return format!(
+                "No key held longer than {} ms.\n{}",
+                LONG_HOLD.as_millis(),
+                rollovers
+            );
+        }
+        let keys = long_holds
+            .iter()
+            .take(MAX_LONG_HOLDS)
+            .map(|k| {
+                format!(
+                    "{} {:.0}% ({})",
+                    k.touch,
+                    k.long_hold_rate() * 100.0,
+                    ms(k.median())
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!(
+            "Held longer than {} ms: {}\n{}",
+            LONG_HOLD.as_millis(),
+            keys,
+            rollovers
+        )
+    }
+
+    fn compute(&mut self) {
+        (self.stats, self.confusion, self.dwell) = match self.history.sessions().last() {
+            Some(last) if self.last_only => (
+                KeyStats::from(last),
+                ConfusionMatrix::from(last),
+                DwellStats::from(last),
+            ),
+            _ => (
+                KeyStats::from(&self.history),
+                ConfusionMatrix::from(&self.history),
+                DwellStats::from(&self.history),
+            ),
+        };
+        self.fill_matrix();
+        self.dwell_histogram
+            .emit(HistogramMsg::Show(self.dwell.dwell.clone()));
+        self.flight_histogram
+            .emit(HistogramMsg::Show(self.dwell.flight.clone()));
+    }
+
+    /// Fills the confusion matrix, the color of a cell tells the kind of
+    /// error and its intensity how frequent it is.
+    fn fill_matrix(&self) {
+        clear(&self.matrix);
+        let columns = self.confusion.typed();
+        for (i, t) in columns.iter().enumerate() {
+            let label = gtk::Label::new(Some(t.to_string().as_str()));
+            label.add_css_class("dim-label");
+            self.matrix.attach(&label, i as i32 + 1, 0, 1, 1);
+        }
+        let substitutions = self.confusion.substitutions(&self.layout);
+        let max = substitutions.first().map(|s| s.count).unwrap_or(1);
+        for (r, e) in self.confusion.expected().iter().enumerate() {
+            let label = gtk::Label::new(Some(e.to_string().as_str()));
+            label.add_css_class("dim-label");
+            self.matrix.attach(&label, 0, r as i32 + 1, 1, 1);
+            for Substitution {
+                typed, count, kind, ..
+            } in substitutions.iter().filter(|s| s.expected == *e)
+            {
+                let (name, color) = self::kind(*kind);
+                let alpha = 30 + 70 * count / max;
+                let label = gtk::Label::new(None);
+                label.set_markup(
+                    format!(
+                        "<span background=\"{}\" bgalpha=\"{}%\"> {} </span>",
+                        color, alpha, count
+                    )
+                    .as_str(),
+                );
+                label.set_tooltip_text(Some(
+                    format!("{} typed as {}: {} ({})", e, typed, count, name).as_str(),
+                ));
+                let column = columns.iter().position(|t| t == typed).unwrap_or_default();
+                self.matrix
+                    .attach(&label, column as i32 + 1, r as i32 + 1, 1, 1);
+            }
+        }
+    }
+
+    /// Fills the table, the headers sort the rows when clicked.
+    fn fill(&self, sender: &ComponentSender<Self>) {
+        clear(&self.grid);
+        for (i, (column, name)) in COLUMNS.iter().enumerate() {
+            let label = match (*column == self.column, self.descending) {
+                (true, true) => format!("{} ▼", name),
+                (true, false) => format!("{} ▲", name),
+                (false, _) => name.to_string(),
+            };
+            let button = gtk::Button::with_label(label.as_str());
+            button.add_css_class("flat");
+            let sender = sender.clone();
+            let column = *column;
+            button.connect_clicked(move |_| sender.input(StatisticsMsg::Sort(column)));
+            self.grid.attach(&button, i as i32, 0, 1, 1);
+        }
+        let rows = self
+            .stats
+            .sorted(self.bigrams, self.column, self.descending);
+        for (r, s) in rows.iter().take(MAX_ROWS).enumerate() {
+            for (i, cell) in cells(s).iter().enumerate() {
+                let label = gtk::Label::new(Some(cell.as_str()));
+                label.set_xalign(if i == 0 { 0.5 } else { 1.0 });
+                self.grid.attach(&label, i as i32, r as i32 + 1, 1, 1);
+            }
+        }
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for StatisticsComp {
+    type Init = Layout;
+    type Input = StatisticsMsg;
+    type Output = ();
+
+    view! {
+        gtk::Window {
+            set_title: Some("Statistics"),
+            set_default_size: (640, 480),
+            set_hide_on_close: true,
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_spacing: 10,
+                set_margin_all: 10,
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_spacing: 10,
+                    gtk::DropDown::from_strings(&["All sessions", "Last session"]) {
+                        connect_selected_notify[sender] => move |d| {
+                            sender.input(StatisticsMsg::LastOnly(d.selected() == 1))
+                        },
+                    },
+                    gtk::DropDown::from_strings(&["Keys", "Bigrams"]) {
+                        connect_selected_notify[sender] => move |d| {
+                            sender.input(StatisticsMsg::Bigrams(d.selected() == 1))
+                        },
+                    },
+                    gtk::Label {
+                        add_css_class: "dim-label",
+                        #[watch]
+                        set_label: &model.flagged(),
+                    },
+                },
+                gtk::Notebook {
+                    set_vexpand: true,
+                    append_page[Some(&gtk::Label::new(Some("Keys")))] = &gtk::ScrolledWindow {
+                        #[local_ref]
+                        grid -> gtk::Grid {
+                            set_column_spacing: 20,
+                            set_row_spacing: 4,
+                        },
+                    },
+                    append_page[Some(&gtk::Label::new(Some("Confusions")))] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 10,
+                        set_margin_all: 10,
+                        gtk::Label {
+                            set_xalign: 0.0,
+                            set_markup: &legend(),
+                        },
+                        gtk::ScrolledWindow {
+                            set_vexpand: true,
+                            #[local_ref]
+                            matrix -> gtk::Grid {
+                                set_column_spacing: 6,
+                                set_row_spacing: 4,
+                            },
+                        },
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Horizontal,
+                            set_spacing: 10,
+                            gtk::Button {
+                                set_label: "Export CSV",
+                                connect_clicked => StatisticsMsg::Export,
+                            },
+                            gtk::Label {
+                                #[watch]
+                                set_label: &model.exported,
+                            },
+                        },
+                    },
+                    append_page[Some(&gtk::Label::new(Some("Dwell and flight")))] = &gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 10,
+                        set_margin_all: 10,
+                        gtk::Label {
+                            set_xalign: 0.0,
+                            set_wrap: true,
+                            #[watch]
+                            set_label: &model.holds(),
+                        },
+                        #[local_ref]
+                        dwell_histogram -> gtk::Box {
+                            set_tooltip_text: Some("How long the keys were held"),
+                        },
+                        #[local_ref]
+                        flight_histogram -> gtk::Box {
+                            set_tooltip_text: Some(
+                                "From releasing a key to pressing the next, without rollovers"
+                            ),
+                        },
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        layout: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = StatisticsComp {
+            history: History::default(),
+            layout,
+            stats: KeyStats::default(),
+            confusion: ConfusionMatrix::default(),
+            dwell: DwellStats::default(),
+            dwell_histogram: HistogramComp::builder()
+                .launch(("Dwell", Some(LONG_HOLD)))
+                .detach(),
+            flight_histogram: HistogramComp::builder().launch(("Flight", None)).detach(),
+            last_only: false,
+            bigrams: false,
+            column: Column::ErrorRate,
+            descending: true,
+            grid: gtk::Grid::new(),
+            matrix: gtk::Grid::new(),
+            exported: String::new(),
+        };
+        let grid = &model.grid;
+        let matrix = &model.matrix;
+        let dwell_histogram = model.dwell_histogram.widget();
+        let flight_histogram = model.flight_histogram.widget();
+
+        let widgets = view_output!();
+        model.fill(&sender);
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+        match message {
+            StatisticsMsg::Show(history) => {
+                self.history = history;
+                self.compute();
+            }
+            StatisticsMsg::LastOnly(last_only) => {
+                self.last_only = last_only;
+                self.compute();
+            }
+            StatisticsMsg::Bigrams(bigrams) => self.bigrams = bigrams,
+            StatisticsMsg::Sort(column) if column == self.column => {
+                self.descending = !self.descending
+            }
+            StatisticsMsg::Sort(column) => {
+                self.column = column;
+                self.descending = true;
+            }
+            StatisticsMsg::Export => {
+                let path = data_dir().join("confusion.csv");
+                self.exported = match self.confusion.export(&path) {
+                    Ok(()) => format!("Exported to {}", path.display()),
+                    Err(e) => format!("{:#}", e),
+                };
+                return;
+            }
+        }
+        self.fill(&sender);
+    }
+}
@@ -2,10 +2,36 @@ use relm4::gtk;
 use relm4::ComponentParts;
 use relm4::ComponentSender;
 use relm4::SimpleComponent;
+use std::time::Instant;
 
-use crate::{msg::Msg, stats::Stats};
+use crate::{ghost::Ghost, msg::Msg, stats::Stats};
 
-pub(crate) struct StatsComp(Stats);
+pub(crate) struct StatsComp {
+    stats: Stats,
+    /// The ghost raced, when the practice started and how many touches
+    /// were typed since.
+    ghost: Option<Ghost>,
+    started: Option<Instant>,
+    typed: usize,
+    /// When the practice was paused, while it is.
+    paused: Option<Instant>,
+    /// Seconds ahead of the ghost, behind if negative.
+    lead: Option<f32>,
+}
+
+impl StatsComp {
+    fn label(&self) -> String {
+        let speed = format!("{}/s", self.stats.avg_key_s());
+        match (&self.ghost, self.lead) {
+            (Some(_), Some(lead)) if lead >= 0.0 => {
+                format!("{}  ·  {:.1} s ahead of the ghost", speed, lead)
+            }
+            (Some(_), Some(lead)) => format!("{}  ·  {:.1} s behind the ghost", speed, -lead),
+            (Some(ghost), None) => format!("{}  ·  racing {}", speed, ghost.name),
+            (None, _) => speed,
+        }
+    }
+}
 
 #[relm4::component(pub)]
 impl SimpleComponent for StatsComp {
@@ -16,7 +42,7 @@ impl SimpleComponent for StatsComp {
     view! {
         gtk::Label {
             #[watch]
-            set_label: &format!("{}/s", model.0.avg_key_s())
+            set_label: &model.label()
         }
     }
 
@@ -25,14 +51,46 @@ impl SimpleComponent for StatsComp {
         root: &Self::Root,
         _sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let model = StatsComp(init);
+        let model = StatsComp {
+            stats: init,
+            ghost: None,
+            started: None,
+            typed: 0,
+            paused: None,
+            lead: None,
+        };
         let widgets = view_output!();
         ComponentParts { model, widgets }
     }
 
     fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
         match msg {
-            Msg::KeyPressed(_, _, _, ts) => self.0.add(ts),
+            Msg::KeyPressed(_, _, _, ts, _) => {
+                self.stats.add(ts);
+                self.typed += 1;
+                let started = *self.started.get_or_insert(ts);
+                self.lead = self
+                    .ghost
+                    .as_ref()
+                    .and_then(|g| g.lead(self.typed, ts.duration_since(started)));
+            }
+            Msg::PracticeStart(_) => {
+                self.started = None;
+                self.typed = 0;
+                self.paused = None;
+                self.lead = None;
+            }
+            Msg::PracticePause(ts) => {
+                self.stats.pause();
+                self.paused.get_or_insert(ts);
+            }
+            Msg::PracticeResume(ts) => {
+                // the ghost waited too
+                if let (Some(paused), Some(started)) = (self.paused.take(), self.started) {
+                    self.started = Some(started + ts.saturating_duration_since(paused));
+                }
+            }
+            Msg::GhostRace(ghost) => self.ghost = ghost,
             _ => (),
         }
     }
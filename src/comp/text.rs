@@ -0,0 +1,126 @@
+use gtk::pango;
+use gtk::prelude::*;
+use relm4::gtk;
+use std::ops::Range;
+
+use crate::comp::caret::Place;
+use crate::model::{Challenge, Touch};
+
+/// How a space to type is shown.
+const SPACE: char = '·';
+/// Lines are spaced by that many times the height of the font.
+const LINE_SPACING: f64 = 1.5;
+
+/// A line of the challenge shaped by Pango.
+pub(crate) struct Line {
+    pub(crate) layout: pango::Layout,
+    /// Indexes in the challenge of the touches of the line.
+    pub(crate) touches: Range<usize>,
+    /// Byte range in the text of the layout of each touch of the line.
+    bytes: Vec<Range<u32>>,
+}
+
+/// The challenge laid out in lines with Pango, so kerning, shaping and the
+/// fallback fonts work for any script. The place of each touch is computed
+/// once, when the challenge or the font change, or when words are appended.
+pub(crate) struct TextLayout {
+    pub(crate) lines: Vec<Line>,
+    /// The line of each touch and its place from the top left of the line.
+    places: Vec<(usize, Place)>,
+    pub(crate) line_height: f64,
+    font: pango::FontDescription,
+    words_per_line: usize,
+}
+
+fn pixels(units: i32) -> f64 {
+    units as f64 / pango::SCALE as f64
+}
+
+impl Line {
+    /// Returns the byte range of the touch at `i` in the challenge.
+    pub(crate) fn bytes(&self, i: usize) -> Option<Range<u32>> {
+        self.bytes.get(i.checked_sub(self.touches.start)?).cloned()
+    }
+}
+
+impl TextLayout {
+    /// Lays out `challenge` with `words_per_line` words per line, in the
+    /// font `font` of the widget `widget`.
+    pub(crate) fn new(
+        widget: &impl IsA<gtk::Widget>,
+        challenge: &Challenge,
+        font: &pango::FontDescription,
+        words_per_line: usize,
+    ) -> TextLayout {
+        let mut text = TextLayout {
+            lines: vec![],
+            places: vec![],
+            line_height: 0.0,
+            font: font.clone(),
+            words_per_line,
+        };
+        text.lay_out(widget, challenge, 0);
+        text
+    }
+
+    /// Lays out the touches appended to `challenge` since it was laid out,
+    /// and the last line again as they may continue it.
+    pub(crate) fn extend(&mut self, widget: &impl IsA<gtk::Widget>, challenge: &Challenge) {
+        let start = self.lines.pop().map_or(0, |line| line.touches.start);
+        self.places.truncate(start);
+        self.lay_out(widget, challenge, start);
+    }
+
+    /// Lays out the touches of `challenge` from `start`, the first touch of
+    /// the line following those laid out.
+    fn lay_out(&mut self, widget: &impl IsA<gtk::Widget>, challenge: &Challenge, start: usize) {
+        let mut texts: Vec<(String, Range<usize>, Vec<Range<u32>>)> = vec![];
+        for (i, (t, w)) in challenge.iter().enumerate().skip(start) {
+            let line = w / self.words_per_line - self.lines.len();
+            if texts.len() <= line {
+                texts.push((String::new(), i..i, vec![]));
+            }
+            let (text, touches, bytes) = texts.last_mut().expect("a line was pushed");
+            let start = text.len() as u32;
+            text.push(match t {
+                Touch::Space => SPACE,
+                Touch::Char(c) => c,
+            });
+            bytes.push(start..text.len() as u32);
+            touches.end = i + 1;
+        }
+
+        let font = &self.font;
+        let metrics = widget.pango_context().metrics(Some(font), None);
+        let (ascent, descent) = (pixels(metrics.ascent()), pixels(metrics.descent()));
+        for (text, touches, bytes) in texts {
+            let layout = widget.create_pango_layout(Some(text.as_str()));
+            layout.set_font_description(Some(font));
+            let baseline = pixels(layout.baseline());
+            for range in &bytes {
+                let rect = layout.index_to_pos(range.start as i32);
+                self.places.push((
+                    self.lines.len(),
+                    Place {
+                        x: pixels(rect.x()),
+                        y: baseline,
+                        width: pixels(rect.width()),
+                        ascent,
+                        descent,
+                    },
+                ));
+            }
+            self.lines.push(Line {
+                layout,
+                touches,
+                bytes,
+            });
+        }
+        self.line_height = (ascent + descent) * LINE_SPACING;
+    }
+
+    /// Returns the line of the touch at `i` and its place in the line.
+    pub(crate) fn place(&self, i: usize) -> Option<(usize, Place)> {
+        self.places.get(i).copied()
+    }
+}
@@ -0,0 +1,133 @@
+use gtk::prelude::*;
+use relm4::gtk;
+use relm4::{drawing::DrawHandler, ComponentParts, ComponentSender, SimpleComponent};
+
+use crate::analysis::rhythm::{BeatKind, Rhythm};
+use crate::model::Touch;
+use crate::utils::{Clear, HasDrawHandler};
+
+const MARGIN: f64 = 30.0;
+const TEXT_Y: f64 = 20.0;
+const STRIP_Y: f64 = 30.0;
+const STRIP_HEIGHT: f64 = 14.0;
+/// Characters closer than this are not all drawn.
+const CHAR_SPACE: f64 = 9.0;
+
+#[derive(Debug)]
+pub(crate) enum TimelineMsg {
+    Show(Rhythm),
+    Redraw,
+}
+
+/// The text typed laid out over time, above a strip telling the steady
+/// intervals from the hesitations and the bursts.
+pub(crate) struct TimelineComp {
+    rhythm: Rhythm,
+    handler: DrawHandler,
+}
+
+impl<'a> HasDrawHandler<'a> for TimelineComp {
+    fn draw_handler_mut(&'a mut self) -> &'a mut DrawHandler {
+        &mut self.handler
+    }
+}
+
+fn color(kind: BeatKind) -> (f64, f64, f64) {
+    match kind {
+        BeatKind::Steady => (0.75, 0.75, 0.75),
+        BeatKind::Hesitation => (0.96, 0.65, 0.14),
+        BeatKind::Burst => (0.18, 0.8, 0.44),
+    }
+}
+
+impl TimelineComp {
+    fn draw(&mut self) {
+        let cx = self.handler.get_context();
+        self.clear();
+        let width = self.handler.drawing_area().width() as f64 - 2.0 * MARGIN;
+        let Some(last) = self.rhythm.beats.last() else {
+            return;
+        };
+        let duration = last.at.as_secs_f64();
+        if width <= 0.0 || duration <= 0.0 {
+            return;
+        }
+        let x = |at: f64| MARGIN + width * at / duration;
+
+        cx.select_font_face(
+            "Courier New",
+            gtk::cairo::FontSlant::Normal,
+            gtk::cairo::FontWeight::Bold,
+        );
+        cx.set_font_size(12.0);
+        let mut drawn = f64::MIN;
+        let mut previous = 0.0;
+        for beat in &self.rhythm.beats {
+            let at = beat.at.as_secs_f64();
+            let (r, g, b) = color(beat.kind);
+            cx.set_source_rgb(r, g, b);
+            cx.rectangle(x(previous), STRIP_Y, x(at) - x(previous), STRIP_HEIGHT);
+            cx.fill().expect("draw an interval");
+            previous = at;
+
+            if x(at) - drawn < CHAR_SPACE {
+                continue;
+            }
+            drawn = x(at);
+            if beat.success {
+                cx.set_source_rgb(0.0, 0.0, 0.0);
+            } else {
+                cx.set_source_rgb(1.0, 0.0, 0.0);
+            }
+            let text = match beat.typed {
+                Touch::Char(c) => c.to_string(),
+                Touch::Space => "·".to_string(),
+            };
+            cx.move_to(x(at) - 4.0, TEXT_Y);
+            cx.show_text(text.as_str()).expect("draw a touch");
+        }
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for TimelineComp {
+    type Init = ();
+    type Input = TimelineMsg;
+    type Output = ();
+
+    view! {
+        gtk::Box {
+            #[local_ref]
+            area -> gtk::DrawingArea {
+                set_hexpand: true,
+                set_content_height: 50,
+                set_tooltip_text: Some("grey: steady, orange: hesitation, green: burst"),
+                connect_resize[sender] => move |_, _, _| sender.input(TimelineMsg::Redraw),
+            },
+        }
+    }
+
+    fn init(
+        _init: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = TimelineComp {
+            rhythm: Rhythm::default(),
+            handler: DrawHandler::new(),
+        };
+        let area = model.handler.drawing_area();
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        match message {
+            TimelineMsg::Show(rhythm) => self.rhythm = rhythm,
+            TimelineMsg::Redraw => (),
+        }
+        self.draw();
+    }
+}
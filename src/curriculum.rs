@@ -0,0 +1,270 @@
+//! Lessons unlocking the letters of a layout little by little.
+//!
+//! Lesson: the letters it introduces and the level needed to master them.
+//! Curriculum: the ordered lessons of a layout, home row first.
+//! Progress: how many lessons are unlocked on each layout, saved between
+//! runs.
+//!
+//! A lesson is mastered once every letter unlocked so far is typed in the
+//! recent sessions often enough, accurately enough and fast enough; the
+//! next lesson is then unlocked.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::analysis::keys::{Gram, KeyStats};
+use crate::layout::{Finger, Layout};
+use crate::model::Touch;
+use crate::session::History;
+
+/// Rows of a five rows layout in the order they are learnt, with their name.
+const ROWS: &[(usize, &str)] = &[
+    (2, "home row"),
+    (1, "top row"),
+    (3, "bottom row"),
+    (0, "number row"),
+];
+/// How many times a letter must have been typed before judging it.
+const MIN_COUNT: usize = 10;
+const TARGET_ACCURACY: f32 = 0.95;
+/// Speed expected on the first lesson, then raised at each lesson.
+const FIRST_WPM: f32 = 15.0;
+const WPM_STEP: f32 = 2.5;
+const PROGRESS_FILE: &str = "progress.json";
+
+/// A step of a curriculum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lesson {
+    /// Like `home row, index fingers`.
+    pub name: String,
+    /// The letters introduced by this lesson.
+    pub letters: Vec<char>,
+    /// Ratio of keystrokes typed right, between 0 and 1.
+    pub accuracy: f32,
+    /// Speed needed to master the lesson, in words per minute.
+    pub wpm: f32,
+}
+
+/// The lessons of a layout.
+#[derive(Clone, Debug)]
+pub struct Curriculum {
+    /// Name of the layout.
+    pub layout: String,
+    /// In the order they are unlocked.
+    pub lessons: Vec<Lesson>,
+}
+
+/// How far the user is in the curriculum of each layout.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Progress {
+    /// Number of lessons unlocked, by layout name.
+    unlocked: BTreeMap<String, usize>,
+}
+
+/// Index and middle fingers come first as they are the easiest to move.
+fn is_strong(finger: Finger) -> bool {
+    matches!(
+        finger,
+        Finger::LeftIndex | Finger::LeftMiddle | Finger::RightIndex | Finger::RightMiddle
+    )
+}
+
+impl Curriculum {
+    /// Builds two lessons per row of `layout`: the letters of the index and
+    /// middle fingers, then those of the ring and little fingers.
+    pub fn new(layout: &Layout) -> Curriculum {
+        let mut lessons = vec![];
+        for (row, name) in ROWS {
+            let Some(keys) = layout.rows.get(*row) else {
+                continue;
+            };
+            for (strong, fingers) in [(true, "index and middle"), (false, "ring and little")] {
+                let letters: Vec<char> = keys
+                    .iter()
+                    .filter(|k| is_strong(k.finger) == strong)
+                    .filter_map(|k| k.base)
+                    .filter(|c| !c.is_whitespace())
+                    .collect();
+                if letters.is_empty() {
+                    continue;
+                }
+                lessons.push(Lesson {
+                    name: format!("{}, {} fingers", name, fingers),
+                    letters,
+                    accuracy: TARGET_ACCURACY,
+                    wpm: FIRST_WPM + WPM_STEP * lessons.len() as f32,
+                });
+            }
+        }
+        Curriculum {
+            layout: layout.name.clone(),
+            lessons,
+        }
+    }
+
+    /// Returns the letters of the first `unlocked` lessons.
+    pub fn letters(&self, unlocked: usize) -> Vec<char> {
+        self.lessons
+            .iter()
+            .take(unlocked)
+            .flat_map(|l| l.letters.iter().copied())
+            .collect()
+    }
+
+    /// Returns whether every letter up to `lesson` is mastered at the level
+    /// of `lesson`.
+    pub fn is_mastered(&self, lesson: usize, stats: &KeyStats) -> bool {
+        let Some(target) = self.lessons.get(lesson) else {
+            return false;
+        };
+        self.letters(lesson + 1).iter().all(|c| {
+            stats
+                .get(Gram::Key(Touch::from(*c)))
+                .filter(|s| s.count >= MIN_COUNT)
+                .is_some_and(|s| {
+                    // a word is 5 keystrokes
+                    let wpm = s
+                        .mean()
+                        .map_or(0.0, |d| 12.0 / d.as_secs_f32().max(f32::EPSILON));
+                    1.0 - s.error_rate() >= target.accuracy && wpm >= target.wpm
+                })
+        })
+    }
+}
+
+impl Progress {
+    fn path(dir: &Path) -> PathBuf {
+        dir.join(PROGRESS_FILE)
+    }
+
+    /// Loads the progress saved in `dir`, none if it was never saved.
+    pub fn load(dir: &Path) -> Result<Progress> {
+        let path = Progress::path(dir);
+        match fs::read_to_string(&path) {
+            Ok(json) => Ok(serde_json::from_str(json.as_str())?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Progress::default()),
+            Err(e) => Err(e).context(format!("cannot read {:?}", path)),
+        }
+    }
+
+    /// Saves the progress in `dir`.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).context(format!("cannot create directory {:?}", dir))?;
+        let path = Progress::path(dir);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .context(format!("cannot create file at {:?}", path))
+    }
+
+    /// Returns the number of lessons unlocked in `curriculum`, at least one.
+    pub fn unlocked(&self, curriculum: &Curriculum) -> usize {
+        self.unlocked
+            .get(&curriculum.layout)
+            .copied()
+            .unwrap_or(1)
+            .clamp(1, curriculum.lessons.len().max(1))
+    }
+
+    /// Unlocks the next lesson if the recent sessions of `history` show the
+    /// last unlocked one is mastered, returns it if so.
+    pub fn update<'a>(
+        &mut self,
+        curriculum: &'a Curriculum,
+        history: &History,
+    ) -> Option<&'a Lesson> {
+        let unlocked = self.unlocked(curriculum);
+        let mut stats = KeyStats::default();
+        for session in history.recent() {
+            stats.add_session(session);
+        }
+        if !curriculum.is_mastered(unlocked - 1, &stats) {
+            return None;
+        }
+        let next = curriculum.lessons.get(unlocked)?;
+        self.unlocked
+            .insert(curriculum.layout.clone(), unlocked + 1);
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::time::Duration;
+
+    use super::{Curriculum, Progress};
+    use crate::layout::Layout;
+    use crate::model::{Keystroke, PracticeGenerator, Touch};
+    use crate::session::{History, Session};
+
+    fn session(text: &str, ms: u64) -> Session {
+        let keystrokes = text
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                Keystroke::new(
+                    Touch::from(c),
+                    Touch::from(c),
+                    Duration::from_millis(ms * i as u64),
+                )
+            })
+            .collect();
+        Session {
+            name: "test".to_string(),
+            timestamp: 0,
+            text: text.to_string(),
+            keystrokes,
+            ..Session::default()
+        }
+    }
+
+    #[test]
+    pub fn it_unlocks_lessons_once_mastered() {
+        let curriculum = Curriculum::new(&Layout::qwerty());
+        assert_eq!(
+            curriculum.lessons[0].letters,
+            vec!['d', 'f', 'g', 'h', 'j', 'k']
+        );
+        assert_eq!(curriculum.letters(2).len(), 11);
+
+        let mut progress = Progress::default();
+        assert_eq!(progress.unlocked(&curriculum), 1);
+        let mut history = History::default();
+        // too slow: 2 wpm
+        history.push(session(&"dfghjk".repeat(10), 1000));
+        assert_eq!(progress.update(&curriculum, &history), None);
+        // 60 wpm
+        history.push(session(&"dfghjk".repeat(10), 200));
+        history.push(session(&"dfghjk".repeat(10), 200));
+        assert_eq!(
+            progress.update(&curriculum, &history),
+            Some(&curriculum.lessons[1])
+        );
+        assert_eq!(progress.unlocked(&curriculum), 2);
+    }
+
+    #[test]
+    pub fn it_unlocks_every_lesson_with_the_words_generated() {
+        let curriculum = Curriculum::new(&Layout::qwerty());
+        let mut progress = Progress::default();
+        let mut history = History::default();
+        let rng = StdRng::seed_from_u64(3);
+        let mut generator = PracticeGenerator::new(rng, 25, "./data/t8.shakespeare.freq");
+        // punctuation and rare letters are typed too, at 120 wpm without
+        // a mistake
+        while progress.unlocked(&curriculum) < curriculum.lessons.len()
+            && history.sessions().len() < 200
+        {
+            let unlocked = progress.unlocked(&curriculum);
+            generator.set_letters(Some(curriculum.letters(unlocked)));
+            let text = generator.generate().unwrap().challenge().text();
+            history.push(session(&text, 100));
+            progress.update(&curriculum, &history);
+        }
+        assert_eq!(progress.unlocked(&curriculum), curriculum.lessons.len());
+    }
+}
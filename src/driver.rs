@@ -0,0 +1,328 @@
+//! What happens to a practice as it is typed, the same for every interface.
+//!
+//! Driver: the practice under way, the keys and commands it takes and when
+//! it ends.
+//! Effect: what the interface does in return, the driver has no clock nor
+//! trainer of its own.
+
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+use crate::flow::Event;
+use crate::latency::Latency;
+use crate::model::{Practice, Touch, Word};
+
+/// How long the end waits for the keys still held to be released.
+pub const RELEASE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What the interface does for the driver.
+#[derive(Clone, Debug)]
+pub enum Effect {
+    /// Words are missing ahead of the cursor, to `extend` the practice with.
+    Grow(usize),
+    /// The practice is over and to be saved, it takes nothing more.
+    End,
+    /// Abandoned before the first touch, there is nothing to save: the next
+    /// practice starts.
+    Skip,
+    /// The time of the practice may be up then, to `tick` again.
+    Wake(Duration),
+}
+
+/// Takes the keys and the commands for a practice until it ends.
+pub struct Driver {
+    practice: Practice,
+    /// When the practice was over with keys still held, it ends once they
+    /// are released to record how long they were.
+    finishing: Option<Instant>,
+    /// The practice ended, whatever comes next is ignored.
+    ended: bool,
+}
+
+impl Driver {
+    /// Drives `practice`, which may be started already.
+    pub fn new(practice: Practice) -> Driver {
+        Driver {
+            practice,
+            finishing: None,
+            ended: false,
+        }
+    }
+
+    /// Returns the practice under way.
+    pub fn practice(&self) -> &Practice {
+        &self.practice
+    }
+
+    /// Returns whether the practice ended, it is saved once.
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Types `touch` at `ts`. A practice of a number of words is over with
+    /// its last touch. The keys are ignored while paused.
+    pub fn press(&mut self, touch: &Touch, ts: Instant) -> Vec<Effect> {
+        if self.ended || self.practice.is_paused() {
+            return vec![];
+        }
+        // the next key was pressed, the release of the last one was missed
+        if self.finishing.is_some() {
+            return self.end();
+        }
+        let first = !self.practice.is_started();
+        let pressed = self.practice.press(touch, ts).is_some();
+        if self.practice.is_over(ts) {
+            return self.finish(ts);
+        }
+        // nothing is expected until the practice is extended
+        if !pressed {
+            return vec![];
+        }
+        let mut effects = vec![];
+        if first {
+            effects.extend(self.practice.time_left(ts).map(Effect::Wake));
+        }
+        let missing = self.practice.missing_words();
+        if missing > 0 {
+            effects.push(Effect::Grow(missing));
+        }
+        effects
+    }
+
+    /// Releases the key typed for `touch` at `ts`. The practice over ends
+    /// with the release of the last key held.
+    pub fn release(&mut self, touch: &Touch, ts: Instant) -> Vec<Effect> {
+        if self.ended {
+            return vec![];
+        }
+        self.practice.release(touch, ts);
+        if self.finishing.is_some() && self.practice.held() == 0 {
+            return self.end();
+        }
+        vec![]
+    }
+
+    /// Ends a timed practice whose time is up at `now`, or a practice
+    /// over whose keys were not released in time, or tells when to check
+    /// again. The time spent paused is not counted.
+    pub fn tick(&mut self, now: Instant) -> Vec<Effect> {
+        if self.ended || !self.practice.is_started() {
+            return vec![];
+        }
+        if let Some(over) = self.finishing {
+            let left = (over + RELEASE_TIMEOUT).saturating_duration_since(now);
+            return if left.is_zero() {
+                self.end()
+            } else {
+                vec![Effect::Wake(left)]
+            };
+        }
+        if self.practice.is_over(now) {
+            return self.finish(now);
+        }
+        match self.practice.time_left(now) {
+            Some(left) if !self.practice.is_paused() => vec![Effect::Wake(left)],
+            _ => vec![],
+        }
+    }
+
+    /// Stops the clock, the keys are ignored until resumed.
+    pub fn pause(&mut self, ts: Instant) {
+        if !self.ended && self.finishing.is_none() {
+            self.practice.pause(ts);
+        }
+    }
+
+    /// Starts the clock again at `ts`.
+    pub fn resume(&mut self, ts: Instant) -> Vec<Effect> {
+        if self.ended || !self.practice.is_paused() {
+            return vec![];
+        }
+        self.practice.resume(ts);
+        if !self.practice.is_started() {
+            return vec![];
+        }
+        self.practice
+            .time_left(ts)
+            .map(Effect::Wake)
+            .into_iter()
+            .collect()
+    }
+
+    /// Ends the practice before its end, it is saved as incomplete.
+    pub fn abandon(&mut self) -> Vec<Effect> {
+        if self.ended {
+            vec![]
+        } else if self.finishing.is_some() {
+            // the practice is complete
+            self.end()
+        } else if !self.practice.is_started() {
+            vec![Effect::Skip]
+        } else {
+            self.practice.abandon();
+            self.end()
+        }
+    }
+
+    /// Adds `words` to the end of the practice, for `Effect::Grow`.
+    pub fn extend(&mut self, words: Vec<Word>) {
+        self.practice.extend(words);
+    }
+
+    /// Records how long a keystroke took to show, even after the end.
+    pub fn record_latency(&mut self, latency: Latency) {
+        self.practice.record_latency(latency);
+    }
+
+    /// Handles `event` sent to the components showing the practice, returns
+    /// the events for the application: the effects, and the practice to
+    /// start again on `Event::Retry`.
+    pub fn handle(&mut self, event: Event) -> Result<Vec<Event>> {
+        let effects = match event {
+            Event::Start(practice) => {
+                *self = Driver::new(practice);
+                vec![]
+            }
+            Event::Press(touch, ts) => self.press(&touch, ts),
+            Event::Release(touch, ts) => self.release(&touch, ts),
+            Event::Extend(words) => {
+                self.extend(words);
+                vec![]
+            }
+            Event::Tick(now) => self.tick(now),
+            Event::Pause(ts) => {
+                self.pause(ts);
+                vec![]
+            }
+            Event::Resume(ts) => self.resume(ts),
+            Event::Abandon => self.abandon(),
+            Event::Retry => return Ok(vec![Event::Start(self.practice.retry()?)]),
+            _ => vec![],
+        };
+        let events = effects.into_iter().map(|effect| match effect {
+            Effect::Grow(missing) => Event::Grow(missing),
+            Effect::End => Event::End(self.practice.clone()),
+            Effect::Skip => Event::Next,
+            Effect::Wake(after) => Event::Wake(after),
+        });
+        Ok(events.collect())
+    }
+
+    /// Ends the practice over at `now`, or waits for the keys held when
+    /// the releases are seen at all: not in a terminal.
+    fn finish(&mut self, now: Instant) -> Vec<Effect> {
+        let releases = self.practice.keystrokes().iter().any(|k| k.dwell.is_some());
+        if self.practice.held() == 0 || !releases {
+            return self.end();
+        }
+        self.finishing = Some(now);
+        vec![Effect::Wake(RELEASE_TIMEOUT)]
+    }
+
+    fn end(&mut self) -> Vec<Effect> {
+        self.ended = true;
+        vec![Effect::End]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::{Duration, Instant};
+
+    use super::{Driver, Effect, RELEASE_TIMEOUT};
+    use crate::model::{Challenge, Mode, Practice, Touch};
+
+    fn driver(text: &str, mode: Mode) -> Driver {
+        let challenge = Challenge::from_str(text).unwrap();
+        Driver::new(Practice::new(challenge, "test".to_string()).with_mode(mode))
+    }
+
+    #[test]
+    pub fn it_ends_with_the_last_release() {
+        let mut driver = driver("ab", Mode::Words(1));
+        let start = Instant::now();
+        let ms = |ms: u64| start + Duration::from_millis(ms);
+        assert!(driver.press(&Touch::Char('a'), ms(0)).is_empty());
+        assert!(driver.release(&Touch::Char('a'), ms(50)).is_empty());
+        // a mistake on the last touch is over too
+        let effects = driver.press(&Touch::Char('x'), ms(100));
+        assert!(matches!(
+            effects.as_slice(),
+            [Effect::Wake(RELEASE_TIMEOUT)]
+        ));
+        assert!(!driver.is_ended());
+        let effects = driver.release(&Touch::Char('x'), ms(180));
+        assert!(matches!(effects.as_slice(), [Effect::End]));
+        assert!(driver.is_ended());
+        assert!(driver.press(&Touch::Char('b'), ms(200)).is_empty());
+        assert!(driver.abandon().is_empty());
+        let keystrokes = driver.practice().keystrokes();
+        assert_eq!(keystrokes.len(), 2);
+        assert_eq!(keystrokes[1].dwell, Some(Duration::from_millis(80)));
+    }
+
+    #[test]
+    pub fn it_ends_when_the_last_release_is_missed() {
+        let mut driver = driver("ab", Mode::Words(1));
+        let start = Instant::now();
+        driver.press(&Touch::Char('a'), start);
+        driver.release(&Touch::Char('a'), start);
+        driver.press(&Touch::Char('b'), start);
+        let later = start + RELEASE_TIMEOUT / 2;
+        assert!(matches!(driver.tick(later).as_slice(), [Effect::Wake(_)]));
+        assert!(matches!(
+            driver.tick(start + RELEASE_TIMEOUT).as_slice(),
+            [Effect::End]
+        ));
+
+        // or when the next key is pressed
+        let mut driver = self::driver("ab", Mode::Words(1));
+        driver.press(&Touch::Char('a'), start);
+        driver.release(&Touch::Char('a'), start);
+        driver.press(&Touch::Char('b'), start);
+        let effects = driver.press(&Touch::Char('c'), later);
+        assert!(matches!(effects.as_slice(), [Effect::End]));
+        assert_eq!(driver.practice().keystrokes().len(), 2);
+
+        // or right away when no release was seen
+        let mut driver = self::driver("ab", Mode::Words(1));
+        driver.press(&Touch::Char('a'), start);
+        let effects = driver.press(&Touch::Char('b'), start);
+        assert!(matches!(effects.as_slice(), [Effect::End]));
+    }
+
+    #[test]
+    pub fn it_ends_when_the_time_is_up() {
+        let mut driver = driver("a b", Mode::Timed(Duration::from_secs(2)));
+        let start = Instant::now();
+        let s = |s: u64| start + Duration::from_secs(s);
+        let wake = |effects: Vec<Effect>| match effects.as_slice() {
+            [Effect::Wake(after)] => Some(after.as_secs()),
+            _ => None,
+        };
+        assert!(driver.tick(s(5)).is_empty());
+        let effects = driver.press(&Touch::Char('a'), start);
+        assert!(matches!(
+            effects.as_slice(),
+            [Effect::Wake(after), Effect::Grow(_)] if after.as_secs() == 2
+        ));
+        driver.release(&Touch::Char('a'), start);
+        // the time paused is not counted
+        driver.pause(s(1));
+        assert!(driver.tick(s(3)).is_empty());
+        assert_eq!(wake(driver.resume(s(3))), Some(1));
+        assert_eq!(wake(driver.tick(s(3))), Some(1));
+        assert!(matches!(driver.tick(s(4)).as_slice(), [Effect::End]));
+        assert!(driver.tick(s(5)).is_empty());
+    }
+
+    #[test]
+    pub fn it_skips_a_practice_abandoned_before_the_first_touch() {
+        let mut driver = driver("ab", Mode::Words(1));
+        assert!(matches!(driver.abandon().as_slice(), [Effect::Skip]));
+        driver.press(&Touch::Char('a'), Instant::now());
+        assert!(matches!(driver.abandon().as_slice(), [Effect::End]));
+        assert!(driver.practice().is_abandoned());
+    }
+}
@@ -0,0 +1,177 @@
+//! How the application handles the messages of the practices, the same
+//! with or without a display.
+//!
+//! Event: a message of the practices, from the user, a timer or the
+//! practice itself.
+//! Flow: the trainer and what the application shows, it takes the events
+//! sent to the application and returns those for the components showing
+//! the practice, which hand them to `Driver::handle`.
+
+use anyhow::Result;
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+use crate::model::{Practice, Touch, Word};
+use crate::session::Report;
+use crate::trainer::Trainer;
+
+/// A message of the practices.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// Starts the practice, the results shown are dismissed.
+    Start(Practice),
+    /// The key for the touch was typed at that time.
+    Press(Touch, Instant),
+    /// The key typed for the touch was released at that time.
+    Release(Touch, Instant),
+    /// The practice needs that many more words to keep growing.
+    Grow(usize),
+    /// Words to append to the current practice.
+    Extend(Vec<Word>),
+    /// The time of the practice may be up then, to `Tick` again.
+    Wake(Duration),
+    /// The time passed, a timed practice may be over.
+    Tick(Instant),
+    /// Stops the clock of the practice.
+    Pause(Instant),
+    /// Restarts the clock of the paused practice.
+    Resume(Instant),
+    /// Ends the current practice early, it is saved as incomplete.
+    Abandon,
+    /// The practice is over, to be saved.
+    End(Practice),
+    /// Results of the practice that just ended.
+    Report(Report),
+    /// Start again the practice that just ended, or the current one.
+    Retry,
+    /// Practice the words mistyped in the practice that just ended.
+    Missed,
+    /// Move on to a new practice.
+    Next,
+}
+
+/// The state of the application around the practices.
+pub struct Flow<R> {
+    trainer: Trainer<R>,
+    /// The practice that just ended and its results, while they are shown.
+    last: Option<(Practice, Report)>,
+    /// A key was typed in the current practice.
+    typing: bool,
+    /// The practice is paused, the keys are ignored.
+    paused: bool,
+    /// Keys typed so far, to tell whether one was typed since.
+    keys: usize,
+}
+
+impl<R: Rng> Flow<R> {
+    /// Starts with a practice shown, none typed yet.
+    pub fn new(trainer: Trainer<R>) -> Flow<R> {
+        Flow {
+            trainer,
+            last: None,
+            typing: false,
+            paused: false,
+            keys: 0,
+        }
+    }
+
+    /// Returns the trainer generating and saving the practices.
+    pub fn trainer(&self) -> &Trainer<R> {
+        &self.trainer
+    }
+
+    /// Returns the trainer, to change the source or take its warnings.
+    pub fn trainer_mut(&mut self) -> &mut Trainer<R> {
+        &mut self.trainer
+    }
+
+    /// Returns the practice that just ended and its results, while they
+    /// are shown.
+    pub fn last(&self) -> Option<&(Practice, Report)> {
+        self.last.as_ref()
+    }
+
+    /// Returns whether a key was typed in the current practice.
+    pub fn is_typing(&self) -> bool {
+        self.typing
+    }
+
+    /// Returns whether the practice is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Returns the number of keys typed so far, in every practice.
+    pub fn keys(&self) -> usize {
+        self.keys
+    }
+
+    /// Handles `event` sent to the application, returns the events for the
+    /// components showing the practice.
+    pub fn update(&mut self, event: Event) -> Result<Vec<Event>> {
+        match event {
+            // the keys are for the results buttons while they are shown
+            Event::Press(_, _) if self.last.is_some() || self.paused => Ok(vec![]),
+            Event::Press(_, _) => {
+                self.typing = true;
+                self.keys += 1;
+                Ok(vec![event])
+            }
+            Event::Start(_) => {
+                self.last = None;
+                self.typing = false;
+                self.paused = false;
+                Ok(vec![event])
+            }
+            Event::Grow(missing) => match self.trainer.grow(missing) {
+                Ok(words) => Ok(vec![Event::Extend(words)]),
+                // the practice ends with the words it has, saved as
+                // incomplete
+                Err(e) => {
+                    let e = e.context("cannot generate more words");
+                    self.trainer.warnings.push(e);
+                    Ok(vec![Event::Abandon])
+                }
+            },
+            Event::End(practice) => {
+                let report = self.trainer.finish(&practice)?;
+                self.last = Some((practice, report.clone()));
+                Ok(vec![Event::Report(report)])
+            }
+            Event::Pause(_) if self.last.is_none() => {
+                self.paused = true;
+                Ok(vec![event])
+            }
+            Event::Resume(_) if self.paused => {
+                self.paused = false;
+                Ok(vec![event])
+            }
+            Event::Abandon if self.last.is_none() => Ok(vec![event]),
+            Event::Retry => match &self.last {
+                Some((practice, _)) => {
+                    let practice = practice.retry()?;
+                    self.update(Event::Start(practice))
+                }
+                // restarts the current practice
+                None => Ok(vec![event]),
+            },
+            Event::Missed => {
+                let missed = self.last.as_ref().and_then(|(_, r)| self.trainer.missed(r));
+                match missed {
+                    Some(practice) => self.update(Event::Start(practice)),
+                    None => Ok(vec![]),
+                }
+            }
+            Event::Next => {
+                let practice = self.trainer.generate()?;
+                self.update(Event::Start(practice))
+            }
+            Event::Release(_, _) | Event::Extend(_) | Event::Tick(_) => Ok(vec![event]),
+            Event::Wake(_)
+            | Event::Pause(_)
+            | Event::Resume(_)
+            | Event::Abandon
+            | Event::Report(_) => Ok(vec![]),
+        }
+    }
+}
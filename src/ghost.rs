@@ -0,0 +1,117 @@
+//! A previous session replayed alongside a practice, to race against it.
+//!
+//! The ghost replays the keystroke timestamps of the session: after a given
+//! time since the start, it has typed as many touches as the session had.
+
+use std::time::Duration;
+
+use crate::model::challenge_code;
+use crate::session::{History, Session};
+
+/// Which previous session of the same challenge to race.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GhostKind {
+    /// The fastest session.
+    Best,
+    /// The most recent session.
+    Last,
+}
+
+/// The keystroke times of a previous session, to race against.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ghost {
+    /// Name of the session replayed.
+    pub name: String,
+    /// When each touch was typed, since the start.
+    times: Vec<Duration>,
+}
+
+impl From<&Session> for Ghost {
+    fn from(session: &Session) -> Self {
+        Ghost {
+            name: session.name.clone(),
+            times: session.keystrokes.iter().map(|k| k.at).collect(),
+        }
+    }
+}
+
+impl Ghost {
+    /// Returns the ghost of a complete session of `history` on the
+    /// challenge `code`, if there is one.
+    pub fn find(history: &History, code: &str, kind: GhostKind) -> Option<Ghost> {
+        let mut sessions = history
+            .sessions()
+            .iter()
+            .filter(|s| !s.incomplete && challenge_code(s.text.as_str()) == code);
+        let session = match kind {
+            GhostKind::Best => sessions.max_by(|a, b| a.summary().wpm.total_cmp(&b.summary().wpm)),
+            GhostKind::Last => sessions.next_back(),
+        }?;
+        Some(Ghost::from(session))
+    }
+
+    /// Returns the number of touches typed after `elapsed`.
+    pub fn position_at(&self, elapsed: Duration) -> usize {
+        self.times.partition_point(|at| *at <= elapsed)
+    }
+
+    /// Returns how much earlier than the ghost the touch `position` was
+    /// typed at `elapsed`, negative if later; None if the ghost never got
+    /// there.
+    pub fn lead(&self, position: usize, elapsed: Duration) -> Option<f32> {
+        let at = self.times.get(position.checked_sub(1)?)?;
+        Some(at.as_secs_f32() - elapsed.as_secs_f32())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Ghost, GhostKind};
+    use crate::model::{challenge_code, Keystroke, Touch};
+    use crate::session::{History, Session};
+
+    fn session(name: &str, text: &str, ms: u64) -> Session {
+        let keystrokes = text
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                Keystroke::new(
+                    Touch::from(c),
+                    Touch::from(c),
+                    Duration::from_millis(ms * i as u64),
+                )
+            })
+            .collect();
+        Session {
+            name: name.to_string(),
+            timestamp: 0,
+            text: text.to_string(),
+            keystrokes,
+            ..Session::default()
+        }
+    }
+
+    #[test]
+    pub fn it_races_the_best_or_last_session() {
+        let mut history = History::default();
+        history.push(session("fast", "the cat", 100));
+        history.push(session("other", "the dog", 10));
+        history.push(session("slow", "the cat", 200));
+        let code = challenge_code("the cat");
+
+        let best = Ghost::find(&history, &code, GhostKind::Best).unwrap();
+        assert_eq!(best.name, "fast");
+        let last = Ghost::find(&history, &code, GhostKind::Last).unwrap();
+        assert_eq!(last.name, "slow");
+        assert!(Ghost::find(&history, &challenge_code("a cat"), GhostKind::Last).is_none());
+
+        assert_eq!(best.position_at(Duration::ZERO), 1);
+        assert_eq!(best.position_at(Duration::from_millis(250)), 3);
+        // the third touch was typed at 200 ms
+        let lead = best.lead(3, Duration::from_millis(150)).unwrap();
+        assert!((lead - 0.05).abs() < 1e-6);
+        assert_eq!(best.lead(8, Duration::from_millis(150)), None);
+    }
+}
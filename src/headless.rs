@@ -0,0 +1,224 @@
+//! The application without a display, to test it end to end.
+//!
+//! Headless: sends the events to the application and to the practice like
+//! the GTK interface does between its components, and keeps a log of every
+//! event the application took.
+//! Script: keystrokes to type, with synthetic timestamps.
+
+use anyhow::Result;
+use rand::Rng;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::driver::Driver;
+use crate::flow::{Event, Flow};
+use crate::model::{Practice, Touch};
+use crate::session::Report;
+use crate::stats::Stats;
+use crate::trainer::Trainer;
+
+/// The application and the practice it shows, driven by events.
+pub struct Headless<R> {
+    flow: Flow<R>,
+    driver: Driver,
+    /// The speed shown as the practice goes.
+    pub stats: Stats,
+    /// Every event sent to the application so far, in order.
+    pub log: Vec<Event>,
+}
+
+/// Keystrokes typed at given offsets from the start.
+#[derive(Clone, Debug, Default)]
+pub struct Script {
+    keys: Vec<(Touch, Duration)>,
+    /// How long each key is held, less than the time to the next key.
+    hold: Duration,
+}
+
+impl<R: Rng> Headless<R> {
+    /// Starts with `practice`.
+    pub fn new(trainer: Trainer<R>, practice: Practice) -> Headless<R> {
+        Headless {
+            stats: Stats::new().with_idle(trainer.idle().threshold),
+            flow: Flow::new(trainer),
+            driver: Driver::new(practice),
+            log: vec![],
+        }
+    }
+
+    /// Returns the state of the application.
+    pub fn flow(&self) -> &Flow<R> {
+        &self.flow
+    }
+
+    /// Returns the state of the application, to change its trainer.
+    pub fn flow_mut(&mut self) -> &mut Flow<R> {
+        &mut self.flow
+    }
+
+    /// Returns the practice shown.
+    pub fn practice(&self) -> &Practice {
+        self.driver.practice()
+    }
+
+    /// Returns the report of the practice that just ended, while it is
+    /// shown.
+    pub fn report(&self) -> Option<&Report> {
+        self.flow.last().map(|(_, report)| report)
+    }
+
+    /// Sends `event` to the application, then the events it returns to the
+    /// practice and those the practice returns to the application, until
+    /// none is left.
+    pub fn send(&mut self, event: Event) -> Result<()> {
+        let mut queue = VecDeque::from([event]);
+        while let Some(event) = queue.pop_front() {
+            self.log.push(event.clone());
+            for event in self.flow.update(event)? {
+                self.show(&event);
+                // the caller ticks often enough
+                let events = self.driver.handle(event)?;
+                queue.extend(events.into_iter().filter(|e| !matches!(e, Event::Wake(_))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the speed with an event for the practice, like the
+    /// statistics component does.
+    fn show(&mut self, event: &Event) {
+        match event {
+            Event::Start(_) => {
+                self.stats = Stats::new().with_idle(self.flow.trainer().idle().threshold)
+            }
+            Event::Press(_, ts) => self.stats.add(*ts),
+            Event::Pause(_) => self.stats.pause(),
+            _ => (),
+        }
+    }
+
+    /// Types `touch` at `ts`.
+    pub fn press(&mut self, touch: Touch, ts: Instant) -> Result<()> {
+        self.send(Event::Press(touch, ts))
+    }
+
+    /// Releases the key typed for `touch` at `ts`.
+    pub fn release(&mut self, touch: Touch, ts: Instant) -> Result<()> {
+        self.send(Event::Release(touch, ts))
+    }
+
+    /// Ends a timed practice whose time is up at `now`, and pauses the
+    /// practice left idle long enough when the trainer says so, like the
+    /// timers of the application.
+    pub fn tick(&mut self, now: Instant) -> Result<()> {
+        self.send(Event::Tick(now))?;
+        if self.is_idle(now) {
+            self.send(Event::Pause(now))?;
+        }
+        Ok(())
+    }
+
+    fn is_idle(&self, now: Instant) -> bool {
+        let Some(after) = self.flow.trainer().idle().auto_pause else {
+            return false;
+        };
+        self.flow.last().is_none()
+            && !self.flow.is_paused()
+            && self
+                .practice()
+                .since_last_touch(now)
+                .is_some_and(|idle| idle >= after)
+    }
+
+    /// Stops the clock, the keys are ignored until resumed.
+    pub fn pause(&mut self, ts: Instant) -> Result<()> {
+        self.send(Event::Pause(ts))
+    }
+
+    /// Starts the clock again at `ts`.
+    pub fn resume(&mut self, ts: Instant) -> Result<()> {
+        self.send(Event::Resume(ts))
+    }
+
+    /// Ends the practice before its end, it is saved as incomplete.
+    pub fn abandon(&mut self) -> Result<()> {
+        self.send(Event::Abandon)
+    }
+
+    /// Starts the practice that just ended, or the current one, again.
+    pub fn retry(&mut self) -> Result<()> {
+        self.send(Event::Retry)
+    }
+
+    /// Practises the words missed in the practice whose report is shown.
+    pub fn missed(&mut self) -> Result<()> {
+        self.send(Event::Missed)
+    }
+
+    /// Starts a new practice from the trainer.
+    pub fn next_practice(&mut self) -> Result<()> {
+        self.send(Event::Next)
+    }
+
+    /// Types the keys of `script` as if it started at `start`.
+    pub fn type_script(&mut self, script: &Script, start: Instant) -> Result<()> {
+        for (touch, ts) in script.keys(start) {
+            self.tick(ts)?;
+            self.press(touch, ts)?;
+            self.tick(ts + script.hold)?;
+            self.release(touch, ts + script.hold)?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether an event matching `f` was sent to the application.
+    pub fn handled(&self, f: impl Fn(&Event) -> bool) -> bool {
+        self.log.iter().any(f)
+    }
+}
+
+impl Script {
+    /// Types `text` without mistakes, one touch every `interval`, each key
+    /// held half of it.
+    pub fn typing(text: &str, interval: Duration) -> Script {
+        Script {
+            keys: text
+                .chars()
+                .enumerate()
+                .map(|(i, c)| (Touch::from(c), interval * i as u32))
+                .collect(),
+            hold: interval / 2,
+        }
+    }
+
+    /// Types `touch` instead of the `i`-th touch.
+    pub fn typo(mut self, i: usize, touch: Touch) -> Script {
+        if let Some(key) = self.keys.get_mut(i) {
+            key.0 = touch;
+        }
+        self
+    }
+
+    /// Waits `duration` more before the touches following the `i`-th.
+    pub fn pause(mut self, i: usize, duration: Duration) -> Script {
+        for key in self.keys.iter_mut().skip(i + 1) {
+            key.1 += duration;
+        }
+        self
+    }
+
+    /// Returns the touches and when they are typed.
+    pub fn keys(&self, start: Instant) -> impl Iterator<Item = (Touch, Instant)> + '_ {
+        self.keys.iter().map(move |(t, at)| (*t, start + *at))
+    }
+
+    /// Returns the number of touches typed.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns whether no touch is typed.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
@@ -0,0 +1,91 @@
+//! Time spent away from the keyboard, left out of the timings.
+//!
+//! Idle: how long a gap between two touches may be before it no longer
+//! counts as typing, when a gap makes a session suspicious and whether the
+//! practice pauses by itself.
+//! Gap: the time left out before a touch.
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::time::Duration;
+
+/// How the time away from the keyboard is handled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Idle {
+    /// A longer gap between two touches counts as this long.
+    pub threshold: Duration,
+    /// A session leaving out a gap this long is flagged in the history.
+    pub suspicious: Duration,
+    /// Pauses the practice after that long without a touch, if set.
+    pub auto_pause: Option<Duration>,
+}
+
+impl Default for Idle {
+    fn default() -> Self {
+        Idle {
+            threshold: Duration::from_secs(5),
+            suspicious: Duration::from_secs(60),
+            auto_pause: None,
+        }
+    }
+}
+
+/// Time left out of the timings before the keystroke `at`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Gap {
+    /// Index of the keystroke typed after the gap.
+    pub at: usize,
+    /// The time left out.
+    pub idle: Duration,
+}
+
+/// Splits `len` keystrokes into the active segments between `gaps`.
+pub fn segments(len: usize, gaps: &[Gap]) -> Vec<Range<usize>> {
+    let mut start = 0;
+    let mut segments = vec![];
+    for gap in gaps.iter().filter(|g| g.at > 0 && g.at < len) {
+        segments.push(start..gap.at);
+        start = gap.at;
+    }
+    if start < len {
+        segments.push(start..len);
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::{Duration, Instant};
+
+    use super::{segments, Gap};
+    use crate::model::{Challenge, Practice, Touch};
+
+    #[test]
+    pub fn it_leaves_idle_gaps_out() {
+        let challenge = Challenge::from_str("abcd").unwrap();
+        let mut practice =
+            Practice::new(challenge, "test".to_string()).with_idle(Duration::from_secs(2));
+        let start = Instant::now();
+        let seconds = [0, 1, 31, 32];
+        for (c, s) in "abcd".chars().zip(seconds) {
+            practice.press(&Touch::from(c), start + Duration::from_secs(s));
+        }
+        let at: Vec<u64> = practice
+            .keystrokes()
+            .iter()
+            .map(|k| k.at.as_secs())
+            .collect();
+        assert_eq!(at, vec![0, 1, 3, 4]);
+        let gaps = practice.gaps();
+        assert_eq!(
+            gaps,
+            &[Gap {
+                at: 2,
+                idle: Duration::from_secs(28)
+            }]
+        );
+        assert_eq!(segments(4, gaps), vec![0..2, 2..4]);
+        assert_eq!(segments(0, &[]), vec![]);
+    }
+}
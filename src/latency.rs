@@ -0,0 +1,106 @@
+//! Input latency, from the key event to the frame showing the keystroke.
+//!
+//! Latency: how long after its key event a keystroke was handled by the
+//! application, drawn and painted on screen.
+//! LatencyStats: the median of each stage and the worst paint over a
+//! series of keystrokes.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long a keystroke took to show, each stage since its key event.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Latency {
+    /// Index of the keystroke.
+    pub at: usize,
+    /// From the key event to the practice receiving it.
+    pub handled: Duration,
+    /// To the end of the redraw of the practice.
+    pub drawn: Duration,
+    /// To the end of the paint of the frame showing it.
+    pub painted: Duration,
+}
+
+/// A summary of the latencies of a series of keystrokes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencyStats {
+    /// Number of keystrokes measured.
+    pub count: usize,
+    /// The median of each stage.
+    pub median: Latency,
+    /// The longest time to paint.
+    pub worst: Duration,
+}
+
+/// Returns the median of the durations `stage` of `latencies`.
+fn median(latencies: &[Latency], stage: fn(&Latency) -> Duration) -> Duration {
+    let mut durations: Vec<Duration> = latencies.iter().map(stage).collect();
+    durations.sort();
+    durations
+        .get(durations.len() / 2)
+        .copied()
+        .unwrap_or_default()
+}
+
+impl LatencyStats {
+    /// Summarises `latencies`, None if there are none.
+    pub fn new(latencies: &[Latency]) -> Option<LatencyStats> {
+        let worst = latencies.iter().map(|l| l.painted).max()?;
+        Some(LatencyStats {
+            count: latencies.len(),
+            median: Latency {
+                at: 0,
+                handled: median(latencies, |l| l.handled),
+                drawn: median(latencies, |l| l.drawn),
+                painted: median(latencies, |l| l.painted),
+            },
+            worst,
+        })
+    }
+}
+
+impl std::fmt::Display for LatencyStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "latency: handled {} ms, drawn {} ms, painted {} ms, worst {} ms",
+            self.median.handled.as_millis(),
+            self.median.drawn.as_millis(),
+            self.median.painted.as_millis(),
+            self.worst.as_millis()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Latency, LatencyStats};
+
+    #[test]
+    pub fn it_summarises_the_latencies() {
+        assert_eq!(LatencyStats::new(&[]), None);
+        let ms = Duration::from_millis;
+        let latencies: Vec<Latency> = [(1, 2, 10), (2, 4, 12), (1, 3, 40)]
+            .iter()
+            .enumerate()
+            .map(|(at, (handled, drawn, painted))| Latency {
+                at,
+                handled: ms(*handled),
+                drawn: ms(*drawn),
+                painted: ms(*painted),
+            })
+            .collect();
+        let stats = LatencyStats::new(&latencies).unwrap();
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.median.handled, ms(1));
+        assert_eq!(stats.median.drawn, ms(3));
+        assert_eq!(stats.median.painted, ms(12));
+        assert_eq!(stats.worst, ms(40));
+        assert_eq!(
+            stats.to_string(),
+            "latency: handled 1 ms, drawn 3 ms, painted 12 ms, worst 40 ms"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Keyboard layouts: where each key is and which finger types it.
+//!
+//! Layout: rows of keys, loaded from a json file or built in.
+//! Key: a label, the characters it types and its width in units.
+//! Finger: the finger expected to type a key in touch typing.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use crate::model::Touch;
+
+/// Distance in units under which two keys are neighbours, a bit more than
+/// a diagonal on staggered rows.
+const NEIGHBOUR_DISTANCE: f64 = 1.3;
+
+/// The finger typing a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Finger {
+    /// The left little finger.
+    LeftPinky,
+    /// The left ring finger.
+    LeftRing,
+    /// The left middle finger.
+    LeftMiddle,
+    /// The left index finger.
+    LeftIndex,
+    /// Either thumb.
+    Thumb,
+    /// The right index finger.
+    RightIndex,
+    /// The right middle finger.
+    RightMiddle,
+    /// The right ring finger.
+    RightRing,
+    /// The right little finger.
+    RightPinky,
+}
+
+/// The hand of a finger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hand {
+    /// The left hand.
+    Left,
+    /// The right hand.
+    Right,
+    /// The space bar is typed by either thumb.
+    Both,
+}
+
+/// A key of a layout.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Key {
+    /// Shown on the keyboard.
+    pub label: String,
+    /// Typed without modifier.
+    #[serde(default)]
+    pub base: Option<char>,
+    /// Typed with shift.
+    #[serde(default)]
+    pub shift: Option<char>,
+    /// In units, 1 for a letter key.
+    #[serde(default = "one")]
+    pub width: f64,
+    /// Space left before the key in units, as between the halves of a split
+    /// keyboard.
+    #[serde(default)]
+    pub gap: f64,
+    /// The finger expected to type it.
+    pub finger: Finger,
+}
+
+/// The keys of a keyboard.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Layout {
+    /// Like `qwerty`.
+    pub name: String,
+    /// From the top row to the space bar.
+    pub rows: Vec<Vec<Key>>,
+}
+
+/// How a wrong touch relates to the expected one on the keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorKind {
+    /// The keys are next to each other.
+    Neighbour,
+    /// Typed by the same finger.
+    SameFinger,
+    /// Typed by another finger of the same hand.
+    SameHand,
+    /// Typed by the other hand.
+    OtherHand,
+    /// One of the keys is not on the layout.
+    Unknown,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+// TODO should be parsed from some resource files
+/// Labels, widths and fingers of the QWERTY keyboard row by row.
+const QWERTY: &[&[(&str, f64, Finger)]] = {
+    use Finger::*;
+    &[
+        &[
+            ("`~", 1.0, LeftPinky),
+            ("1!", 1.0, LeftPinky),
+            ("2@", 1.0, LeftRing),
+            ("3#", 1.0, LeftMiddle),
+            ("4$", 1.0, LeftIndex),
+            ("5%", 1.0, LeftIndex),
+            ("6^", 1.0, RightIndex),
+            ("7&", 1.0, RightIndex),
+            ("8*", 1.0, RightMiddle),
+            ("9(", 1.0, RightRing),
+            ("0)", 1.0, RightPinky),
+            ("-_", 1.0, RightPinky),
+            ("=+", 1.0, RightPinky),
+            ("del", 1.5, RightPinky),
+        ],
+        &[
+            ("tab", 1.5, LeftPinky),
+            ("qQ", 1.0, LeftPinky),
+            ("wW", 1.0, LeftRing),
+            ("eE", 1.0, LeftMiddle),
+            ("rR", 1.0, LeftIndex),
+            ("tT", 1.0, LeftIndex),
+            ("yY", 1.0, RightIndex),
+            ("uU", 1.0, RightIndex),
+            ("iI", 1.0, RightMiddle),
+            ("oO", 1.0, RightRing),
+            ("pP", 1.0, RightPinky),
+            ("[{", 1.0, RightPinky),
+            ("]}", 1.0, RightPinky),
+            ("\\|", 1.0, RightPinky),
+        ],
+        &[
+            ("caps", 2.0, LeftPinky),
+            ("aA", 1.0, LeftPinky),
+            ("sS", 1.0, LeftRing),
+            ("dD", 1.0, LeftMiddle),
+            ("fF", 1.0, LeftIndex),
+            ("gG", 1.0, LeftIndex),
+            ("hH", 1.0, RightIndex),
+            ("jJ", 1.0, RightIndex),
+            ("kK", 1.0, RightMiddle),
+            ("lL", 1.0, RightRing),
+            (";:", 1.0, RightPinky),
+            ("'\"", 1.0, RightPinky),
+            ("enter", 2.0, RightPinky),
+        ],
+        &[
+            ("shift", 3.0, LeftPinky),
+            ("zZ", 1.0, LeftPinky),
+            ("xX", 1.0, LeftRing),
+            ("cC", 1.0, LeftMiddle),
+            ("vV", 1.0, LeftIndex),
+            ("bB", 1.0, LeftIndex),
+            ("nN", 1.0, RightIndex),
+            ("mM", 1.0, RightIndex),
+            (",<", 1.0, RightMiddle),
+            (".>", 1.0, RightRing),
+            ("/?", 1.0, RightPinky),
+            ("shift", 3.0, RightPinky),
+        ],
+        &[("space", 10.0, Thumb)],
+    ]
+};
+
+impl Finger {
+    /// Returns the hand of the finger.
+    pub fn hand(&self) -> Hand {
+        match self {
+            Finger::LeftPinky | Finger::LeftRing | Finger::LeftMiddle | Finger::LeftIndex => {
+                Hand::Left
+            }
+            Finger::Thumb => Hand::Both,
+            _ => Hand::Right,
+        }
+    }
+}
+
+impl Key {
+    /// Builds a key from two characters, the base and the shifted one, or
+    /// from the name of a key that types nothing.
+    fn from_symbols(symbols: &str, width: f64, finger: Finger) -> Key {
+        let chars: Vec<char> = symbols.chars().collect();
+        match chars.as_slice() {
+            [base, shift] => Key {
+                label: base.to_uppercase().to_string(),
+                base: Some(*base),
+                shift: Some(*shift),
+                width,
+                gap: 0.0,
+                finger,
+            },
+            _ => Key {
+                label: symbols.to_string(),
+                base: (symbols == "space").then_some(' '),
+                shift: None,
+                width,
+                gap: 0.0,
+                finger,
+            },
+        }
+    }
+
+    /// Returns wether typing this key (maybe with shift) gives `touch`.
+    pub fn types(&self, touch: &Touch) -> bool {
+        let c = match touch {
+            Touch::Char(c) => *c,
+            Touch::Space => ' ',
+        };
+        self.base == Some(c) || self.shift == Some(c)
+    }
+}
+
+impl Layout {
+    /// Returns the built-in US qwerty layout.
+    pub fn qwerty() -> Layout {
+        Layout {
+            name: "qwerty".to_string(),
+            rows: QWERTY
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|(symbols, width, finger)| Key::from_symbols(symbols, *width, *finger))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// Reads the layout saved as json at `path`.
+    pub fn load(path: &Path) -> Result<Layout> {
+        let json = fs::read_to_string(path).context(format!("cannot read {:?}", path))?;
+        Ok(serde_json::from_str(json.as_str())?)
+    }
+
+    /// Saves the layout as json at `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .context(format!("cannot create file at {:?}", path))
+    }
+
+    /// Returns the key typing `touch` along with the position of its center,
+    /// in units from the top left corner.
+    pub fn find(&self, touch: &Touch) -> Option<(&Key, (f64, f64))> {
+        self.rows.iter().enumerate().find_map(|(y, row)| {
+            let mut x = 0.0;
+            row.iter().find_map(|key| {
+                x += key.gap;
+                let center = x + key.width / 2.0;
+                x += key.width;
+                key.types(touch).then_some((key, (center, y as f64 + 0.5)))
+            })
+        })
+    }
+
+    /// Returns how `typed` relates to `expected` on this layout.
+    pub fn classify(&self, expected: &Touch, typed: &Touch) -> ErrorKind {
+        let (Some((e, (ex, ey))), Some((t, (tx, ty)))) = (self.find(expected), self.find(typed))
+        else {
+            return ErrorKind::Unknown;
+        };
+        if (ex - tx).hypot(ey - ty) <= NEIGHBOUR_DISTANCE {
+            ErrorKind::Neighbour
+        } else if e.finger == t.finger {
+            ErrorKind::SameFinger
+        } else if e.finger.hand() == t.finger.hand() {
+            ErrorKind::SameHand
+        } else {
+            ErrorKind::OtherHand
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorKind, Layout};
+    use crate::model::Touch;
+
+    #[test]
+    pub fn it_classifies_errors() {
+        let qwerty = Layout::qwerty();
+        let classify = |e: char, t: char| qwerty.classify(&Touch::from(e), &Touch::from(t));
+        assert_eq!(classify('e', 'r'), ErrorKind::Neighbour);
+        assert_eq!(classify('e', 'D'), ErrorKind::Neighbour);
+        assert_eq!(classify('e', 'c'), ErrorKind::SameFinger);
+        assert_eq!(classify('e', 'a'), ErrorKind::SameHand);
+        assert_eq!(classify('e', 'i'), ErrorKind::OtherHand);
+        assert_eq!(classify('e', 'é'), ErrorKind::Unknown);
+    }
+}
@@ -0,0 +1,30 @@
+//! Touch typing practice, without any interface.
+//!
+//! The challenges and their generators, the practices typed against them,
+//! the sessions saved once they end and the statistics computed from them.
+//! The GTK application and the terminal interface are built on top of it,
+//! so can other frontends and analysis scripts.
+
+#![warn(missing_docs)]
+
+pub mod analysis;
+pub mod curriculum;
+pub mod driver;
+pub mod flow;
+pub mod ghost;
+pub mod headless;
+pub mod idle;
+pub mod latency;
+pub mod layout;
+pub mod model;
+pub mod race;
+pub mod replay;
+pub mod review;
+pub mod run_enumerate;
+pub mod session;
+pub mod settings;
+pub mod source;
+pub mod stats;
+pub mod theme;
+pub mod trainer;
+pub mod xkb;
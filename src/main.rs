@@ -1,65 +1,470 @@
 use comp::keyboard::KeyboardComp;
 use comp::practice::PracticeComp;
+use comp::replay::ReplayComp;
+use comp::results::ResultsComp;
+use comp::settings::SettingsComp;
+use comp::statistics::{StatisticsComp, StatisticsMsg};
 use comp::stats::StatsComp;
+use curriculum::{Curriculum, Progress};
+use flow::{Event, Flow};
+use ghost::{Ghost, GhostKind};
+use idle::Idle;
 use gtk::prelude::*;
-use model::{Practice, PracticeGenerator};
-use rand::rngs::ThreadRng;
-use rand::thread_rng;
+use layout::Layout;
+use model::{Challenge, Mode, Practice, PracticeGenerator};
+use race::{ClientMsg, Race, RaceClient, Server, ServerMessages, ServerMsg};
+use rand::rngs::{StdRng, ThreadRng};
+use rand::{thread_rng, SeedableRng};
 use relm4::tokio;
 use relm4::{gtk::Inhibit, prelude::*};
+use replay::Replay;
+use session::{data_dir, Session};
+use settings::Settings;
+use theme::Theme;
+use trainer::Trainer;
+use xkb::Geometry;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::convert::identity;
+use std::rc::Rc;
 use std::path::Path;
+use std::str::FromStr;
 use std::time::{Duration, Instant};
 
 mod comp;
-mod model;
 mod msg;
-mod stats;
+mod tui;
 mod utils;
-mod run_enumerate;
+use touchtype::{
+    analysis, curriculum, driver, flow, ghost, idle, latency, layout, model, race, replay,
+    session, settings, stats, theme, trainer, xkb,
+};
 use crate::model::Touch;
 use crate::msg::Msg;
 use crate::stats::Stats;
+use crate::utils::event_instant;
 
 #[derive(Debug)]
 struct UpdateDrawingMsg;
 
+/// Seconds counted down before a paused practice resumes.
+const RESUME_COUNTDOWN: u32 = 3;
+
 struct App {
     stats: Controller<StatsComp>,
     keyboard_state: Controller<KeyboardComp>,
     practice_comp: Controller<PracticeComp>,
-    practice_generator: PracticeGenerator<ThreadRng>,
+    results: Controller<ResultsComp>,
+    replay: Controller<ReplayComp>,
+    /// A saved session is replayed, the keyboard is ignored.
+    replaying: bool,
+    statistics: Controller<StatisticsComp>,
+    settings: Controller<SettingsComp>,
+    /// The trainer and the practices shown, shared with the headless
+    /// harness.
+    flow: Flow<ThreadRng>,
+    /// Which previous session to race, if any.
+    ghost: Option<GhostKind>,
+    /// The race on the local network, until it is ranked.
+    race: Option<(Race, RaceClient)>,
+    /// Seconds left before the paused practice resumes.
+    countdown: Option<u32>,
+    /// The window closes once the current practice is saved.
+    closing: bool,
+    /// Pauses the practice when no key is typed until it fires, emptied
+    /// once it fired.
+    idle_timer: Rc<Cell<Option<gtk::glib::SourceId>>>,
+    /// The bundled themes and those of the user.
+    themes: Vec<Theme>,
+    /// The index of the theme chosen, None to follow the dark mode of the
+    /// desktop.
+    theme: Option<usize>,
+    /// What failed, shown above the practice until dismissed.
+    warnings: Vec<String>,
+}
+
+impl App {
+    fn title(&self) -> String {
+        if let Some((race, _)) = &self.race {
+            let opponents: Vec<String> = race.opponents().into_iter().map(|(n, _)| n).collect();
+            return match race.countdown {
+                Some(seconds) => format!("Type Touching - race starts in {}", seconds),
+                None if race.started => {
+                    format!("Type Touching - racing {}", opponents.join(", "))
+                }
+                None => "Type Touching - waiting for the race".to_string(),
+            };
+        }
+        match self.flow.trainer().lesson() {
+            Some(lesson) => format!("Type Touching - {}", lesson),
+            None => "Type Touching".to_string(),
+        }
+    }
+
+    fn page(&self) -> &'static str {
+        if self.flow.last().is_some() {
+            "results"
+        } else if self.flow.is_paused() {
+            "paused"
+        } else {
+            "practice"
+        }
+    }
+
+    fn pause_label(&self) -> String {
+        match self.countdown {
+            Some(seconds) => format!("Resuming in {}", seconds),
+            None => "Paused".to_string(),
+        }
+    }
+
+    /// Sends `event` to the flow of the practices, then the events it
+    /// returns to the components. `pressed` is the key event of a press.
+    fn send(
+        &mut self,
+        event: Event,
+        pressed: Option<&Msg>,
+        sender: &ComponentSender<Self>,
+        root: &gtk::Window,
+    ) {
+        let events = match self.flow.update(event) {
+            Ok(events) => events,
+            Err(e) => {
+                self.warnings.push(format!("{:#}", e));
+                return;
+            }
+        };
+        for event in events {
+            match event {
+                Event::Start(practice) => {
+                    self.stop_idle_timer();
+                    self.race(&practice);
+                    self.countdown = None;
+                    let msg = Msg::PracticeStart(practice);
+                    self.practice_comp.emit(msg.clone());
+                    self.stats.emit(msg.clone());
+                    self.keyboard_state.emit(msg);
+                }
+                Event::Press(_, _) => {
+                    self.race_progress();
+                    self.pause_when_idle(sender);
+                    if let Some(msg) = pressed {
+                        self.practice_comp.emit(msg.clone());
+                        self.stats.emit(msg.clone());
+                        self.keyboard_state.emit(msg.clone());
+                    }
+                }
+                Event::Release(touch, ts) => self.practice_comp.emit(Msg::KeyReleased(touch, ts)),
+                Event::Extend(words) => self.practice_comp.emit(Msg::PracticeExtend(words)),
+                Event::Pause(ts) => {
+                    self.countdown = None;
+                    self.practice_comp.emit(Msg::PracticePause(ts));
+                    self.stats.emit(Msg::PracticePause(ts));
+                }
+                Event::Resume(ts) => {
+                    self.countdown = None;
+                    self.practice_comp.emit(Msg::PracticeResume(ts));
+                    self.stats.emit(Msg::PracticeResume(ts));
+                }
+                Event::Abandon => self.practice_comp.emit(Msg::PracticeAbandon),
+                Event::Retry => self.practice_comp.emit(Msg::PracticeRetry),
+                Event::Report(report) => {
+                    self.take_warnings();
+                    self.results.emit(Msg::PracticeReport(report));
+                    self.race_ranking();
+                    if self.closing {
+                        root.destroy();
+                    }
+                }
+                // for the application only, the practice keeps its own clock
+                Event::Tick(_)
+                | Event::Grow(_)
+                | Event::Wake(_)
+                | Event::End(_)
+                | Event::Missed
+                | Event::Next => (),
+            }
+        }
+    }
+
+    /// Pauses the practice if no other key is typed for long enough, when
+    /// asked to. The timer of the previous key is dropped.
+    fn pause_when_idle(&self, sender: &ComponentSender<Self>) {
+        self.stop_idle_timer();
+        let Some(after) = self.flow.trainer().idle().auto_pause else {
+            return;
+        };
+        let sender = sender.clone();
+        let keys = self.flow.keys();
+        let timer = self.idle_timer.clone();
+        let id = gtk::glib::timeout_add_local_once(after, move || {
+            // a timer that fired cannot be removed
+            timer.take();
+            sender.input(Msg::PracticeIdle(keys))
+        });
+        self.idle_timer.set(Some(id));
+    }
+
+    /// Shows the warnings of the trainer.
+    fn take_warnings(&mut self) {
+        for e in self.flow.trainer_mut().take_warnings() {
+            self.warnings.push(format!("{:#}", e));
+        }
+    }
+
+    fn stop_idle_timer(&self) {
+        if let Some(timer) = self.idle_timer.take() {
+            timer.remove();
+        }
+    }
+
+    /// Returns whether the current practice can be paused, a race does not
+    /// wait.
+    fn can_pause(&self) -> bool {
+        self.flow.last().is_none() && !self.replaying && self.race.is_none()
+    }
+
+    fn theme_names(&self) -> Vec<&str> {
+        let mut names = vec!["system"];
+        names.extend(self.themes.iter().map(|t| t.name.as_str()));
+        names
+    }
+
+    /// Returns the theme chosen, or the first one matching the dark mode of
+    /// the desktop.
+    fn current_theme(&self) -> Theme {
+        if let Some(theme) = self.theme.and_then(|i| self.themes.get(i)) {
+            return theme.clone();
+        }
+        let dark = gtk::Settings::default().is_some_and(|s| {
+            s.is_gtk_application_prefer_dark_theme()
+                || s.gtk_theme_name()
+                    .is_some_and(|n| n.to_lowercase().ends_with("dark"))
+        });
+        Theme::matching(&self.themes, dark)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn apply_theme(&self) {
+        let theme = self.current_theme();
+        self.practice_comp.emit(Msg::Theme(Box::new(theme.clone())));
+        self.results.emit(Msg::Theme(Box::new(theme.clone())));
+        self.keyboard_state.emit(Msg::Theme(Box::new(theme)));
+    }
+
+    fn source_names(&self) -> Vec<&'static str> {
+        self.flow.trainer().sources().iter().map(|s| s.name).collect()
+    }
+
+    /// Returns the index of the current source in the registry.
+    fn source_index(&self) -> u32 {
+        let name = self.flow.trainer().source().name;
+        self.source_names()
+            .iter()
+            .position(|n| *n == name)
+            .unwrap_or_default() as u32
+    }
+
+    /// Sends the ghost to race in `practice` to the components showing it.
+    fn race(&self, practice: &Practice) {
+        let ghost = self
+            .ghost
+            .and_then(|kind| Ghost::find(self.flow.trainer().history(), &practice.challenge().code(), kind));
+        self.practice_comp.emit(Msg::GhostRace(ghost.clone()));
+        self.stats.emit(Msg::GhostRace(ghost));
+    }
+
+    /// Tells the server how far we are in the race.
+    fn race_progress(&mut self) {
+        let Some((race, client)) = &mut self.race else {
+            return;
+        };
+        let Some(position) = race.positions.get_mut(race.id) else {
+            return;
+        };
+        *position += 1;
+        let position = *position;
+        if let Err(e) = client.send(&ClientMsg::Progress { position }) {
+            self.warnings
+                .push(format!("cannot send the race progress: {:#}", e));
+        }
+    }
+
+    /// Shows the ranking of the race, if known, and leaves it.
+    fn race_ranking(&mut self) {
+        let Some(ranks) = self.race.as_ref().and_then(|(r, _)| r.ranking.clone()) else {
+            return;
+        };
+        self.results.emit(Msg::Race(ServerMsg::Ranking { ranks }));
+        self.practice_comp.emit(Msg::RaceOpponents(vec![]));
+        self.race = None;
+    }
 }
 
 #[relm4::component]
 impl Component for App {
-    type Init = (Practice, PracticeGenerator<ThreadRng>);
+    type Init = (
+        Practice,
+        Trainer<ThreadRng>,
+        Layout,
+        Option<GhostKind>,
+        Option<Replay>,
+        Option<(Race, RaceClient, ServerMessages)>,
+        Option<String>,
+    );
     type Input = Msg;
     type Output = Msg;
     type CommandOutput = Msg;
 
     view! {
         gtk::Window {
-            set_title: Some("Type Touching"),
+            #[watch]
+            set_title: Some(&model.title()),
             set_default_size: (800, 640),
+            connect_is_active_notify[sender] => move |window| {
+                if !window.is_active() {
+                    sender.input(Msg::PracticePause(Instant::now()));
+                }
+            },
+            connect_close_request[sender] => move |_| {
+                sender.input(Msg::Close);
+                Inhibit(true)
+            },
+            #[wrap(Some)]
+            set_titlebar = &gtk::HeaderBar {
+                pack_start = &gtk::DropDown::from_strings(&model.source_names()) {
+                    set_can_focus: false,
+                    #[watch]
+                    #[block_signal(source_handler)]
+                    set_selected: model.source_index(),
+                    #[watch]
+                    set_tooltip_text: Some(model.flow.trainer().source().description),
+                    connect_selected_notify[sender] => move |d| {
+                        sender.input(Msg::SourceSelected(d.selected()))
+                    } @source_handler,
+                },
+                pack_end = &gtk::DropDown::from_strings(&model.theme_names()) {
+                    set_can_focus: false,
+                    set_tooltip_text: Some("theme"),
+                    set_selected: model.theme.map_or(0, |i| i as u32 + 1),
+                    connect_selected_notify[sender] => move |d| {
+                        sender.input(Msg::ThemeSelected(d.selected()))
+                    },
+                },
+                pack_end = &gtk::Button {
+                    set_label: "Settings",
+                    set_focus_on_click: false,
+                    set_can_focus: false,
+                    connect_clicked => Msg::ShowSettings,
+                },
+                pack_end = &gtk::Button {
+                    set_label: "Statistics",
+                    // typing must not click it
+                    set_focus_on_click: false,
+                    set_can_focus: false,
+                    connect_clicked => Msg::ShowStatistics,
+                },
+            },
             add_controller = gtk::EventControllerKey {
-                connect_key_pressed[sender] => move |_, keyval, _, state| {
-                    let now = Instant::now();
+                connect_key_pressed[sender, held] => move |controller, keyval, keycode, state| {
+                    // when the key was pressed, not when the event is handled
+                    let time = controller.current_event_time();
+                    let now = event_instant(time);
+                    if keyval == gtk::gdk::Key::Escape {
+                        sender.input(Msg::Escape);
+                        return Inhibit(true);
+                    }
                     if let Some(touch) = keyval.to_unicode().map(Touch::from) {
-                        sender.input(Msg::KeyPressed(keyval, touch, state, now));
+                        held.borrow_mut().insert(keycode, touch);
+                        sender.input(Msg::KeyPressed(keyval, touch, state, now, time));
                     }
                     Inhibit(false)
-                }
+                },
+                // the modifiers may have changed since the press, the key
+                // tells which touch is released
+                connect_key_released[sender, held] => move |controller, _, keycode, _| {
+                    let now = event_instant(controller.current_event_time());
+                    if let Some(touch) = held.borrow_mut().remove(&keycode) {
+                        sender.input(Msg::KeyReleased(touch, now));
+                    }
+                },
             },
             gtk::Box {
-              set_orientation: gtk::Orientation::Vertical,
-              set_spacing: 10,
-              #[local_ref]
-              my_stats -> gtk::Label {set_opacity: 0.7},
-              #[local_ref]
-              my_practice -> gtk::Box {},
-              #[local_ref]
-              my_ks -> gtk::Box {},
+                set_orientation: gtk::Orientation::Vertical,
+                gtk::Box {
+                    set_spacing: 10,
+                    set_margin_all: 6,
+                    #[watch]
+                    set_visible: !model.warnings.is_empty(),
+                    gtk::Label {
+                        #[watch]
+                        set_label: &model.warnings.join("\n"),
+                        add_css_class: "error",
+                        set_hexpand: true,
+                        set_xalign: 0.0,
+                        set_wrap: true,
+                    },
+                    gtk::Button {
+                        set_label: "Dismiss",
+                        set_can_focus: false,
+                        set_valign: gtk::Align::Start,
+                        connect_clicked => Msg::DismissWarnings,
+                    },
+                },
+                #[name = "stack"]
+                gtk::Stack {
+                  #[watch]
+                  set_visible_child_name: model.page(),
+                  add_named[Some("practice")] = &gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 10,
+                    #[local_ref]
+                    my_stats -> gtk::Label {set_opacity: 0.7},
+                    #[local_ref]
+                    my_practice -> gtk::Box {},
+                    #[local_ref]
+                    my_ks -> gtk::Box {},
+                    #[local_ref]
+                    my_replay -> gtk::Box {
+                        set_visible: model.replaying,
+                        set_margin_all: 10,
+                    },
+                  },
+                  add_named[Some("paused")] = &gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 10,
+                    set_valign: gtk::Align::Center,
+                    set_halign: gtk::Align::Center,
+                    gtk::Label {
+                        #[watch]
+                        set_label: &model.pause_label(),
+                        add_css_class: "title-1",
+                    },
+                    gtk::Label {
+                        set_label: "Escape to resume",
+                        set_opacity: 0.7,
+                    },
+                    gtk::Box {
+                        set_spacing: 10,
+                        gtk::Button {
+                            set_label: "Resume",
+                            set_can_focus: false,
+                            connect_clicked => Msg::PracticeCountdown(RESUME_COUNTDOWN),
+                        },
+                        gtk::Button {
+                            set_label: "Restart",
+                            set_can_focus: false,
+                            connect_clicked => Msg::PracticeRetry,
+                        },
+                        gtk::Button {
+                            set_label: "Abandon",
+                            set_can_focus: false,
+                            connect_clicked => Msg::PracticeAbandon,
+                        },
+                    },
+                  },
+                },
             },
         },
 
@@ -70,23 +475,101 @@ impl Component for App {
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let (practice, practice_generator) = init;
-        let stats = StatsComp::builder().launch(Stats::new()).detach();
-        let keyboard_state = KeyboardComp::builder().launch(()).detach();
+        let (practice, trainer, layout, ghost, replay, race, theme) = init;
+        let replaying = replay.is_some();
+        let stats = StatsComp::builder()
+            .launch(Stats::new().with_idle(trainer.idle().threshold))
+            .detach();
+        let keyboard_state = KeyboardComp::builder().launch(layout.clone()).detach();
         let msg = Msg::PracticeStart(practice.clone());
         let practice_comp = PracticeComp::builder()
             .launch(practice)
             .forward(sender.input_sender(), identity);
-        let model = App {
+        let results = ResultsComp::builder()
+            .launch(())
+            .forward(sender.input_sender(), identity);
+        let replay = ReplayComp::builder()
+            .launch(replay)
+            .forward(sender.input_sender(), identity);
+        let statistics = StatisticsComp::builder()
+            .transient_for(root)
+            .launch(layout)
+            .detach();
+        let mut warnings = vec![];
+        let preferences = Settings::load(data_dir().as_path()).unwrap_or_else(|e| {
+            warnings.push(format!("cannot load the settings: {:#}", e));
+            Settings::default()
+        });
+        practice_comp.emit(Msg::Settings(preferences.clone()));
+        let settings = SettingsComp::builder()
+            .transient_for(root)
+            .launch(preferences)
+            .forward(sender.input_sender(), identity);
+        let (themes, errors) = Theme::all(&data_dir().join("themes"));
+        for e in errors {
+            warnings.push(format!("cannot load the theme: {:#}", e));
+        }
+        let mut model = App {
             stats,
             keyboard_state,
             practice_comp,
-            practice_generator,
+            results,
+            replay,
+            replaying,
+            statistics,
+            settings,
+            flow: Flow::new(trainer),
+            ghost,
+            race: None,
+            countdown: None,
+            closing: false,
+            idle_timer: Rc::new(Cell::new(None)),
+            themes,
+            theme: None,
+            warnings,
         };
+        model.take_warnings();
+        model.theme = theme.and_then(|name| model.themes.iter().position(|t| t.name == name));
+        model.apply_theme();
+        if let Some(settings) = gtk::Settings::default() {
+            let input = sender.clone();
+            settings.connect_gtk_application_prefer_dark_theme_notify(move |_| {
+                input.input(Msg::DarkModeChanged)
+            });
+            let input = sender.clone();
+            settings.connect_gtk_theme_name_notify(move |_| input.input(Msg::DarkModeChanged));
+        }
+        if let Some((race, client, messages)) = race {
+            let input = sender.input_sender().clone();
+            std::thread::spawn(move || {
+                for msg in messages {
+                    let msg = match msg {
+                        Ok(msg) => msg,
+                        Err(e) => {
+                            let _ = input.send(Msg::Warning(format!(
+                                "the race server left: {:#}",
+                                e
+                            )));
+                            break;
+                        }
+                    };
+                    // the window was closed
+                    if input.send(Msg::Race(msg)).is_err() {
+                        break;
+                    }
+                }
+            });
+            model.race = Some((race, client));
+        }
         let my_stats = model.stats.widget();
         let my_ks = model.keyboard_state.widget();
         let my_practice = model.practice_comp.widget();
+        let my_replay = model.replay.widget();
+        let held: Rc<RefCell<HashMap<u32, Touch>>> = Rc::default();
         let widgets = view_output!();
+        widgets
+            .stack
+            .add_named(model.results.widget(), Some("results"));
         sender.command(|out, shutdown| {
             shutdown.register(async move{
                 tokio::time::sleep(Duration::from_millis(200)).await;
@@ -97,26 +580,135 @@ impl Component for App {
 
         ComponentParts { model, widgets }
     }
-    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
         match msg {
-            Msg::KeyPressed(_, _, _, _) | Msg::PracticeStart(_) => {
-                println!("emitting {:?}", msg);
-                self.practice_comp.emit(msg.clone());
-                self.stats.emit(msg.clone());
-                self.keyboard_state.emit(msg.clone());
-            }
-            Msg::PracticeEnd(practice) => {
-                let home = env!("HOME");
-                let path = Path::new(home).join(".config/touchtype");
-                practice
-                    .save(path.as_path())
-                    .expect("practice should be saved");
-                let practice = self
-                    .practice_generator
-                    .generate()
-                    .expect("generate a new practice");
-                sender.input(Msg::PracticeStart(practice));
+            // the keys are ignored while a session is replayed
+            Msg::KeyPressed(_, _, _, _, _) if self.replaying => {}
+            // nobody types before the start of a race
+            Msg::KeyPressed(_, _, _, _, _)
+                if self.race.as_ref().is_some_and(|(r, _)| !r.started) => {}
+            Msg::KeyPressed(_, touch, _, ts, _) => {
+                self.send(Event::Press(touch, ts), Some(&msg), &sender, root)
+            }
+            Msg::PracticeStart(practice) => {
+                self.send(Event::Start(practice), None, &sender, root)
+            }
+            Msg::PracticeGrow(missing) => self.send(Event::Grow(missing), None, &sender, root),
+            Msg::PracticeExtend(_) | Msg::PracticeTimeUp(_) => {
+                self.practice_comp.emit(msg);
+            }
+            Msg::Replayed(msg) => {
+                self.practice_comp.emit(*msg.clone());
+                self.stats.emit(*msg.clone());
+                self.keyboard_state.emit(*msg);
+            }
+            // a replayed session is not saved again
+            Msg::PracticeEnd(_) if self.replaying => (),
+            Msg::PracticeEnd(practice) => self.send(Event::End(practice), None, &sender, root),
+            // losing the focus pauses only a practice under way
+            Msg::PracticePause(ts) if self.flow.is_typing() && self.can_pause() => {
+                self.send(Event::Pause(ts), None, &sender, root)
+            }
+            Msg::PracticeIdle(keys)
+                if keys == self.flow.keys() && !self.flow.is_paused() && self.can_pause() =>
+            {
+                self.send(Event::Pause(Instant::now()), None, &sender, root)
+            }
+            Msg::Escape if self.flow.is_paused() && self.countdown.is_none() => {
+                sender.input(Msg::PracticeCountdown(RESUME_COUNTDOWN));
+            }
+            Msg::Escape if self.can_pause() => {
+                self.send(Event::Pause(Instant::now()), None, &sender, root)
+            }
+            Msg::PracticeCountdown(seconds) => {
+                // a countdown is dropped when paused again meanwhile
+                let expected = match self.countdown {
+                    Some(countdown) => countdown.checked_sub(1),
+                    None if self.flow.is_paused() => Some(RESUME_COUNTDOWN),
+                    None => None,
+                };
+                if expected != Some(seconds) {
+                    return;
+                }
+                if seconds == 0 {
+                    self.send(Event::Resume(Instant::now()), None, &sender, root);
+                    return;
+                }
+                self.countdown = Some(seconds);
+                let sender = sender.clone();
+                gtk::glib::timeout_add_local_once(Duration::from_secs(1), move || {
+                    sender.input(Msg::PracticeCountdown(seconds - 1))
+                });
+            }
+            Msg::PracticeAbandon if !self.replaying => {
+                self.send(Event::Abandon, None, &sender, root)
+            }
+            Msg::Close if self.flow.is_typing() && self.flow.last().is_none() && !self.replaying => {
+                self.closing = true;
+                self.send(Event::Abandon, None, &sender, root);
+            }
+            Msg::Close => root.destroy(),
+            Msg::Race(msg) => {
+                let Some((race, _)) = &mut self.race else {
+                    return;
+                };
+                race.apply(&msg);
+                match msg {
+                    ServerMsg::Progress { .. } => {
+                        self.practice_comp.emit(Msg::RaceOpponents(race.opponents()))
+                    }
+                    // the ranking waits for the results of our own practice
+                    ServerMsg::Ranking { .. } if self.flow.last().is_some() => self.race_ranking(),
+                    _ => (),
+                }
+            }
+            // only the practice that just ended is retried while replaying
+            Msg::PracticeRetry if self.replaying && self.flow.last().is_none() => (),
+            Msg::PracticeRetry => self.send(Event::Retry, None, &sender, root),
+            Msg::PracticeMissed => self.send(Event::Missed, None, &sender, root),
+            Msg::PracticeNext => self.send(Event::Next, None, &sender, root),
+            Msg::SourceSelected(i) => {
+                let Some(name) = self.source_names().get(i as usize).copied() else {
+                    return;
+                };
+                match self.flow.trainer_mut().select_source(name, &[]) {
+                    Ok(()) => sender.input(Msg::PracticeNext),
+                    Err(e) => self
+                        .warnings
+                        .push(format!("cannot take the words from {}: {:#}", name, e)),
+                }
+            }
+            Msg::ThemeSelected(i) => {
+                self.theme = (i as usize).checked_sub(1);
+                self.apply_theme();
+            }
+            Msg::DarkModeChanged if self.theme.is_none() => self.apply_theme(),
+            Msg::Settings(settings) => self.practice_comp.emit(Msg::Settings(settings)),
+            Msg::KeyReleased(_, _) if self.replaying => {}
+            Msg::KeyReleased(touch, ts) => {
+                self.send(Event::Release(touch, ts), None, &sender, root)
             }
+            Msg::Warning(warning) => self.warnings.push(warning),
+            Msg::DismissWarnings => self.warnings.clear(),
+            Msg::ShowSettings => self.settings.widget().present(),
+            Msg::ShowStatistics => {
+                self.statistics
+                    .emit(StatisticsMsg::Show(self.flow.trainer().history().clone()));
+                self.statistics.widget().present();
+            }
+            Msg::PracticeReport(_)
+            | Msg::GhostRace(_)
+            | Msg::GhostTick(_)
+            | Msg::RaceOpponents(_)
+            | Msg::PracticePause(_)
+            | Msg::PracticeResume(_)
+            | Msg::PracticeAbandon
+            | Msg::PracticeIdle(_)
+            | Msg::Theme(_)
+            | Msg::DarkModeChanged
+            | Msg::CaretFrame
+            | Msg::Painted(_)
+            | Msg::Escape => (),
         }
     }
     fn update_cmd(
@@ -129,14 +721,239 @@ impl Component for App {
     }
 }
 
+/// Reads which session to race from `--ghost best` or `--ghost last`.
+fn parse_ghost(args: &[String]) -> Option<GhostKind> {
+    let i = args.iter().position(|a| a == "--ghost")?;
+    match args.get(i + 1).map(String::as_str) {
+        Some("last") => Some(GhostKind::Last),
+        _ => Some(GhostKind::Best),
+    }
+}
+
+/// Reads the session to replay from the file given by `--replay PATH`,
+/// what fails is added to `warnings`.
+fn parse_replay(args: &[String], warnings: &mut Vec<anyhow::Error>) -> Option<Replay> {
+    let i = args.iter().position(|a| a == "--replay")?;
+    let path = args.get(i + 1)?;
+    match Session::load(Path::new(path)) {
+        Ok(session) => Some(Replay::new(session, Instant::now())),
+        Err(e) => {
+            warnings.push(e.context("cannot load the session to replay"));
+            None
+        }
+    }
+}
+
+/// Reads the layout from the file given by `--layout PATH`, or imports the
+/// XKB layout `--xkb FILE[(VARIANT)]` on the `--geometry` preset, saved to
+/// `--save-layout PATH` if given. QWERTY if none, what fails is added to
+/// `warnings`.
+fn parse_layout(args: &[String], warnings: &mut Vec<anyhow::Error>) -> Layout {
+    if let Some(spec) = option::<String>(args, "--xkb") {
+        let geometry = args
+            .iter()
+            .position(|a| a == "--geometry")
+            .and_then(|i| args.get(i + 1))
+            .map(|g| g.parse::<Geometry>())
+            .unwrap_or(Ok(Geometry::Ansi));
+        let (path, variant) = xkb::resolve(&spec);
+        let layout = geometry.and_then(|g| xkb::import(&path, variant.as_deref(), g));
+        return match layout {
+            Ok(layout) => {
+                if let Some(path) = option::<String>(args, "--save-layout") {
+                    if let Err(e) = layout.save(Path::new(&path)) {
+                        warnings.push(e.context("cannot save the layout"));
+                    }
+                }
+                layout
+            }
+            Err(e) => {
+                warnings.push(e.context("cannot import the layout, using qwerty"));
+                Layout::qwerty()
+            }
+        };
+    }
+    let path = args
+        .iter()
+        .position(|a| a == "--layout")
+        .and_then(|i| args.get(i + 1));
+    match path.map(|p| Layout::load(Path::new(p))) {
+        Some(Ok(layout)) => layout,
+        Some(Err(e)) => {
+            warnings.push(e.context("cannot load the layout, using qwerty"));
+            Layout::qwerty()
+        }
+        None => Layout::qwerty(),
+    }
+}
+
+/// Returns the value following `--name` on the command line, if any.
+fn option<T: FromStr>(args: &[String], name: &str) -> Option<T> {
+    let i = args.iter().position(|a| a == name)?;
+    args.get(i + 1)?.parse().ok()
+}
+
+/// Joins the race served at `--race HOST[:PORT]` as `--name NAME`, and waits
+/// for its challenge. What fails is added to `warnings`.
+fn parse_race(
+    args: &[String],
+    warnings: &mut Vec<anyhow::Error>,
+) -> Option<(Race, RaceClient, ServerMessages, Practice)> {
+    let mut addr: String = option(args, "--race")?;
+    if !addr.contains(':') {
+        addr = format!("{}:{}", addr, race::DEFAULT_PORT);
+    }
+    let name: String = option(args, "--name")
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_else(|| "player".to_string());
+    let joined = RaceClient::connect(addr.as_str(), &name).and_then(|client| {
+        let mut messages = client.messages()?;
+        println!("waiting for the other players");
+        let (race, text) = Race::join(&mut messages)?;
+        Ok((race, client, messages, text))
+    });
+    match joined {
+        Ok((race, client, messages, text)) => {
+            let challenge = Challenge::from_str(text.as_str()).expect("split the words");
+            let practice = Practice::new(challenge, Practice::new_name().ok()?);
+            Some((race, client, messages, practice))
+        }
+        Err(e) => {
+            warnings.push(e.context("cannot join the race"));
+            None
+        }
+    }
+}
+
+/// Serves a race: `serve --port N --players N --words N --countdown SECONDS`.
+fn serve(args: &[String]) -> anyhow::Result<()> {
+    let port = option(args, "--port").unwrap_or(race::DEFAULT_PORT);
+    let players = option(args, "--players").unwrap_or(2);
+    let words = option(args, "--words").unwrap_or(25);
+    let seed = session::timestamp();
+    let rng = StdRng::seed_from_u64(seed);
+    let text = PracticeGenerator::new(rng, words, "./data/t8.shakespeare.freq")
+        .generate()?
+        .challenge()
+        .text();
+    let server = Server::bind(("0.0.0.0", port), players, seed, text)?
+        .with_countdown(option(args, "--countdown").unwrap_or(3));
+    println!("waiting for {} players on {}", players, server.local_addr()?);
+    for (i, rank) in server.run()?.iter().enumerate() {
+        match rank.time_ms {
+            Some(ms) => println!("{}. {} {:.1} s", i + 1, rank.name, ms as f32 / 1000.0),
+            None => println!("{}. {} (left)", i + 1, rank.name),
+        }
+    }
+    Ok(())
+}
+
+/// Reads the source of the words from `--source NAME`, set by any number
+/// of `--source-option KEY=VALUE`.
+fn parse_source(args: &[String]) -> Option<(String, Vec<(String, String)>)> {
+    let name = option(args, "--source")?;
+    let options = args
+        .windows(2)
+        .filter(|w| w[0] == "--source-option")
+        .filter_map(|w| w[1].split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    Some((name, options))
+}
+
+/// Reads when the user is away from the keyboard from the command line:
+/// `--idle SECONDS`, `--suspicious SECONDS` and `--auto-pause SECONDS`.
+fn parse_idle(args: &[String]) -> Idle {
+    let seconds = |name| option(args, name).map(Duration::from_secs);
+    let default = Idle::default();
+    Idle {
+        threshold: seconds("--idle").unwrap_or(default.threshold),
+        suspicious: seconds("--suspicious").unwrap_or(default.suspicious),
+        auto_pause: seconds("--auto-pause"),
+    }
+}
+
+/// Reads the practice mode from the command line:
+/// `--words N` (default 25), `--endless` or `--timed SECONDS`.
+fn parse_mode(args: &[String]) -> Mode {
+    let value = |i: usize| args.get(i + 1).and_then(|v| v.parse::<u64>().ok());
+    let mut mode = Mode::Words(25);
+    for (i, arg) in args.iter().enumerate() {
+        match arg.as_str() {
+            "--endless" => mode = Mode::Endless,
+            "--timed" => mode = Mode::Timed(Duration::from_secs(value(i).unwrap_or(60))),
+            "--words" => mode = Mode::Words(value(i).unwrap_or(25) as usize),
+            _ => (),
+        }
+    }
+    mode
+}
+
 // TODO should be a result later
 fn main() {
-    let app = RelmApp::new("TouchTyping Master");
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("serve") {
+        if let Err(e) = serve(&args) {
+            println!("cannot serve the race: {:?}", e);
+        }
+        return;
+    }
+    // shown by the interface, with those of the trainer
+    let mut warnings = vec![];
+    let mode = parse_mode(&args);
+    let layout = parse_layout(&args, &mut warnings);
+    // lessons restrict the words to the letters unlocked so far
+    let lessons = args.iter().any(|a| a == "--lessons").then(|| {
+        let progress = Progress::load(data_dir().as_path()).unwrap_or_else(|e| {
+            warnings.push(e.context("cannot load the progress"));
+            Progress::default()
+        });
+        (Curriculum::new(&layout), progress)
+    });
     let rng = thread_rng();
-    let mut practice_generator =
-        PracticeGenerator::<ThreadRng>::new(rng, 25, "./data/t8.shakespeare.freq");
-    let practice = practice_generator
-        .generate()
-        .expect("should generate first practice");
-    app.run::<App>((practice, practice_generator));
+    let practice_generator =
+        PracticeGenerator::<ThreadRng>::new(rng, 25, "./data/t8.shakespeare.freq")
+            .with_mode(mode)
+            // only the words due for review, if any
+            .with_review_only(args.iter().any(|a| a == "--review"));
+    let mut trainer =
+        Trainer::load(practice_generator, lessons, data_dir()).with_idle(parse_idle(&args));
+    for e in warnings.drain(..) {
+        trainer.warn(e);
+    }
+    if let Some((name, options)) = parse_source(&args) {
+        if let Err(e) = trainer.select_source(&name, &options) {
+            trainer.warn(e.context(format!("cannot take the words from {}", name)));
+        }
+    }
+    // no display is needed in a terminal, it shows the warnings itself
+    if args.iter().any(|a| a == "--tui") {
+        if let Err(e) = tui::run(trainer, layout) {
+            println!("the terminal interface failed: {:?}", e);
+        }
+        return;
+    }
+    // gtk would reject our own arguments
+    let app = RelmApp::new("TouchTyping Master").with_args(args[..1].to_vec());
+    let replay = parse_replay(&args, &mut warnings);
+    let race = parse_race(&args, &mut warnings);
+    for e in warnings {
+        trainer.warn(e);
+    }
+    let practice = match (&replay, &race) {
+        (Some(replay), _) => replay.practice(),
+        (None, Some((_, _, _, practice))) => practice.clone(),
+        (None, None) => trainer.generate().expect("should generate first practice"),
+    };
+    let race = race.map(|(race, client, messages, _)| (race, client, messages));
+    let ghost = parse_ghost(&args);
+    app.run::<App>((
+        practice,
+        trainer,
+        layout,
+        ghost,
+        replay,
+        race,
+        option(&args, "--theme"),
+    ));
 }
@@ -1,48 +1,64 @@
-/// Domain model for the App.
-///
-/// Challenge: A randomly generated sequence of words
-/// Practice: The challenge on top of which we put attempts and a cursor
-/// Touch: Key is overloaded term (GTK) but it just mean a key
-/// Attempt: sequence (sometimes uncomplete) of true or false wether key was
-/// successfull typed.
-///
-///
-use anyhow::{Context, Result};
-use rand::{
-    distributions::{Distribution, WeightedIndex},
-    Rng,
-};
+//! Domain model for the App.
+//!
+//! Challenge: A randomly generated sequence of words
+//! Practice: The challenge on top of which we put attempts and a cursor
+//! Touch: Key is overloaded term (GTK) but it just mean a key
+//! Attempt: sequence (sometimes uncomplete) of keystrokes, each one is
+//! successfull when the typed touch is the expected one.
+//! Mode: fixed number of words, endless (zen) or timed practice. The last two
+//! grow their challenge on demand.
+//! Keystroke: what was expected, what was typed and when.
+
+use anyhow::Result;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::Display,
-    fs,
-    io::Write,
-    path::Path,
     str::FromStr,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use thiserror::Error;
 
+use crate::idle::Gap;
+use crate::latency::Latency;
+use crate::source::{ChallengeSource, Frequency, SourceInfo};
+
 /// Simple type alias for WordIndex
-pub(crate) type WordIndex = usize;
+pub type WordIndex = usize;
+
+/// Number of words that should always be left ahead of the cursor in a
+/// growing practice.
+pub const LOOKAHEAD_WORDS: usize = 15;
 
+/// What can go wrong generating a challenge.
 #[derive(Error, Debug)]
-pub(crate) enum TouchTypingError {
+pub enum TouchTypingError {
+    /// A line of a frequency file is not a word and a count.
     #[error("A line for form 'word(str) count(usize)' was expected")]
     FileParseError,
-    #[error("invalid path")]
-    InvalidPathError,
+    /// There is no source by that name.
+    #[error("No challenge source is named {0}")]
+    UnknownSource(String),
+    /// The source has no option by that name.
+    #[error("The {0} source has no option {1}")]
+    UnknownOption(String, String),
+    /// The value does not suit the option.
+    #[error("{1} is not a valid value for {0}")]
+    InvalidOption(String, String),
 }
 
 /// Differentiates between Space and any other characters.
-#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
-pub(crate) enum Touch {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Touch {
+    /// Any character but a space.
     Char(char),
+    /// The space between two words.
     Space,
 }
 
 /// Sequence of words that the user will try
 #[derive(Debug, Clone)]
-pub(crate) struct Challenge {
+pub struct Challenge {
     /// The words in sequence
     words: Vec<Word>,
     /// number of Touches in the challenge
@@ -53,42 +69,93 @@ pub(crate) struct Challenge {
 }
 
 /// A `Touch` iterator for a challenge
-pub(crate) struct CIter<'a> {
+pub struct CIter<'a> {
     challenge: &'a Challenge,
     word_ix: usize,
     ix: usize,
+    /// The chars of the current word not iterated yet.
+    chars: std::str::Chars<'a>,
+    /// Position of the next touch in the current word.
+    char_ix: usize,
 }
 
 /// New type for a Word which is just a string
 #[derive(Clone, Debug)]
-pub(crate) struct Word(String);
+pub struct Word(String);
 
 /// Records the current progress in the challenge.
-/// `keys[i]` will be true if the touch was expected, otherwise false.
+/// `keystrokes[i]` is what was typed when the touch `i` was expected.
 #[derive(Debug, Clone, Default)]
-pub(crate) struct Attempt {
-    touches: Vec<bool>,
+pub struct Attempt {
+    keystrokes: Vec<Keystroke>,
 }
 
+/// How a practice ends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// A fixed number of words, the practice ends with the last one.
+    Words(usize),
+    /// Zen mode: words keep coming until the user stops.
+    Endless,
+    /// Words keep coming until the duration since the first touch elapsed.
+    Timed(Duration),
+}
+
+/// A touch as typed by the user.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keystroke {
+    /// What the challenge expected.
+    pub expected: Touch,
+    /// What was typed.
+    pub typed: Touch,
+    /// Elapsed time since the first touch of the practice.
+    pub at: Duration,
+    /// How long the key was held, if its release was seen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dwell: Option<Duration>,
+    /// From the release of the previous key to this press, zero if this key
+    /// was pressed first, if both were seen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flight: Option<Duration>,
+}
+
+/// A challenge being typed, and what was typed so far.
 #[derive(Clone)]
-pub(crate) struct Practice {
+pub struct Practice {
     challenge: Challenge,
     attempt: Attempt,
     name: String,
     /// max(index of next touch in the challenge, challenge.len())
     cursor: usize,
+    mode: Mode,
+    /// When the first touch was pressed.
+    started: Option<Instant>,
+    /// When the practice was paused, while it is.
+    paused: Option<Instant>,
+    /// Time spent paused since the start, left out of the timings.
+    pauses: Duration,
+    /// Stopped by the user before its end.
+    abandoned: bool,
+    /// Longer gaps between touches are left out of the timings, if set.
+    idle: Option<Duration>,
+    /// The gaps left out so far.
+    gaps: Vec<Gap>,
+    /// The latency of the keystrokes measured so far.
+    latencies: Vec<Latency>,
+    /// The keystrokes whose key is still held, the last pressed last.
+    held: Vec<usize>,
 }
 
 /// Given an underlying challenge, this is an iterator that
 /// helps displaying the current state of progress.
-pub(crate) struct PIter<'a> {
+pub struct PIter<'a> {
     practice: &'a Practice,
     challenge_iter: CIter<'a>,
 }
 
 /// a `Touch` state within a practice, mostly to help with UI.
 #[derive(Clone)]
-pub(crate) enum TouchState {
+pub enum TouchState {
     /// was attempted successfully or not
     Attempted(bool),
     /// the last one attempted.
@@ -102,15 +169,30 @@ pub(crate) enum TouchState {
 /// A generator for the practice.
 ///
 /// R holds usually a Random Number Generator
-pub(crate) struct PracticeGenerator<R> {
+pub struct PracticeGenerator<R> {
     /// Random Number Generator
     rng: R,
     /// Size in number of words
     size: usize,
-    /// Path to use for generating words.
-    path: String,
+    /// Mode of the generated practices.
+    mode: Mode,
+    /// Where the words come from.
+    source: Box<dyn ChallengeSource>,
+    /// Only words made of these letters are generated, any word if None.
+    /// Kept to restrict the next source too.
+    letters: Option<Vec<char>>,
+    /// Words due for review, mixed into the generated words.
+    review: Vec<Word>,
+    /// Generates only the words due for review, as long as there are some.
+    review_only: bool,
 }
 
+/// Keys held at once, beyond that a release was missed.
+const MAX_HELD: usize = 10;
+
+/// At most one word in this many is a word to review.
+const REVIEW_RATIO: usize = 5;
+
 // Implementations
 
 impl From<char> for Touch {
@@ -125,7 +207,10 @@ impl From<char> for Touch {
 
 impl Display for Touch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(self.to_string().as_str())
+        match self {
+            Touch::Char(c) => write!(f, "{}", c),
+            Touch::Space => f.write_str("␣"),
+        }
     }
 }
 
@@ -145,65 +230,96 @@ impl FromStr for Challenge {
 
 impl From<Vec<Word>> for Challenge {
     fn from(words: Vec<Word>) -> Self {
-        let total_count = words.iter().map(|w| w.len()).sum::<usize>() + words.len() - 1;
-        let mut skip_index: Vec<usize> = vec![];
-        let mut skips: usize = 0;
-        for w in &words {
-            skip_index.push(skips);
-            skips += w.len() + 1;
-        }
-        Challenge {
-            words,
-            total_count,
-            skip_index,
+        let mut challenge = Challenge {
+            words: Vec::with_capacity(words.len()),
+            total_count: 0,
+            skip_index: Vec::with_capacity(words.len()),
+        };
+        challenge.extend(words);
+        challenge
+    }
+}
+
+impl Extend<Word> for Challenge {
+    fn extend<T: IntoIterator<Item = Word>>(&mut self, iter: T) {
+        for word in iter {
+            self.push(word);
         }
     }
 }
 
 impl Challenge {
-    /// Returns a random challenge
+    /// Appends a word at the end of the challenge.
     ///
-    /// # Arguments
-    ///
-    /// * `rng` : a Random number generator
-    /// * `size`: number of words in the challenge
-    /// * `path`: path to the file the .freq files that contains the words to
-    /// sample from,
-    ///
-    pub(crate) fn generate<R: Rng>(rng: &mut R, size: usize, path: &Path) -> Result<Challenge> {
-        let (words, freqs): (Vec<Word>, Vec<u32>) = std::fs::read_to_string(path)
-            .with_context(|| TouchTypingError::FileParseError)?
-            .lines()
-            .filter_map(
-                |line| match line.split(' ').collect::<Vec<&str>>().as_slice() {
-                    [word, count] => {
-                        let count = count.parse::<u32>().ok()?;
-                        Some((Word::from(word), count))
-                    }
-                    _ => None,
-                },
-            )
-            .unzip();
-        let dist = WeightedIndex::new(freqs)?;
-        let words: Vec<Word> = dist
-            .sample_iter(rng)
-            .map(|i| words[i].clone())
-            .take(size)
-            .collect();
-        Ok(Challenge::from(words))
+    /// Touches already expected keep their position, the space separating
+    /// the previous last word from the new one becomes expected.
+    pub fn push(&mut self, word: Word) {
+        let skip = if self.words.is_empty() {
+            0
+        } else {
+            self.total_count + 1
+        };
+        self.total_count = skip + word.len();
+        self.skip_index.push(skip);
+        self.words.push(word);
+    }
+
+    /// Returns the number of words in the challenge.
+    pub fn word_count(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Returns the words in sequence.
+    pub fn words(&self) -> &[Word] {
+        &self.words
+    }
+
+    /// The challenge as a string of space separated words, the inverse
+    /// of `from_str`.
+    pub fn text(&self) -> String {
+        self.words
+            .iter()
+            .map(Word::as_str)
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// Returns a short code naming this challenge, the same for the same
+    /// words.
+    pub fn code(&self) -> String {
+        challenge_code(self.text().as_str())
+    }
+
+    /// Returns the index of the word containing the touch at `position`.
+    /// A space belongs to the word preceding it.
+    pub fn word_index_at(&self, position: usize) -> Option<WordIndex> {
+        if position >= self.total_count {
+            None
+        } else {
+            self.skip_index
+                .partition_point(|x| *x <= position)
+                .checked_sub(1)
+        }
     }
 
     /// Returns an iterator for words in the challenge.
-    pub(crate) fn iter<'a>(&'a self) -> CIter<'a> {
+    pub fn iter<'a>(&'a self) -> CIter<'a> {
         CIter {
             challenge: self,
             word_ix: 0,
             ix: 0,
+            chars: self
+                .words
+                .first()
+                .map(Word::as_str)
+                .unwrap_or_default()
+                .chars(),
+            char_ix: 0,
         }
     }
 
     /// Returns the `Touch` expected at position `count` or None.
-    pub(crate) fn expected_at(&self, position: usize) -> Option<Touch> {
+    pub fn expected_at(&self, position: usize) -> Option<Touch> {
         if position >= self.total_count {
             None
         } else {
@@ -211,7 +327,7 @@ impl Challenge {
             if i > 0 {
                 let skip = self.skip_index.get(i - 1)?;
 
-                let word = self.words.iter().nth(i - 1)?;
+                let word = self.words.get(i - 1)?;
                 let char_index = position - skip;
                 if char_index >= word.len() {
                     Some(Touch::Space)
@@ -226,59 +342,107 @@ impl Challenge {
     }
 
     /// Returns the number of `Touch`s expected in the challenge.
-    pub(crate) fn len(&self) -> usize {
+    pub fn len(&self) -> usize {
         self.total_count
     }
+
+    /// Returns whether no touch is expected.
+    pub fn is_empty(&self) -> bool {
+        self.total_count == 0
+    }
 }
 
 impl<'a> Iterator for CIter<'a> {
     /// The `Touch` and it's word index.
     /// If it's a space then the word index will be of the previous one.
     type Item = (Touch, WordIndex);
+    /// Walks the words in order, unlike `expected_at` it does not search
+    /// the word of each touch.
     fn next(&mut self) -> Option<Self::Item> {
-        if self.ix < self.challenge.len() {
-            // TODO improve the performance
-            let res = match self.challenge.expected_at(self.ix) {
-                Some(Touch::Space) => {
-                    let wix = self.word_ix;
-                    let res = Some((Touch::Space, wix));
-                    self.word_ix += 1;
-                    res
-                }
-                other => other.map(|x| (x, self.word_ix)),
-            };
-            self.ix += 1;
-            res
+        if self.ix >= self.challenge.len() {
+            return None;
+        }
+        let word = self.challenge.words.get(self.word_ix)?;
+        let res = if self.char_ix < word.len() {
+            self.char_ix += 1;
+            (Touch::Char(self.chars.next()?), self.word_ix)
         } else {
-            None
+            // the space after the word
+            self.word_ix += 1;
+            self.char_ix = 0;
+            self.chars = self
+                .challenge
+                .words
+                .get(self.word_ix)
+                .map(Word::as_str)
+                .unwrap_or_default()
+                .chars();
+            (Touch::Space, self.word_ix - 1)
+        };
+        self.ix += 1;
+        Some(res)
+    }
+}
+
+impl Keystroke {
+    /// Returns the keystroke typed `at`, before its release is seen.
+    pub fn new(expected: Touch, typed: Touch, at: Duration) -> Self {
+        Keystroke {
+            expected,
+            typed,
+            at,
+            dwell: None,
+            flight: None,
         }
     }
+
+    /// Returns whether the touch typed was the one expected.
+    pub fn is_success(&self) -> bool {
+        self.expected == self.typed
+    }
 }
 
 impl Attempt {
-    pub(crate) fn new() -> Self {
-        Attempt { touches: vec![] }
+    /// Starts without any keystroke.
+    pub fn new() -> Self {
+        Attempt { keystrokes: vec![] }
     }
-    pub(crate) fn add(&mut self, success: bool) {
-        self.touches.push(success);
+    /// Records the next keystroke.
+    pub fn add(&mut self, keystroke: Keystroke) {
+        self.keystrokes.push(keystroke);
     }
-    pub(crate) fn get(&self, i: usize) -> Option<&bool> {
-        self.touches.get(i)
+    /// Returns wether the touch `i` was successfully typed.
+    pub fn get(&self, i: usize) -> Option<bool> {
+        self.keystrokes.get(i).map(Keystroke::is_success)
     }
 }
 
-impl Word {
-    pub(crate) fn from(s: &str) -> Word {
+impl From<&str> for Word {
+    fn from(s: &str) -> Word {
         Word(s.to_string())
     }
+}
+
+impl Word {
+    /// Returns the number of touches, one per char.
+    pub fn len(&self) -> usize {
+        self.0.chars().count()
+    }
 
-    pub(crate) fn len(&self) -> usize {
-        self.0.len()
+    /// Returns whether the word has no char.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
     }
 
-    pub(crate) fn char_at(&self, i: usize) -> Option<char> {
+    /// Returns the char at the touch `i` of the word.
+    pub fn char_at(&self, i: usize) -> Option<char> {
         self.0.chars().nth(i)
     }
+
+    /// Returns the word as typed.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
 }
 
 impl std::fmt::Debug for Practice {
@@ -297,58 +461,272 @@ impl std::fmt::Display for Practice {
 }
 
 impl Practice {
-    pub(crate) fn generate<R: Rng>(rng: &mut R, size: usize, path: &Path) -> Result<Practice> {
-        let challenge = Challenge::generate(rng, size, path)?;
+    /// Returns a name for a practice starting now.
+    pub fn new_name() -> Result<String> {
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
-        let name = format!("practice_{}.txt", now.as_secs());
-        Ok(Self::new(challenge, name))
+        Ok(format!("practice_{}", now.as_secs()))
     }
-    pub(crate) fn new(challenge: Challenge, name: String) -> Practice {
+    /// Starts a practice of `challenge` saved as `name`, over after its last
+    /// word.
+    pub fn new(challenge: Challenge, name: String) -> Practice {
+        let mode = Mode::Words(challenge.word_count());
         Practice {
             challenge,
             attempt: Attempt::new(),
             name,
             cursor: 0,
+            mode,
+            started: None,
+            paused: None,
+            pauses: Duration::ZERO,
+            abandoned: false,
+            idle: None,
+            gaps: vec![],
+            latencies: vec![],
+            held: vec![],
         }
     }
 
-    pub(crate) fn iter<'a>(&'a self) -> PIter<'a> {
-        PIter::new(self)
+    /// Returns a fresh practice on the same challenge.
+    pub fn retry(&self) -> Result<Practice> {
+        let mut practice =
+            Practice::new(self.challenge.clone(), Practice::new_name()?).with_mode(self.mode);
+        practice.idle = self.idle;
+        Ok(practice)
     }
 
-    pub(crate) fn name<'a>(&'a self) -> &'a String {
-        &self.name
+    /// Returns the challenge typed.
+    pub fn challenge(&self) -> &Challenge {
+        &self.challenge
+    }
+
+    /// Returns the keystrokes recorded so far.
+    pub fn keystrokes(&self) -> &[Keystroke] {
+        &self.attempt.keystrokes
+    }
+
+    /// Returns the same practice ending according to `mode`.
+    pub fn with_mode(mut self, mode: Mode) -> Practice {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns the same practice counting the gaps between touches longer
+    /// than `threshold` as `threshold`.
+    pub fn with_idle(mut self, threshold: Duration) -> Practice {
+        self.idle = Some(threshold);
+        self
+    }
+
+    /// Returns the gaps left out of the timings so far.
+    pub fn gaps(&self) -> &[Gap] {
+        &self.gaps
+    }
+
+    /// Records how long the keystroke `latency.at` took to show.
+    pub fn record_latency(&mut self, latency: Latency) {
+        self.latencies.push(latency);
+    }
+
+    /// Returns how long the keystrokes took to show, when measured.
+    pub fn latencies(&self) -> &[Latency] {
+        &self.latencies
+    }
+
+    /// Appends words at the end of the underlying challenge.
+    pub fn extend(&mut self, words: Vec<Word>) {
+        self.challenge.extend(words);
+    }
+
+    /// Returns how many words must be appended to keep `LOOKAHEAD_WORDS`
+    /// ahead of the cursor. Always 0 for a fixed number of words.
+    pub fn missing_words(&self) -> usize {
+        match self.mode {
+            Mode::Words(_) => 0,
+            Mode::Endless | Mode::Timed(_) => {
+                let ahead = self.challenge.word_count() - self.cursor_word();
+                LOOKAHEAD_WORDS.saturating_sub(ahead)
+            }
+        }
+    }
+
+    /// Returns the index of the word under the cursor, or the last word
+    /// once the challenge is finished.
+    pub fn cursor_word(&self) -> WordIndex {
+        self.challenge
+            .word_index_at(self.cursor)
+            .unwrap_or(self.challenge.word_count().saturating_sub(1))
+    }
+
+    /// Returns the time elapsed since the first touch was pressed, the
+    /// time spent paused and the idle gaps left out. Zero before the first
+    /// touch.
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        let Some(started) = self.started else {
+            return Duration::ZERO;
+        };
+        let paused = self
+            .paused
+            .map(|paused| now.saturating_duration_since(paused))
+            .unwrap_or_default();
+        now.saturating_duration_since(started)
+            .saturating_sub(self.pauses + paused)
+    }
+
+    /// Returns the time since the last touch, the pauses left out.
+    pub fn since_last_touch(&self, now: Instant) -> Option<Duration> {
+        let last = self.attempt.keystrokes.last()?;
+        Some(self.elapsed(now).saturating_sub(last.at))
+    }
+
+    /// Stops the clock at `now` until `resume`.
+    pub fn pause(&mut self, now: Instant) {
+        if self.paused.is_none() {
+            self.paused = Some(now);
+        }
+    }
+
+    /// Restarts the clock at `now`, the pause is left out of the timings.
+    pub fn resume(&mut self, now: Instant) {
+        if let Some(paused) = self.paused.take() {
+            // nothing to leave out before the first touch
+            if self.started.is_some() {
+                self.pauses += now.saturating_duration_since(paused);
+            }
+        }
+    }
+
+    /// Returns whether the clock is stopped.
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
     }
 
-    pub(crate) fn save(self, path: &Path) -> Result<String> {
-        let path = path.join(self.name);
-        let path = path.as_path();
-        let succ = self.attempt.touches.iter().filter(|x| **x).count();
-        let total = self.attempt.touches.len();
-        let mut f = fs::File::create(&path).context(format!("cannot create file at {:?}", path))?;
-        writeln!(f, "total = {}", total)?;
-        writeln!(f, "succ = {}", succ)?;
+    /// Marks the practice as stopped before its end, it is saved anyway.
+    pub fn abandon(&mut self) {
+        self.abandoned = true;
+    }
+
+    /// Returns whether the practice was stopped before its end.
+    pub fn is_abandoned(&self) -> bool {
+        self.abandoned
+    }
+
+    /// Returns wether the first touch was pressed.
+    pub fn is_started(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Returns the time left in a timed practice, None for other modes.
+    pub fn time_left(&self, now: Instant) -> Option<Duration> {
+        match self.mode {
+            Mode::Timed(limit) => Some(limit.saturating_sub(self.elapsed(now))),
+            _ => None,
+        }
+    }
+
+    /// Returns wether the practice is over at `now`.
+    pub fn is_over(&self, now: Instant) -> bool {
+        match self.mode {
+            Mode::Words(_) => self.cursor >= self.challenge.len(),
+            Mode::Endless => false,
+            Mode::Timed(_) => self.time_left(now) == Some(Duration::ZERO),
+        }
+    }
 
-        let path = path.to_str().ok_or(TouchTypingError::InvalidPathError)?;
-        Ok(path.to_string())
+    /// Iterates over the touches of the challenge and how they were typed.
+    pub fn iter<'a>(&'a self) -> PIter<'a> {
+        PIter::new(self)
+    }
+
+    /// Returns the name the practice is saved as.
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
     ///
     /// Returns wether the touch is the expected one or None if
     /// no more touches are expected.
-    pub(crate) fn check(&self, touch: &Touch) -> Option<bool> {
+    pub fn check(&self, touch: &Touch) -> Option<bool> {
         self.challenge.expected_at(self.cursor).map(|e| e == *touch)
     }
 
-    /// Records the attempt of pressing a touch in a challenge
+    /// Records the attempt of pressing a touch in a challenge at `ts`
     /// if no touch is expected (challenge finished) we return None.
     /// Otherwise we return wether the touch was expected or not.
-    pub(crate) fn press(&mut self, touch: &Touch) -> Option<bool> {
-        let success = self.check(touch)?;
-        self.attempt.add(success);
+    /// A touch pressed while paused resumes the practice.
+    pub fn press(&mut self, touch: &Touch, ts: Instant) -> Option<bool> {
+        if self.is_over(ts) {
+            return None;
+        }
+        let expected = self.challenge.expected_at(self.cursor)?;
+        self.resume(ts);
+        self.started.get_or_insert(ts);
+        self.leave_idle_out(ts);
+        let at = self.elapsed(ts);
+        let flight = self
+            .attempt
+            .keystrokes
+            .last()
+            .and_then(|k| Some(at.saturating_sub(k.at + k.dwell?)));
+        let keystroke = Keystroke {
+            flight,
+            ..Keystroke::new(expected, *touch, at)
+        };
+        let success = keystroke.is_success();
+        self.attempt.add(keystroke);
+        if self.held.len() == MAX_HELD {
+            // the release of the oldest was missed
+            self.held.remove(0);
+        }
+        self.held.push(self.cursor);
         self.cursor += 1;
         Some(success)
     }
+
+    /// Returns how many keys typed are still held.
+    pub fn held(&self) -> usize {
+        self.held.len()
+    }
+
+    /// Records that the key last typed for `touch` was released at `ts`,
+    /// the time it was held and the flight to the next key follow.
+    pub fn release(&mut self, touch: &Touch, ts: Instant) {
+        let keystrokes = &mut self.attempt.keystrokes;
+        let Some(h) = self
+            .held
+            .iter()
+            .rposition(|i| keystrokes[*i].typed == *touch)
+        else {
+            return;
+        };
+        let i = self.held.remove(h);
+        let released = self.elapsed(ts);
+        let keystrokes = &mut self.attempt.keystrokes;
+        keystrokes[i].dwell = Some(released.saturating_sub(keystrokes[i].at));
+        // the next key was pressed before this one was released
+        if let Some(next) = keystrokes.get_mut(i + 1) {
+            next.flight = Some(Duration::ZERO);
+        }
+    }
+
+    /// Leaves out of the timings the part of the gap since the last touch
+    /// beyond the idle threshold.
+    fn leave_idle_out(&mut self, ts: Instant) {
+        let (Some(threshold), Some(last)) = (self.idle, self.attempt.keystrokes.last()) else {
+            return;
+        };
+        let idle = self
+            .elapsed(ts)
+            .saturating_sub(last.at)
+            .saturating_sub(threshold);
+        if !idle.is_zero() {
+            self.pauses += idle;
+            self.gaps.push(Gap {
+                at: self.attempt.keystrokes.len(),
+                idle,
+            });
+        }
+    }
 }
 
 /// Iterates on a practice and give the state of each touch in the challenge.
@@ -367,7 +745,7 @@ impl<'a> PIter<'a> {
         } else {
             // challenge_iter.x <= self.practice.cursor
             if let Some(b) = self.practice.attempt.get(self.challenge_iter.ix) {
-                TouchState::Attempted(*b)
+                TouchState::Attempted(b)
             } else {
                 unreachable!("should always have a value")
             }
@@ -393,29 +771,123 @@ impl<'a> Iterator for PIter<'a> {
     }
 }
 
+/// Returns the code of the challenge made of `text`, a FNV-1a hash.
+pub fn challenge_code(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:08x}", (hash >> 32) as u32 ^ hash as u32)
+}
+
 impl<R> PracticeGenerator<R> {
-    /// Returns a new Generator.
-    pub(crate) fn new(rng: R, size: usize, path: &str) -> PracticeGenerator<R> {
+    /// Returns a new Generator sampling the words of the .freq file `path`.
+    pub fn new(rng: R, size: usize, path: &str) -> PracticeGenerator<R> {
         PracticeGenerator {
             rng,
             size,
-            path: path.to_string(),
+            mode: Mode::Words(size),
+            source: Box::new(Frequency::new(path)),
+            letters: None,
+            review: vec![],
+            review_only: false,
+        }
+    }
+
+    /// Returns the same generator producing only words to review.
+    pub fn with_review_only(mut self, review_only: bool) -> PracticeGenerator<R> {
+        self.review_only = review_only;
+        self
+    }
+
+    /// Takes the words of the next practices from `source`, unless it
+    /// cannot give any.
+    pub fn set_source(&mut self, mut source: Box<dyn ChallengeSource>) -> Result<()>
+    where
+        R: rand::Rng,
+    {
+        source.set_letters(self.letters.as_deref());
+        // loads whatever the source needs
+        source.words(&mut self.rng, 0)?;
+        self.source = source;
+        Ok(())
+    }
+
+    /// Describes where the words come from.
+    pub fn source(&self) -> SourceInfo {
+        self.source.info()
+    }
+
+    /// Sets the words due for review, the most urgent first.
+    pub fn set_review(&mut self, words: Vec<Word>) {
+        self.review = words;
+    }
+
+    /// Restricts the generated words to those made of `letters`, or lifts
+    /// the restriction if None.
+    pub fn set_letters(&mut self, letters: Option<Vec<char>>) {
+        self.source.set_letters(letters.as_deref());
+        self.letters = letters;
+    }
+
+    /// Returns the same generator producing practices in `mode`.
+    ///
+    /// For endless and timed modes, `size` is only the initial number of
+    /// words, the rest is appended with `words` as the user progresses.
+    pub fn with_mode(mut self, mode: Mode) -> PracticeGenerator<R> {
+        if let Mode::Words(size) = mode {
+            self.size = size;
         }
+        self.mode = mode;
+        self
     }
+
     /// Generates a new practice.
-    pub(crate) fn generate(&mut self) -> Result<Practice>
+    pub fn generate(&mut self) -> Result<Practice>
     where
         R: rand::Rng,
     {
-        Practice::generate(&mut self.rng, self.size, &Path::new(self.path.as_str()))
+        let words = self.words(self.size)?;
+        Ok(Practice::new(Challenge::from(words), Practice::new_name()?).with_mode(self.mode))
+    }
+
+    /// Samples `n` words, to grow an endless or timed practice.
+    pub fn words(&mut self, n: usize) -> Result<Vec<Word>>
+    where
+        R: rand::Rng,
+    {
+        if self.review_only && !self.review.is_empty() {
+            let mut words: Vec<Word> = self.review.iter().cycle().take(n).cloned().collect();
+            words.shuffle(&mut self.rng);
+            return Ok(words);
+        }
+        let mut words = self.source.words(&mut self.rng, n)?;
+        // quotes or code would not make sense anymore
+        if self.source.info().ordered {
+            return Ok(words);
+        }
+        // each word to review shows up once, in place of a sampled word,
+        // the source may have given less than `n`
+        let count = (words.len() / REVIEW_RATIO)
+            .max(1)
+            .min(self.review.len())
+            .min(words.len());
+        let positions = rand::seq::index::sample(&mut self.rng, words.len(), count);
+        for (i, word) in positions.iter().zip(self.review.drain(..count)) {
+            words[i] = word;
+        }
+        Ok(words)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::model::Touch;
+    use std::time::{Duration, Instant};
+
+    use crate::source::{ChallengeSource, SourceInfo};
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
 
-    use super::{Challenge, FromStr};
+    use super::{Challenge, FromStr, Mode, Practice, PracticeGenerator, Word, LOOKAHEAD_WORDS};
     #[test]
     pub fn it_computes_expected_at() {
         let p = Challenge::from_str("this is a practice").unwrap();
@@ -426,4 +898,123 @@ mod tests {
         assert_eq!(p.expected_at(13), Some(Touch::Char('c')));
         assert_eq!(p.expected_at(18), None);
     }
+
+    #[test]
+    pub fn it_iterates_like_expected_at() {
+        let p = Challenge::from_str("this is a practice").unwrap();
+        let touches: Vec<_> = p.iter().collect();
+        assert_eq!(touches.len(), p.len());
+        for (i, (t, w)) in touches.into_iter().enumerate() {
+            assert_eq!(Some(t), p.expected_at(i));
+            assert_eq!(Some(w), p.word_index_at(i));
+        }
+    }
+
+    #[test]
+    pub fn it_ends_non_ascii_words_on_their_last_char() {
+        let mut practice = Practice::new(
+            Challenge::from_str("héllo мир").unwrap(),
+            "test".to_string(),
+        );
+        let start = Instant::now();
+        assert_eq!(practice.challenge.len(), 9);
+        for (i, c) in "héllo мир".chars().enumerate() {
+            assert!(!practice.is_over(start), "over before touch {}", i);
+            assert_eq!(practice.press(&Touch::from(c), start), Some(true));
+        }
+        assert!(practice.is_over(start));
+    }
+
+    #[test]
+    pub fn it_records_dwell_and_flight() {
+        let mut practice = Practice::new(Challenge::from_str("abc").unwrap(), "test".to_string());
+        let start = Instant::now();
+        let ms = |ms: u64| start + Duration::from_millis(ms);
+        practice.press(&Touch::Char('a'), ms(0));
+        practice.release(&Touch::Char('a'), ms(80));
+        practice.press(&Touch::Char('b'), ms(200));
+        // c is pressed before b is released
+        practice.press(&Touch::Char('c'), ms(300));
+        practice.release(&Touch::Char('b'), ms(320));
+        practice.release(&Touch::Char('c'), ms(400));
+        let k = practice.keystrokes();
+        assert_eq!(k[0].dwell, Some(Duration::from_millis(80)));
+        assert_eq!(k[0].flight, None);
+        assert_eq!(k[1].dwell, Some(Duration::from_millis(120)));
+        assert_eq!(k[1].flight, Some(Duration::from_millis(120)));
+        assert_eq!(k[2].dwell, Some(Duration::from_millis(100)));
+        assert_eq!(k[2].flight, Some(Duration::ZERO));
+    }
+
+    #[test]
+    pub fn it_grows_consistently() {
+        let full = Challenge::from_str("this is a practice").unwrap();
+        let mut grown = Challenge::from_str("this").unwrap();
+        grown.extend(vec![Word::from("is"), Word::from("a")]);
+        grown.push(Word::from("practice"));
+        assert_eq!(grown.len(), full.len());
+        for i in 0..=full.len() {
+            assert_eq!(grown.expected_at(i), full.expected_at(i));
+        }
+        assert_eq!(
+            grown.iter().collect::<Vec<_>>(),
+            full.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(grown.word_index_at(4), Some(0));
+        assert_eq!(grown.word_index_at(5), Some(1));
+    }
+
+    #[test]
+    pub fn it_never_ends_in_endless_mode() {
+        let challenge = Challenge::from_str("a b").unwrap();
+        let mut practice = Practice::new(challenge, "test".to_string()).with_mode(Mode::Endless);
+        let now = Instant::now();
+        assert_eq!(practice.missing_words(), LOOKAHEAD_WORDS - 2);
+        assert!(!practice.is_started());
+        for c in "a b".chars() {
+            assert_eq!(practice.press(&Touch::from(c), now), Some(true));
+        }
+        assert!(!practice.is_over(now));
+        assert_eq!(practice.press(&Touch::Space, now), None);
+        practice.extend(vec![Word::from("c")]);
+        assert_eq!(practice.press(&Touch::Space, now), Some(true));
+        assert_eq!(practice.press(&Touch::Char('c'), now), Some(true));
+    }
+
+    /// Gives its two words at most, like a short text.
+    struct Short;
+
+    impl ChallengeSource for Short {
+        fn info(&self) -> SourceInfo {
+            SourceInfo {
+                name: "short",
+                description: "Two words",
+                options: vec![],
+                ordered: false,
+            }
+        }
+
+        fn set_option(&mut self, _name: &str, _value: &str) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn words(&mut self, _rng: &mut dyn RngCore, n: usize) -> anyhow::Result<Vec<Word>> {
+            Ok(["short", "text"]
+                .iter()
+                .take(n)
+                .map(|w| Word::from(*w))
+                .collect())
+        }
+    }
+
+    #[test]
+    pub fn it_reviews_words_of_a_short_source() {
+        let rng = StdRng::seed_from_u64(0);
+        let mut generator = PracticeGenerator::new(rng, 25, "./data/t8.shakespeare.freq");
+        generator.set_source(Box::new(Short)).unwrap();
+        generator.set_review(vec![Word::from("review")]);
+        let words = generator.words(25).unwrap();
+        assert_eq!(words.len(), 2);
+        assert!(words.iter().any(|w| w.as_str() == "review"));
+    }
 }
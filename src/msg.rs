@@ -2,11 +2,84 @@
 use relm4::gtk::gdk::{Key, ModifierType};
 use std::time::Instant;
 
-use crate::model::{Practice, Touch};
+use crate::ghost::Ghost;
+use crate::model::{Practice, Touch, Word};
+use crate::race::ServerMsg;
+use crate::session::Report;
+use crate::settings::Settings;
+use crate::theme::Theme;
 
 #[derive(Debug, Clone)]
 pub(crate) enum Msg {
-    KeyPressed(Key, Touch, ModifierType, Instant),
+    /// The last field is the time of the key event in milliseconds, as
+    /// given by GDK, 0 if unknown.
+    KeyPressed(Key, Touch, ModifierType, Instant, u32),
+    /// The key typed for the touch was released.
+    KeyReleased(Touch, Instant),
     PracticeEnd(Practice),
     PracticeStart(Practice),
+    /// The practice needs that many more words to keep growing.
+    PracticeGrow(usize),
+    /// Words to append to the current practice.
+    PracticeExtend(Vec<Word>),
+    /// The time of the named practice is up.
+    PracticeTimeUp(String),
+    /// Stops the clock of the practice, from Escape or when the window
+    /// loses the focus.
+    PracticePause(Instant),
+    /// Restarts the clock of the paused practice.
+    PracticeResume(Instant),
+    /// Seconds left before the paused practice resumes.
+    PracticeCountdown(u32),
+    /// No key was typed since the key counted here, for a while.
+    PracticeIdle(usize),
+    /// Ends the current practice early, it is saved as incomplete.
+    PracticeAbandon,
+    /// Escape was pressed: pauses the practice, or resumes it when paused.
+    Escape,
+    /// The window is closed, the current practice is saved first.
+    Close,
+    /// Results of the practice that just ended.
+    PracticeReport(Report),
+    /// Start again the practice that just ended, or the current one.
+    PracticeRetry,
+    /// Practice the words mistyped in the practice that just ended.
+    PracticeMissed,
+    /// Move on to a new practice.
+    PracticeNext,
+    /// Draw the practice and the keyboard with this theme.
+    Theme(Box<Theme>),
+    /// Draw with the theme at this index, following the dark mode of the
+    /// desktop if 0.
+    ThemeSelected(u32),
+    /// The dark mode of the desktop changed.
+    DarkModeChanged,
+    /// The settings changed, they are already saved.
+    Settings(Settings),
+    /// Open the settings window.
+    ShowSettings,
+    /// The frame clock of the practice ticked, the caret may move.
+    CaretFrame,
+    /// A frame showing a keystroke was painted at this time, in the clock
+    /// of the key events.
+    Painted(u32),
+    /// Open the statistics window.
+    ShowStatistics,
+    /// Take the words from the source at this index of the registry.
+    SourceSelected(u32),
+    /// The ghost to race in the next practice, if any.
+    GhostRace(Option<Ghost>),
+    /// The ghost of the named practice moved.
+    GhostTick(String),
+    /// A message of a replayed session, for the components showing the
+    /// practice.
+    Replayed(Box<Msg>),
+    /// A message of the race server.
+    Race(ServerMsg),
+    /// Name and position of the other players of the race.
+    RaceOpponents(Vec<(String, usize)>),
+    /// Something failed, shown in the window while the practices go on.
+    Warning(String),
+    /// Hide the warnings shown.
+    DismissWarnings,
 }
@@ -0,0 +1,384 @@
+//! Typing races on the local network.
+//!
+//! Server: waits for the players, sends them the same challenge, counts
+//! down, relays the progress of each player to everyone and ranks them
+//! by the time they took to type the whole challenge.
+//! RaceClient: a connection to the server.
+//! Race: what a client knows of the race, from the server messages.
+//!
+//! Messages are json objects, one per line.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The port the server listens on when none is given.
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// The messages of the server, read on another thread by the interface.
+pub type ServerMessages = Box<dyn Iterator<Item = Result<ServerMsg>> + Send>;
+
+/// A message from a player to the server.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMsg {
+    /// Enters the race.
+    Join {
+        /// The name shown to the other players.
+        name: String,
+    },
+    /// Number of touches typed so far.
+    Progress {
+        /// The touches typed.
+        position: usize,
+    },
+}
+
+/// A message from the server to the players.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMsg {
+    /// The id of the player receiving it.
+    Welcome {
+        /// The index of the player.
+        id: usize,
+    },
+    /// The challenge generated from `seed`, and the players by id.
+    Challenge {
+        /// The seed the challenge was generated from.
+        seed: u64,
+        /// The words of the challenge.
+        text: String,
+        /// The names of the players.
+        players: Vec<String>,
+    },
+    /// Seconds left before the start.
+    Countdown {
+        /// The seconds left.
+        seconds: u32,
+    },
+    /// The race starts, the keys count from now on.
+    Start,
+    /// A player typed more touches.
+    Progress {
+        /// The index of the player.
+        id: usize,
+        /// The touches typed.
+        position: usize,
+    },
+    /// Every player, the fastest first.
+    Ranking {
+        /// The players, the fastest first.
+        ranks: Vec<Rank>,
+    },
+}
+
+/// How a player did in a race.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rank {
+    /// The name of the player.
+    pub name: String,
+    /// Time taken to type the challenge, None if the player left before.
+    pub time_ms: Option<u64>,
+}
+
+/// Runs a race for the players joining it.
+pub struct Server {
+    listener: TcpListener,
+    players: usize,
+    seed: u64,
+    text: String,
+    countdown: u32,
+}
+
+/// The connection of a player to the server.
+pub struct RaceClient {
+    stream: TcpStream,
+}
+
+/// A race as seen by a player.
+#[derive(Clone, Debug, Default)]
+pub struct Race {
+    /// Our id among the players.
+    pub id: usize,
+    /// The names of the players, by id.
+    pub players: Vec<String>,
+    /// Touches typed by each player.
+    pub positions: Vec<usize>,
+    /// Seconds left before the start, while counting down.
+    pub countdown: Option<u32>,
+    /// The keys count.
+    pub started: bool,
+    /// The players ranked once everyone finished or left.
+    pub ranking: Option<Vec<Rank>>,
+}
+
+fn send<T: Serialize>(stream: &mut TcpStream, msg: &T) -> Result<()> {
+    let mut line = serde_json::to_string(msg)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(line: std::io::Result<String>) -> Result<T> {
+    Ok(serde_json::from_str(line?.as_str())?)
+}
+
+impl Server {
+    /// Listens on `addr` for a race of `players` on `text`.
+    pub fn bind(
+        addr: impl ToSocketAddrs,
+        players: usize,
+        seed: u64,
+        text: String,
+    ) -> Result<Server> {
+        let listener = TcpListener::bind(addr).context("cannot listen")?;
+        Ok(Server {
+            listener,
+            players,
+            seed,
+            text,
+            countdown: 3,
+        })
+    }
+
+    /// Returns the same server counting down from `seconds`.
+    pub fn with_countdown(mut self, seconds: u32) -> Server {
+        self.countdown = seconds;
+        self
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Runs a whole race and returns its ranking.
+    pub fn run(self) -> Result<Vec<Rank>> {
+        let (tx, rx) = mpsc::channel::<(usize, Option<ClientMsg>)>();
+        let mut streams = vec![];
+        let mut names = vec![];
+        while streams.len() < self.players {
+            let (mut stream, addr) = self.listener.accept()?;
+            let mut lines = BufReader::new(stream.try_clone()?).lines();
+            let name = match lines.next().map(parse::<ClientMsg>) {
+                Some(Ok(ClientMsg::Join { name })) => name,
+                _ => {
+                    println!("{} did not join", addr);
+                    continue;
+                }
+            };
+            let id = streams.len();
+            println!("{} joined from {}", name, addr);
+            send(&mut stream, &ServerMsg::Welcome { id })?;
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for msg in lines.map(parse::<ClientMsg>) {
+                    let Ok(msg) = msg else {
+                        break;
+                    };
+                    if tx.send((id, Some(msg))).is_err() {
+                        return;
+                    }
+                }
+                let _ = tx.send((id, None));
+            });
+            streams.push(stream);
+            names.push(name);
+        }
+
+        // a player leaving is not an error, the others keep racing
+        let mut broadcast = |msg: &ServerMsg| {
+            for stream in streams.iter_mut() {
+                let _ = send(stream, msg);
+            }
+        };
+        broadcast(&ServerMsg::Challenge {
+            seed: self.seed,
+            text: self.text.clone(),
+            players: names.clone(),
+        });
+        for seconds in (1..=self.countdown).rev() {
+            broadcast(&ServerMsg::Countdown { seconds });
+            thread::sleep(Duration::from_secs(1));
+        }
+        broadcast(&ServerMsg::Start);
+        let start = Instant::now();
+
+        let len = self.text.chars().count();
+        let mut times: Vec<Option<Duration>> = vec![None; names.len()];
+        let mut racing = vec![true; names.len()];
+        while racing.contains(&true) {
+            let Ok((id, msg)) = rx.recv() else {
+                break;
+            };
+            match msg {
+                Some(ClientMsg::Progress { position }) => {
+                    broadcast(&ServerMsg::Progress { id, position });
+                    if position >= len && racing[id] {
+                        times[id] = Some(start.elapsed());
+                        racing[id] = false;
+                    }
+                }
+                Some(ClientMsg::Join { .. }) => (),
+                None => racing[id] = false,
+            }
+        }
+
+        let mut ranks: Vec<Rank> = names
+            .into_iter()
+            .zip(times)
+            .map(|(name, time)| Rank {
+                name,
+                time_ms: time.map(|t| t.as_millis() as u64),
+            })
+            .collect();
+        // the players who left come last
+        ranks.sort_by_key(|r| r.time_ms.unwrap_or(u64::MAX));
+        broadcast(&ServerMsg::Ranking {
+            ranks: ranks.clone(),
+        });
+        // also ends the threads reading the players
+        for stream in &streams {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        Ok(ranks)
+    }
+}
+
+impl RaceClient {
+    /// Connects to the server at `addr` and joins the race as `name`.
+    pub fn connect(addr: impl ToSocketAddrs, name: &str) -> Result<RaceClient> {
+        let stream = TcpStream::connect(addr).context("cannot connect to the race server")?;
+        let mut client = RaceClient { stream };
+        client.send(&ClientMsg::Join {
+            name: name.to_string(),
+        })?;
+        Ok(client)
+    }
+
+    /// Sends `msg` to the server.
+    pub fn send(&mut self, msg: &ClientMsg) -> Result<()> {
+        send(&mut self.stream, msg)
+    }
+
+    /// Returns the messages of the server as they arrive, until it closes
+    /// the connection.
+    pub fn messages(&self) -> Result<ServerMessages> {
+        let reader = BufReader::new(self.stream.try_clone()?);
+        Ok(Box::new(reader.lines().map(parse::<ServerMsg>)))
+    }
+}
+
+impl Race {
+    /// Waits for the challenge: returns the race and the text to type.
+    pub fn join(messages: &mut impl Iterator<Item = Result<ServerMsg>>) -> Result<(Race, String)> {
+        let mut race = Race::default();
+        for msg in messages {
+            let msg = msg?;
+            race.apply(&msg);
+            if let ServerMsg::Challenge { text, .. } = msg {
+                return Ok((race, text));
+            }
+        }
+        Err(anyhow!("the server left before sending the challenge"))
+    }
+
+    /// Updates the race from a message of the server.
+    pub fn apply(&mut self, msg: &ServerMsg) {
+        match msg {
+            ServerMsg::Welcome { id } => self.id = *id,
+            ServerMsg::Challenge { players, .. } => {
+                self.players = players.clone();
+                self.positions = vec![0; players.len()];
+            }
+            ServerMsg::Countdown { seconds } => self.countdown = Some(*seconds),
+            ServerMsg::Start => {
+                self.countdown = None;
+                self.started = true;
+            }
+            ServerMsg::Progress { id, position } => {
+                if let Some(p) = self.positions.get_mut(*id) {
+                    *p = *position;
+                }
+            }
+            ServerMsg::Ranking { ranks } => self.ranking = Some(ranks.clone()),
+        }
+    }
+
+    /// Returns the name and position of the other players.
+    pub fn opponents(&self) -> Vec<(String, usize)> {
+        self.players
+            .iter()
+            .cloned()
+            .zip(self.positions.iter().copied())
+            .enumerate()
+            .filter(|(id, _)| *id != self.id)
+            .map(|(_, p)| p)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::{ClientMsg, Race, RaceClient, Server, ServerMsg};
+
+    #[test]
+    pub fn it_races_on_loopback() {
+        let server = Server::bind("127.0.0.1:0", 2, 42, "ab cd".to_string())
+            .unwrap()
+            .with_countdown(0);
+        let addr = server.local_addr().unwrap();
+        let server = thread::spawn(move || server.run().unwrap());
+
+        let racer = |name: &'static str, typed: usize| {
+            thread::spawn(move || {
+                let mut client = RaceClient::connect(addr, name).unwrap();
+                let mut messages = client.messages().unwrap();
+                let (mut race, text) = Race::join(&mut messages).unwrap();
+                assert_eq!(text, "ab cd");
+                for msg in messages.by_ref() {
+                    let msg = msg.unwrap();
+                    race.apply(&msg);
+                    if msg == ServerMsg::Start {
+                        break;
+                    }
+                }
+                for position in 1..=typed {
+                    client.send(&ClientMsg::Progress { position }).unwrap();
+                }
+                // leaving before the end
+                if typed < text.len() {
+                    return race;
+                }
+                for msg in messages {
+                    race.apply(&msg.unwrap());
+                    if race.ranking.is_some() {
+                        break;
+                    }
+                }
+                race
+            })
+        };
+        let (alice, bob) = (racer("alice", 5), racer("bob", 2));
+        let (alice, bob) = (alice.join().unwrap(), bob.join().unwrap());
+
+        let ranks = server.join().unwrap();
+        let names: Vec<&str> = ranks.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["alice", "bob"]);
+        assert!(ranks[0].time_ms.is_some());
+        assert!(ranks[1].time_ms.is_none());
+        assert_eq!(alice.ranking, Some(ranks));
+        assert!(alice.started);
+        assert_eq!(alice.opponents().len(), 1);
+        assert!(bob.ranking.is_none());
+    }
+}
@@ -0,0 +1,141 @@
+//! Replays a saved session keystroke by keystroke.
+//!
+//! The keystrokes are replayed on a fresh practice of the same challenge,
+//! each one at the instant it was typed relative to the start, scaled by
+//! the speed of the replay.
+
+use std::time::{Duration, Instant};
+
+use crate::model::{Practice, Touch};
+use crate::session::Session;
+
+/// A saved session replayed on a new practice.
+pub struct Replay {
+    session: Session,
+    /// Number of keystrokes replayed so far.
+    position: usize,
+    /// 2.0 replays twice as fast as typed.
+    pub speed: f32,
+    /// The keystrokes are replayed only while not paused.
+    pub paused: bool,
+    /// Stands for the start of the session: a keystroke typed at `at` is
+    /// replayed as if pressed at `start + at`.
+    start: Instant,
+}
+
+impl Replay {
+    /// Replays `session` as if it started at `start`, paused at first.
+    pub fn new(session: Session, start: Instant) -> Replay {
+        Replay {
+            session,
+            position: 0,
+            speed: 1.0,
+            paused: true,
+            start,
+        }
+    }
+
+    /// Returns the number of keystrokes replayed so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Number of keystrokes of the session.
+    pub fn len(&self) -> usize {
+        self.session.keystrokes.len()
+    }
+
+    /// Returns whether the session has no keystroke.
+    pub fn is_empty(&self) -> bool {
+        self.session.keystrokes.is_empty()
+    }
+
+    /// Returns whether every keystroke was replayed.
+    pub fn is_over(&self) -> bool {
+        self.position >= self.len()
+    }
+
+    /// Returns a practice of the session challenge, to replay the
+    /// keystrokes on.
+    pub fn practice(&self) -> Practice {
+        Practice::new(
+            self.session.challenge(),
+            format!("replay_{}", self.session.name),
+        )
+    }
+
+    /// Returns how long to wait before replaying the next keystroke, at the
+    /// speed of the replay.
+    pub fn delay(&self) -> Option<Duration> {
+        let next = self.session.keystrokes.get(self.position)?.at;
+        let previous = match self.position.checked_sub(1) {
+            Some(i) => self.session.keystrokes[i].at,
+            None => next,
+        };
+        Some(next.saturating_sub(previous).div_f64(self.speed as f64))
+    }
+
+    /// Replays the next keystroke: returns what was typed and the instant
+    /// it stands for.
+    pub fn step(&mut self) -> Option<(Touch, Instant)> {
+        let k = self.session.keystrokes.get(self.position)?;
+        self.position += 1;
+        Some((k.typed, self.start + k.at))
+    }
+
+    /// Moves to `position`: returns the keystrokes to replay at once on a
+    /// fresh practice to get there.
+    pub fn seek(&mut self, position: usize) -> Vec<(Touch, Instant)> {
+        self.position = 0;
+        (0..position.min(self.len()))
+            .filter_map(|_| self.step())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::Replay;
+    use crate::model::{Keystroke, Touch};
+    use crate::session::Session;
+
+    #[test]
+    pub fn it_replays_at_the_recorded_pace() {
+        let keystrokes = [('a', 0), ('b', 300), ('c', 400)]
+            .iter()
+            .map(|(c, ms)| {
+                Keystroke::new(Touch::from(*c), Touch::from(*c), Duration::from_millis(*ms))
+            })
+            .collect();
+        let session = Session {
+            name: "test".to_string(),
+            timestamp: 0,
+            text: "abc".to_string(),
+            keystrokes,
+            ..Session::default()
+        };
+        let start = Instant::now();
+        let mut replay = Replay::new(session, start);
+        replay.speed = 2.0;
+        assert_eq!(replay.delay(), Some(Duration::ZERO));
+        assert_eq!(replay.step(), Some((Touch::Char('a'), start)));
+        assert_eq!(replay.delay(), Some(Duration::from_millis(150)));
+
+        let replayed = replay.seek(2);
+        assert_eq!(replayed.len(), 2);
+        assert_eq!(replayed[1].1, start + Duration::from_millis(300));
+        assert_eq!(replay.delay(), Some(Duration::from_millis(50)));
+        replay.step();
+        assert!(replay.is_over());
+        assert_eq!(replay.delay(), None);
+        assert_eq!(replay.seek(10).len(), 3);
+
+        let mut practice = replay.practice();
+        for (touch, ts) in replay.seek(3) {
+            assert_eq!(practice.press(&touch, ts), Some(true));
+        }
+        assert_eq!(practice.keystrokes(), replay.session.keystrokes.as_slice());
+    }
+}
@@ -0,0 +1,195 @@
+//! Spaced repetition of the mistyped words.
+//!
+//! Card: the schedule of a word, following SM-2: each review is graded,
+//! a good grade spaces the next review more and more, a bad one starts
+//! over.
+//! ReviewQueue: the cards of every word mistyped so far, saved between
+//! runs.
+//!
+//! A word enters the queue when it is mistyped, and is reviewed each time
+//! it shows up in a practice.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::model::Word;
+use crate::session::Session;
+
+const REVIEW_FILE: &str = "review.json";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const INITIAL_EASE: f32 = 2.5;
+const MIN_EASE: f32 = 1.3;
+/// Grades from 0 to 5, under 3 the word is considered forgotten.
+const GRADE_MISTYPED: u8 = 1;
+const GRADE_TYPED: u8 = 5;
+const MIN_GOOD_GRADE: u8 = 3;
+
+/// The schedule of the reviews of a word.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Card {
+    /// Reviews in a row with a good grade.
+    pub repetitions: u32,
+    /// Days between the last review and the next one.
+    pub interval: u32,
+    /// How much the interval grows after a good review.
+    pub ease: f32,
+    /// Unix timestamp from which the word should be reviewed.
+    pub due: u64,
+}
+
+/// The words mistyped, scheduled for review.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ReviewQueue {
+    cards: BTreeMap<String, Card>,
+}
+
+impl Card {
+    fn new(now: u64) -> Card {
+        Card {
+            repetitions: 0,
+            interval: 0,
+            ease: INITIAL_EASE,
+            due: now,
+        }
+    }
+
+    /// Schedules the next review after a review graded `grade` at `now`.
+    fn review(&mut self, grade: u8, now: u64) {
+        if grade < MIN_GOOD_GRADE {
+            self.repetitions = 0;
+            self.interval = 1;
+        } else {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f32 * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        }
+        let miss = (5 - grade.min(5)) as f32;
+        self.ease = (self.ease + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE);
+        self.due = now + self.interval as u64 * SECONDS_PER_DAY;
+    }
+}
+
+impl ReviewQueue {
+    fn path(dir: &Path) -> PathBuf {
+        dir.join(REVIEW_FILE)
+    }
+
+    /// Loads the queue saved in `dir`, empty if it was never saved.
+    pub fn load(dir: &Path) -> Result<ReviewQueue> {
+        let path = ReviewQueue::path(dir);
+        match fs::read_to_string(&path) {
+            Ok(json) => Ok(serde_json::from_str(json.as_str())?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ReviewQueue::default()),
+            Err(e) => Err(e).context(format!("cannot read {:?}", path)),
+        }
+    }
+
+    /// Saves the queue in `dir`.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).context(format!("cannot create directory {:?}", dir))?;
+        let path = ReviewQueue::path(dir);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .context(format!("cannot create file at {:?}", path))
+    }
+
+    /// Grades the words typed in `session`: the mistyped ones are added to
+    /// the queue, the others are reviewed only if already queued.
+    pub fn record(&mut self, session: &Session) {
+        let now = session.timestamp;
+        let mistyped: HashSet<String> = session
+            .summary()
+            .mistakes
+            .iter()
+            .map(|m| m.word.as_str().to_string())
+            .collect();
+        let challenge = session.challenge();
+        let typed = session
+            .keystrokes
+            .len()
+            .checked_sub(1)
+            .and_then(|last| challenge.word_index_at(last))
+            .map_or(0, |wix| wix + 1);
+        let mut reviewed = HashSet::new();
+        for word in challenge.words().iter().take(typed) {
+            let word = word.as_str().to_string();
+            // a word repeated in a practice is graded once
+            if !reviewed.insert(word.clone()) {
+                continue;
+            }
+            if mistyped.contains(&word) {
+                self.cards
+                    .entry(word)
+                    .or_insert_with(|| Card::new(now))
+                    .review(GRADE_MISTYPED, now);
+            } else if let Some(card) = self.cards.get_mut(&word) {
+                card.review(GRADE_TYPED, now);
+            }
+        }
+    }
+
+    /// Returns the words to review at `now`, the most overdue first.
+    pub fn due(&self, now: u64) -> Vec<Word> {
+        let mut due: Vec<(&String, &Card)> =
+            self.cards.iter().filter(|(_, c)| c.due <= now).collect();
+        due.sort_by_key(|(_, c)| c.due);
+        due.into_iter()
+            .map(|(w, _)| Word::from(w.as_str()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ReviewQueue, SECONDS_PER_DAY};
+    use crate::model::{Keystroke, Touch};
+    use crate::session::Session;
+
+    fn session(text: &str, typed: &str, timestamp: u64) -> Session {
+        let keystrokes = typed
+            .chars()
+            .zip(text.chars())
+            .map(|(t, e)| Keystroke::new(Touch::from(e), Touch::from(t), Duration::ZERO))
+            .collect();
+        Session {
+            name: "test".to_string(),
+            timestamp,
+            text: text.to_string(),
+            keystrokes,
+            ..Session::default()
+        }
+    }
+
+    #[test]
+    pub fn it_schedules_mistyped_words() {
+        let mut queue = ReviewQueue::default();
+        queue.record(&session("the cat sat", "thr cat sat", 0));
+        assert!(!queue.cards.contains_key("cat"));
+        let card = queue.cards.get("the").unwrap().clone();
+        assert_eq!((card.repetitions, card.interval), (0, 1));
+        assert!(queue.due(0).is_empty());
+        assert_eq!(queue.due(SECONDS_PER_DAY).len(), 1);
+
+        // typed right three times: 1 day, 6 days, then 6 * ease days
+        let mut now = SECONDS_PER_DAY;
+        for interval in [1, 6, 13] {
+            queue.record(&session("the dog", "the dog", now));
+            let card = queue.cards.get("the").unwrap();
+            assert_eq!(card.interval, interval);
+            now = card.due;
+        }
+        // forgotten again
+        queue.record(&session("the", "tge", now));
+        assert_eq!(queue.cards.get("the").unwrap().repetitions, 0);
+        assert_eq!(queue.cards.get("the").unwrap().interval, 1);
+    }
+}
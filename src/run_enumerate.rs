@@ -1,10 +1,10 @@
-/// A `RunEnumerateIter` wraps an `Iterator` and returns the same enumeration
-/// as long as the inner value are the same.
-///
-/// A `RunEnumerateProjectIter` does the same but using a projection function
-/// to check the equality.
-///
-/// For lack of other alternatives, the name is adapted from RunLength which accumulates the length instead.
+//! A `RunEnumerateIter` wraps an `Iterator` and returns the same enumeration
+//! as long as the inner value are the same.
+//!
+//! A `RunEnumerateProjectIter` does the same but using a projection function
+//! to check the equality.
+//!
+//! For lack of other alternatives, the name is adapted from RunLength which accumulates the length instead.
 
 #[derive(Clone, Default, PartialEq)]
 enum Ix {
@@ -28,7 +28,8 @@ impl Ix {
     }
 }
 
-pub(crate) struct RunEnumerateIter<I, A> {
+/// Enumerates the runs of equal items, see `run_enumerate`.
+pub struct RunEnumerateIter<I, A> {
     inner_iter: I,
     last: Option<A>,
     ix: Ix,
@@ -73,7 +74,9 @@ where
     }
 }
 
-pub(crate) struct RunEnumerateProjectIter<I, A, F> {
+/// Enumerates the runs of items with an equal projection, see
+/// `run_enumerate_with`.
+pub struct RunEnumerateProjectIter<I, A, F> {
     inner_iter: I,
     last: Option<A>,
     ix: Ix,
@@ -102,8 +105,8 @@ where
             Ix::At(ix) => {
                 if self.last.is_some() {
                     let maybe_next = self.inner_iter.next();
-                    let is_eq = self.last.as_ref().map(|x| (self.proj)(&x))
-                        == maybe_next.as_ref().map(|x| (self.proj)(&x));
+                    let is_eq = self.last.as_ref().map(|x| (self.proj)(x))
+                        == maybe_next.as_ref().map(|x| (self.proj)(x));
                     if !is_eq {
                         self.ix.inc();
                     }
@@ -121,7 +124,17 @@ where
     }
 }
 
-pub(crate) fn run_enumerate<I, Item>(iter: I) -> RunEnumerateIter<I, Item>
+/// Given an iterator, the enumeration doesn't change when consecutive
+/// items are the same.
+///
+/// # Example
+/// ```
+/// use touchtype::run_enumerate::run_enumerate;
+///
+/// let runs: Vec<(usize, char)> = run_enumerate("aabc".chars()).collect();
+/// assert_eq!(runs, &[(0, 'a'), (0, 'a'), (1, 'b'), (2, 'c')]);
+/// ```
+pub fn run_enumerate<I, Item>(iter: I) -> RunEnumerateIter<I, Item>
 where
     Item: PartialEq,
     I: Iterator<Item = Item>,
@@ -140,6 +153,7 @@ where
 /// `proj` computes the floor division by 3 which groups items by triplets.
 ///
 /// ```
+/// # use touchtype::run_enumerate::run_enumerate_with;
 ///   let ints: std::ops::Range<i32> = 1..6;
 ///   let mut iter = ints.enumerate();
 ///   let v: Vec<(usize, (usize, i32))> = run_enumerate_with(&mut iter, |x| x.0 / 3).collect();
@@ -154,7 +168,7 @@ where
 ///       ]
 ///   )
 /// ```
-pub(crate) fn run_enumerate_with<I, R, F>(
+pub fn run_enumerate_with<I, R, F>(
     iter: I,
     f: F,
 ) -> RunEnumerateProjectIter<I, <I as Iterator>::Item, F>
@@ -173,10 +187,10 @@ where
 
 #[cfg(test)]
 mod test {
-    use crate::uniq_enumerate::{run_enumerate, run_enumerate_with};
+    use crate::run_enumerate::{run_enumerate, run_enumerate_with};
     #[test]
     pub fn it_increases_only_when_item_change() {
-        let v = vec![1, 1, 2, 2, 3, 4];
+        let v = [1, 1, 2, 2, 3, 4];
         let mut iter = v.iter();
         let v: Vec<(usize, &u8)> = run_enumerate(&mut iter).collect();
         assert_eq!(v, &[(0, &1), (0, &1), (1, &2), (1, &2), (2, &3), (3, &4)])
@@ -0,0 +1,459 @@
+//! Sessions are finished practices as saved on disk, they are the base of
+//! every statistic shown after the practice.
+//!
+//! Session: the challenge and the keystrokes typed against it.
+//! Summary: speed, accuracy and mistakes of one session.
+//! History: every session saved so far.
+//! Report: a summary compared to the history.
+//! Day: the average of the sessions of a day.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::analysis::rhythm::Rhythm;
+use crate::idle::{self, Gap};
+use crate::latency::Latency;
+use crate::model::{Challenge, Keystroke, Practice, Touch, Word};
+
+/// Average number of touches in a word, to compute words per minute.
+const CHARS_PER_WORD: f32 = 5.0;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A finished practice.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    /// Like `practice_1700000000`, the file it is saved in.
+    pub name: String,
+    /// Seconds since UNIX_EPOCH when the session was saved.
+    pub timestamp: u64,
+    /// The words of the challenge separated by spaces.
+    pub text: String,
+    /// `keystrokes[i]` was typed at the position `i` of the challenge.
+    pub keystrokes: Vec<Keystroke>,
+    /// The practice was abandoned before its end.
+    #[serde(default)]
+    pub incomplete: bool,
+    /// The time left out of the timings while away from the keyboard.
+    #[serde(default)]
+    pub gaps: Vec<Gap>,
+    /// A gap was long enough to doubt the session was typed in one go.
+    #[serde(default)]
+    pub suspicious: bool,
+    /// How long the keystrokes took to show, when measured.
+    #[serde(default)]
+    pub latencies: Vec<Latency>,
+}
+
+/// A word of the challenge that was not typed as expected.
+#[derive(Debug, Clone)]
+pub struct Mistake {
+    /// The word expected.
+    pub word: Word,
+    /// What was typed instead.
+    pub typed: String,
+}
+
+/// The outcome of a session.
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    /// Words per minute, counting only successful touches.
+    pub wpm: f32,
+    /// Ratio of successful touches, between 0 and 1.
+    pub accuracy: f32,
+    /// How steady the speed was, between 0 and 1.
+    pub consistency: f32,
+    /// Words per minute for each second of the session.
+    pub speed: Vec<f32>,
+    /// Seconds since the first touch at which a wrong touch was typed.
+    pub errors: Vec<f32>,
+    /// The words not typed as expected, in order.
+    pub mistakes: Vec<Mistake>,
+    /// The practice was abandoned before its end.
+    pub incomplete: bool,
+    /// Seconds left out while away from the keyboard.
+    pub idle_s: f32,
+    /// A long gap was left out, see `Session::suspicious`.
+    pub suspicious: bool,
+    /// The intervals between the keystrokes.
+    pub rhythm: Rhythm,
+}
+
+/// Averages of the sessions saved during a day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Day {
+    /// Days since UNIX_EPOCH.
+    pub day: u64,
+    /// Average speed, in words per minute.
+    pub wpm: f32,
+    /// Average ratio of successful touches, between 0 and 1.
+    pub accuracy: f32,
+    /// Number of sessions saved that day.
+    pub sessions: usize,
+}
+
+/// Sessions saved so far, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    sessions: Vec<Session>,
+}
+
+/// A summary along with the previous results.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// The outcome of this session.
+    pub summary: Summary,
+    /// Best speed before this session.
+    pub best_wpm: Option<f32>,
+    /// Average speed of the previous sessions, up to `History::RECENT`.
+    pub recent_wpm: Option<f32>,
+    /// Every day with a session, including this one.
+    pub daily: Vec<Day>,
+    /// The lesson this session unlocked, if any.
+    pub unlocked: Option<String>,
+}
+
+/// Returns the directory where sessions are saved.
+pub fn data_dir() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    Path::new(home.as_str()).join(".config/touchtype")
+}
+
+/// Returns the seconds elapsed since UNIX_EPOCH.
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl From<&Practice> for Session {
+    fn from(practice: &Practice) -> Self {
+        let timestamp = timestamp();
+        Session {
+            name: practice.name().to_string(),
+            timestamp,
+            text: practice.challenge().text(),
+            keystrokes: practice.keystrokes().to_vec(),
+            incomplete: practice.is_abandoned(),
+            gaps: practice.gaps().to_vec(),
+            suspicious: false,
+            latencies: practice.latencies().to_vec(),
+        }
+    }
+}
+
+impl Session {
+    /// Writes the session as json in the directory `dir`.
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir).context(format!("cannot create directory {:?}", dir))?;
+        let path = dir.join(format!("{}.json", self.name));
+        let json = serde_json::to_string(self)?;
+        fs::write(&path, json).context(format!("cannot create file at {:?}", path))?;
+        Ok(path)
+    }
+
+    /// Reads the session saved at `path`.
+    pub fn load(path: &Path) -> Result<Session> {
+        let json = fs::read_to_string(path).context(format!("cannot read {:?}", path))?;
+        Ok(serde_json::from_str(json.as_str())?)
+    }
+
+    /// Returns the challenge the session was typed against.
+    pub fn challenge(&self) -> Challenge {
+        Challenge::from_str(self.text.as_str()).expect("parsing a challenge never fails")
+    }
+
+    /// Returns the ranges of keystrokes typed without a long gap.
+    pub fn segments(&self) -> Vec<Range<usize>> {
+        idle::segments(self.keystrokes.len(), &self.gaps)
+    }
+
+    /// Returns the session duration, from the first to the last keystroke.
+    pub fn duration_s(&self) -> f32 {
+        self.keystrokes
+            .last()
+            .map(|k| k.at.as_secs_f32())
+            .unwrap_or_default()
+    }
+
+    /// Computes the outcome of the session.
+    pub fn summary(&self) -> Summary {
+        let challenge = self.challenge();
+        let success: Vec<bool> = self.keystrokes.iter().map(Keystroke::is_success).collect();
+        let succ = success.iter().filter(|s| **s).count();
+        let total = success.len();
+        let duration = self.duration_s();
+
+        let wpm = if duration > 0.0 {
+            succ as f32 / CHARS_PER_WORD * 60.0 / duration
+        } else {
+            0.0
+        };
+        let accuracy = if total > 0 {
+            succ as f32 / total as f32
+        } else {
+            0.0
+        };
+
+        let mut speed = vec![0.0; duration.ceil() as usize];
+        for (k, _) in self.keystrokes.iter().zip(&success).filter(|(_, s)| **s) {
+            let second = (k.at.as_secs_f32() as usize).min(speed.len().saturating_sub(1));
+            if let Some(s) = speed.get_mut(second) {
+                *s += 60.0 / CHARS_PER_WORD;
+            }
+        }
+
+        let errors = self
+            .keystrokes
+            .iter()
+            .zip(&success)
+            .filter(|(_, s)| !**s)
+            .map(|(k, _)| k.at.as_secs_f32())
+            .collect();
+
+        Summary {
+            wpm,
+            accuracy,
+            consistency: consistency(&speed),
+            errors,
+            mistakes: self.mistakes(&challenge, &success),
+            speed,
+            incomplete: self.incomplete,
+            idle_s: self.gaps.iter().map(|g| g.idle.as_secs_f32()).sum(),
+            suspicious: self.suspicious,
+            rhythm: Rhythm::from(self),
+        }
+    }
+
+    /// Groups the keystrokes by word and keeps the words with a failure.
+    /// A space belongs to the word preceding it.
+    fn mistakes(&self, challenge: &Challenge, success: &[bool]) -> Vec<Mistake> {
+        let mut mistakes: Vec<Mistake> = vec![];
+        let mut current: Option<(usize, String, bool)> = None;
+        for (i, (k, s)) in self.keystrokes.iter().zip(success).enumerate() {
+            let Some(wix) = challenge.word_index_at(i) else {
+                break;
+            };
+            if current.as_ref().map(|c| c.0) != Some(wix) {
+                if let Some((wix, typed, true)) = current.take() {
+                    mistakes.push(Mistake {
+                        word: challenge.words()[wix].clone(),
+                        typed,
+                    });
+                }
+                current = Some((wix, String::new(), false));
+            }
+            if let Some((_, typed, failed)) = current.as_mut() {
+                *failed |= !s;
+                match k.typed {
+                    Touch::Char(c) => typed.push(c),
+                    // only a misplaced space is worth showing
+                    Touch::Space if !s => typed.push(' '),
+                    Touch::Space => (),
+                }
+            }
+        }
+        if let Some((wix, typed, true)) = current {
+            mistakes.push(Mistake {
+                word: challenge.words()[wix].clone(),
+                typed,
+            });
+        }
+        mistakes
+    }
+}
+
+/// 1 minus the coefficient of variation of `samples`, 0 when there is
+/// nothing to compare.
+pub(crate) fn consistency(samples: &[f32]) -> f32 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+    let n = samples.len() as f32;
+    let mean = samples.iter().sum::<f32>() / n;
+    if mean == 0.0 {
+        return 0.0;
+    }
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / n;
+    (1.0 - variance.sqrt() / mean).clamp(0.0, 1.0)
+}
+
+impl History {
+    /// Number of sessions used for the recent average.
+    pub const RECENT: usize = 10;
+
+    /// Loads every session saved in `dir`, ignoring files that are not
+    /// sessions.
+    pub fn load(dir: &Path) -> Result<History> {
+        let mut sessions: Vec<Session> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .filter_map(|p| Session::load(p.as_path()).ok())
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e).context(format!("cannot read {:?}", dir)),
+        };
+        sessions.sort_by_key(|s| s.timestamp);
+        Ok(History { sessions })
+    }
+
+    /// Adds a session just saved.
+    pub fn push(&mut self, session: Session) {
+        self.sessions.push(session);
+    }
+
+    /// Returns the sessions, oldest first.
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    /// Returns the last sessions, up to `History::RECENT`.
+    pub fn recent(&self) -> &[Session] {
+        &self.sessions[self.sessions.len().saturating_sub(Self::RECENT)..]
+    }
+
+    /// Returns the sessions flagged for a long gap.
+    pub fn suspicious(&self) -> impl Iterator<Item = &Session> {
+        self.sessions.iter().filter(|s| s.suspicious)
+    }
+
+    /// Compares `session` to the sessions recorded so far, leaving out the
+    /// incomplete ones.
+    pub fn report(&self, session: &Session) -> Report {
+        let previous: Vec<&Session> = self
+            .sessions
+            .iter()
+            .filter(|s| s.name != session.name && !s.incomplete)
+            .collect();
+        let mut daily: Vec<&Session> = previous.clone();
+        if !session.incomplete {
+            daily.push(session);
+        }
+        let previous: Vec<f32> = previous.iter().map(|s| s.summary().wpm).collect();
+        let best_wpm = previous.iter().copied().reduce(f32::max);
+        let recent = &previous[previous.len().saturating_sub(Self::RECENT)..];
+        let recent_wpm = if recent.is_empty() {
+            None
+        } else {
+            Some(recent.iter().sum::<f32>() / recent.len() as f32)
+        };
+        Report {
+            summary: session.summary(),
+            best_wpm,
+            recent_wpm,
+            daily: daily_averages(daily),
+            unlocked: None,
+        }
+    }
+}
+
+/// Groups `sessions`, sorted by timestamp, by day.
+fn daily_averages(sessions: Vec<&Session>) -> Vec<Day> {
+    let mut days: Vec<Day> = vec![];
+    for s in sessions {
+        let day = s.timestamp / SECONDS_PER_DAY;
+        let summary = s.summary();
+        match days.last_mut() {
+            Some(d) if d.day == day => {
+                let n = d.sessions as f32;
+                d.wpm = (d.wpm * n + summary.wpm) / (n + 1.0);
+                d.accuracy = (d.accuracy * n + summary.accuracy) / (n + 1.0);
+                d.sessions += 1;
+            }
+            _ => days.push(Day {
+                day,
+                wpm: summary.wpm,
+                accuracy: summary.accuracy,
+                sessions: 1,
+            }),
+        }
+    }
+    days
+}
+
+impl Day {
+    /// Returns the day as `yyyy-mm-dd`.
+    pub fn date(&self) -> String {
+        // from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = self.day as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let d = doy - (153 * mp + 2) / 5 + 1;
+        let m = if mp < 10 { mp + 3 } else { mp - 9 };
+        let y = yoe + era * 400 + i64::from(m <= 2);
+        format!("{:04}-{:02}-{:02}", y, m, d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{History, Session};
+    use crate::model::{Keystroke, Touch};
+
+    fn session(text: &str, typed: &str) -> Session {
+        Session {
+            name: "test".to_string(),
+            timestamp: 0,
+            text: text.to_string(),
+            keystrokes: text
+                .chars()
+                .zip(typed.chars())
+                .enumerate()
+                .map(|(i, (e, c))| {
+                    Keystroke::new(
+                        Touch::from(e),
+                        Touch::from(c),
+                        Duration::from_millis(200 * i as u64),
+                    )
+                })
+                .collect(),
+            ..Session::default()
+        }
+    }
+
+    #[test]
+    pub fn it_summarizes_a_session() {
+        let summary = session("this is a test", "thos is a tesy").summary();
+        // 12 successful touches in 2.6s
+        assert!((summary.wpm - 12.0 / 5.0 * 60.0 / 2.6).abs() < 0.01);
+        assert!((summary.accuracy - 12.0 / 14.0).abs() < 0.01);
+        assert_eq!(summary.speed.len(), 3);
+        let mistakes: Vec<(&str, &str)> = summary
+            .mistakes
+            .iter()
+            .map(|m| (m.word.as_str(), m.typed.as_str()))
+            .collect();
+        assert_eq!(mistakes, &[("this", "thos"), ("test", "tesy")]);
+        assert_eq!(summary.errors, &[0.4, 2.6]);
+    }
+
+    #[test]
+    pub fn it_averages_sessions_by_day() {
+        let mut first = session("a b", "a b");
+        first.timestamp = 1_760_000_000;
+        let mut second = session("a b", "a c");
+        second.timestamp = first.timestamp + 60;
+        second.name = "second".to_string();
+        let mut history = History::default();
+        history.push(first);
+        let report = history.report(&second);
+        assert_eq!(report.daily.len(), 1);
+        assert_eq!(report.daily[0].sessions, 2);
+        assert!((report.daily[0].accuracy - (1.0 + 2.0 / 3.0) / 2.0).abs() < 0.01);
+        assert_eq!(report.daily[0].date(), "2025-10-09");
+    }
+}
@@ -0,0 +1,128 @@
+//! Preferences of the user, saved between runs.
+//!
+//! CaretStyle: how the caret shows the next touch to type.
+//! Settings: the caret, the font of the practice, saved as json in the data
+//! directory.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// How the caret is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaretStyle {
+    /// A box behind the touch.
+    Block,
+    /// A bar before the touch.
+    Line,
+    /// A bar under the touch.
+    #[default]
+    Underline,
+    /// No caret.
+    Off,
+}
+
+/// The preferences of the user, saved between runs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// How the caret is drawn.
+    pub caret: CaretStyle,
+    /// The caret slides from a touch to the next instead of jumping.
+    pub smooth_caret: bool,
+    /// The caret blinks while no touch is typed.
+    pub blink: bool,
+    /// The font of the practice as a Pango description like `Monospace 20`,
+    /// the one of the theme if None.
+    pub font: Option<String>,
+    /// Shows how long the keystrokes take to show, above the practice.
+    pub latency_overlay: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            caret: CaretStyle::default(),
+            smooth_caret: true,
+            blink: true,
+            font: None,
+            latency_overlay: false,
+        }
+    }
+}
+
+impl CaretStyle {
+    /// Every style, in the order offered.
+    pub const ALL: [CaretStyle; 4] = [
+        CaretStyle::Block,
+        CaretStyle::Line,
+        CaretStyle::Underline,
+        CaretStyle::Off,
+    ];
+
+    /// Returns the name of the style, as shown in the menu.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CaretStyle::Block => "block",
+            CaretStyle::Line => "line",
+            CaretStyle::Underline => "underline",
+            CaretStyle::Off => "off",
+        }
+    }
+}
+
+impl Settings {
+    fn path(dir: &Path) -> PathBuf {
+        dir.join(SETTINGS_FILE)
+    }
+
+    /// Loads the settings saved in `dir`, the default ones if they were
+    /// never saved.
+    pub fn load(dir: &Path) -> Result<Settings> {
+        let path = Settings::path(dir);
+        match fs::read_to_string(&path) {
+            Ok(json) => Ok(serde_json::from_str(json.as_str())?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(e) => Err(e).context(format!("cannot read {:?}", path)),
+        }
+    }
+
+    /// Saves the settings in `dir`.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).context(format!("cannot create directory {:?}", dir))?;
+        let path = Settings::path(dir);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .context(format!("cannot create file at {:?}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CaretStyle, Settings};
+
+    #[test]
+    pub fn it_saves_and_loads_the_settings() {
+        let dir = std::env::temp_dir().join(format!("touchtype_settings_{}", std::process::id()));
+        assert_eq!(Settings::load(&dir).unwrap(), Settings::default());
+        let settings = Settings {
+            caret: CaretStyle::Block,
+            smooth_caret: false,
+            font: Some("DejaVu Sans Mono 18".to_string()),
+            ..Settings::default()
+        };
+        settings.save(&dir).unwrap();
+        assert_eq!(Settings::load(&dir).unwrap(), settings);
+
+        // missing settings take their default value
+        std::fs::write(dir.join("settings.json"), r#"{"caret": "Line"}"#).unwrap();
+        let loaded = Settings::load(&dir).unwrap();
+        assert_eq!(loaded.caret, CaretStyle::Line);
+        assert!(loaded.blink);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
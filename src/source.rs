@@ -0,0 +1,675 @@
+//! Where the words of a challenge come from.
+//!
+//! ChallengeSource: gives words on demand, described by a `SourceInfo`.
+//! Frequency: words sampled according to their frequency in a corpus.
+//! Passages: quotes or code snippets, typed in order one after the other.
+//! Text: a custom text typed from start to end, over and over.
+//! Numbers: random numbers.
+//! Drills: combinations of a few letters, repeated to build reflexes.
+//! Registry: the sources known to the application, by name.
+
+use anyhow::{Context, Result};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng, RngCore,
+};
+use std::{collections::VecDeque, path::Path};
+
+use crate::model::{TouchTypingError, Word};
+
+/// The words of Shakespeare, with their frequency.
+pub const FREQUENCY_PATH: &str = "./data/t8.shakespeare.freq";
+/// A quote per line.
+pub const QUOTES_PATH: &str = "./data/quotes.txt";
+/// Code snippets, separated by empty lines.
+pub const CODE_PATH: &str = "./data/code.txt";
+
+/// Under this many real words made of the allowed letters, made-up words
+/// are added to the pool.
+const MIN_POOL_WORDS: usize = 50;
+/// How many made-up words are added to a pool.
+const PSEUDO_WORDS: usize = 200;
+/// A letter typed less than this fraction of its fair share in the real
+/// words, like punctuation or `z`, gets made-up words of its own.
+const RARE_LETTER_RATIO: f64 = 0.5;
+
+/// A setting of a source, as a string to be set from anywhere.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceOption {
+    /// Like `path`.
+    pub name: &'static str,
+    /// What the option sets.
+    pub description: &'static str,
+    /// The current value.
+    pub value: String,
+}
+
+/// What a source is, shown to choose it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceInfo {
+    /// Like `words`, to select the source.
+    pub name: &'static str,
+    /// What the words are, as shown in a tooltip.
+    pub description: &'static str,
+    /// The settings of the source.
+    pub options: Vec<SourceOption>,
+    /// The words follow each other in order, nothing should be mixed in.
+    pub ordered: bool,
+}
+
+/// Gives the words of the practices.
+pub trait ChallengeSource {
+    /// Describes the source and its current settings.
+    fn info(&self) -> SourceInfo;
+
+    /// Sets the option `name` of `info().options`.
+    fn set_option(&mut self, name: &str, value: &str) -> Result<()>;
+
+    /// Returns the next `n` words, fewer if the source has no more.
+    fn words(&mut self, rng: &mut dyn RngCore, n: usize) -> Result<Vec<Word>>;
+
+    /// Restricts the words to those made of `letters`, or lifts the
+    /// restriction if None. Sources that cannot ignore it.
+    fn set_letters(&mut self, _letters: Option<&[char]>) {}
+}
+
+/// Words sampled by frequency from a file of words and counts.
+pub struct Frequency {
+    path: String,
+    /// Words and their frequencies, loaded from `path` on first use.
+    vocabulary: Option<Vec<(Word, u32)>>,
+    /// Only words made of these letters are generated, any word if None.
+    letters: Option<Vec<char>>,
+    /// Words that can be generated and their distribution, built from the
+    /// vocabulary and the letters on first use.
+    pool: Option<(Vec<Word>, WeightedIndex<u32>)>,
+}
+
+/// Passages of a file, typed in order.
+pub struct Passages {
+    name: &'static str,
+    description: &'static str,
+    path: String,
+    /// Passages are separated by empty lines, otherwise one per line.
+    paragraphs: bool,
+    /// Loaded from `path` on first use.
+    passages: Option<Vec<Vec<Word>>>,
+    /// Words of the passages picked so far and not yet given.
+    queue: VecDeque<Word>,
+}
+
+/// A custom text, set with the option `path`.
+pub struct Text {
+    path: Option<String>,
+    /// Loaded from `path` on first use.
+    words: Option<Vec<Word>>,
+    /// Index of the next word to give.
+    position: usize,
+}
+
+/// Random numbers.
+pub struct Numbers {
+    /// Maximum number of digits.
+    digits: u32,
+}
+
+/// Combinations of a few letters.
+pub struct Drills {
+    letters: Vec<char>,
+    /// Letters of the lesson, if any, replace `letters`.
+    lesson: Option<Vec<char>>,
+    /// Number of letters in each combination.
+    size: usize,
+}
+
+/// Builds a source in its default settings.
+type Constructor = Box<dyn Fn() -> Box<dyn ChallengeSource>>;
+
+/// The sources to choose from, in the order they are offered.
+pub struct Registry {
+    constructors: Vec<Constructor>,
+}
+
+fn unknown_option(source: &str, option: &str) -> anyhow::Error {
+    TouchTypingError::UnknownOption(source.to_string(), option.to_string()).into()
+}
+
+fn invalid_option(option: &str, value: &str) -> anyhow::Error {
+    TouchTypingError::InvalidOption(option.to_string(), value.to_string()).into()
+}
+
+/// Reads a .freq file made of lines `word count`.
+fn read_vocabulary(path: &Path) -> Result<Vec<(Word, u32)>> {
+    Ok(std::fs::read_to_string(path)
+        .with_context(|| TouchTypingError::FileParseError)?
+        .lines()
+        .filter_map(
+            |line| match line.split(' ').collect::<Vec<&str>>().as_slice() {
+                [word, count] => {
+                    let count = count.parse::<u32>().ok()?;
+                    Some((Word::from(*word), count))
+                }
+                _ => None,
+            },
+        )
+        .collect())
+}
+
+/// Returns whether `word` is typed only with `letters`, shift aside.
+fn is_made_of(word: &Word, letters: &[char]) -> bool {
+    word.as_str()
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|c| letters.contains(&c))
+}
+
+/// Makes up a word of 2 to 5 letters.
+fn pseudo_word(rng: &mut dyn RngCore, letters: &[char]) -> Word {
+    let len = rng.gen_range(2..=5);
+    let word: String = (0..len)
+        .map(|_| letters[rng.gen_range(0..letters.len())])
+        .collect();
+    Word::from(word.as_str())
+}
+
+/// Adds made-up words of the `letters` rare in `words`, weighted so that
+/// each of them is typed as often as if every letter was equally likely:
+/// the lessons need every letter they unlocked to be typed.
+fn add_rare_letters(
+    rng: &mut dyn RngCore,
+    letters: &[char],
+    words: &mut Vec<Word>,
+    freqs: &mut Vec<u32>,
+) {
+    // how many touches of each letter, and of any letter, a sample makes
+    let mut touches = vec![0u64; letters.len()];
+    let mut total = 0u64;
+    for (word, freq) in words.iter().zip(freqs.iter()) {
+        for c in word.as_str().chars().flat_map(char::to_lowercase) {
+            if let Some(i) = letters.iter().position(|l| *l == c) {
+                touches[i] += *freq as u64;
+            }
+            total += *freq as u64;
+        }
+    }
+    let fair = total as f64 / letters.len() as f64;
+    let rare: Vec<char> = letters
+        .iter()
+        .zip(&touches)
+        .filter(|(_, n)| (**n as f64) < fair * RARE_LETTER_RATIO)
+        .map(|(c, _)| *c)
+        .collect();
+    if rare.is_empty() {
+        return;
+    }
+    // the made-up words take the share of the touches the rare letters
+    // would have if fair
+    let share = rare.len() as f64 / letters.len() as f64;
+    let made_up: Vec<Word> = (0..PSEUDO_WORDS).map(|_| pseudo_word(rng, &rare)).collect();
+    let length: usize = made_up.iter().map(Word::len).sum();
+    let weight = if total == 0 {
+        1
+    } else {
+        let mass = total as f64 * share / (1.0 - share);
+        u32::try_from((mass / length as f64).ceil() as u64).unwrap_or(u32::MAX)
+    };
+    freqs.extend(vec![weight.max(1); made_up.len()]);
+    words.extend(made_up);
+}
+
+fn split_words(text: &str) -> Vec<Word> {
+    text.split_whitespace().map(Word::from).collect()
+}
+
+impl Frequency {
+    /// Samples the words of the file at `path`, loaded on first use.
+    pub fn new(path: &str) -> Frequency {
+        Frequency {
+            path: path.to_string(),
+            vocabulary: None,
+            letters: None,
+            pool: None,
+        }
+    }
+
+    /// Keeps the words of the vocabulary made of the allowed letters, with
+    /// made-up words when there are too few of them or when some letters
+    /// are rare in them.
+    fn build_pool(&mut self, rng: &mut dyn RngCore) -> Result<(Vec<Word>, WeightedIndex<u32>)> {
+        if self.vocabulary.is_none() {
+            let vocabulary = read_vocabulary(Path::new(self.path.as_str()))?;
+            let _ = self.vocabulary.insert(vocabulary);
+        }
+        let vocabulary = self.vocabulary.as_ref().expect("vocabulary is loaded");
+        let (mut words, mut freqs): (Vec<Word>, Vec<u32>) = match &self.letters {
+            None => vocabulary.iter().cloned().unzip(),
+            Some(letters) => vocabulary
+                .iter()
+                .filter(|(w, _)| is_made_of(w, letters))
+                .cloned()
+                .unzip(),
+        };
+        let few = words.len() < MIN_POOL_WORDS;
+        if let Some(letters) = self.letters.as_ref().filter(|l| few && !l.is_empty()) {
+            // real and made-up words are then equally likely
+            freqs = vec![1; words.len()];
+            for _ in 0..PSEUDO_WORDS {
+                words.push(pseudo_word(rng, letters));
+                freqs.push(1);
+            }
+        }
+        if let Some(letters) = self.letters.as_ref().filter(|l| !l.is_empty()) {
+            add_rare_letters(rng, letters, &mut words, &mut freqs);
+        }
+        let dist = WeightedIndex::new(freqs)?;
+        Ok((words, dist))
+    }
+}
+
+impl ChallengeSource for Frequency {
+    fn info(&self) -> SourceInfo {
+        SourceInfo {
+            name: "words",
+            description: "Common words, the most frequent the most likely",
+            options: vec![SourceOption {
+                name: "path",
+                description: "File of lines 'word count'",
+                value: self.path.clone(),
+            }],
+            ordered: false,
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "path" => {
+                self.path = value.to_string();
+                self.vocabulary = None;
+                self.pool = None;
+                Ok(())
+            }
+            _ => Err(unknown_option("words", name)),
+        }
+    }
+
+    fn words(&mut self, rng: &mut dyn RngCore, n: usize) -> Result<Vec<Word>> {
+        if self.pool.is_none() {
+            let pool = self.build_pool(rng)?;
+            let _ = self.pool.insert(pool);
+        }
+        let (pool, dist) = self.pool.as_ref().expect("pool is built");
+        Ok((0..n).map(|_| pool[dist.sample(rng)].clone()).collect())
+    }
+
+    fn set_letters(&mut self, letters: Option<&[char]>) {
+        let letters = letters.map(<[char]>::to_vec);
+        if letters != self.letters {
+            self.letters = letters;
+            self.pool = None;
+        }
+    }
+}
+
+impl Passages {
+    /// Famous quotes, one per line.
+    pub fn quotes() -> Passages {
+        Passages {
+            name: "quotes",
+            description: "Famous quotes, one after the other",
+            path: QUOTES_PATH.to_string(),
+            paragraphs: false,
+            passages: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Code snippets, separated by empty lines.
+    ///
+    /// As there is no touch for a new line, the snippets are typed as words
+    /// separated by single spaces: indentation and line breaks are dropped.
+    pub fn code() -> Passages {
+        Passages {
+            name: "code",
+            description: "Snippets of code, with their symbols",
+            path: CODE_PATH.to_string(),
+            paragraphs: true,
+            passages: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    fn load(&self) -> Result<Vec<Vec<Word>>> {
+        let text = std::fs::read_to_string(self.path.as_str())
+            .with_context(|| format!("cannot read {}", self.path))?;
+        let passages: Vec<Vec<Word>> = if self.paragraphs {
+            text.split("\n\n").map(split_words).collect()
+        } else {
+            text.lines().map(split_words).collect()
+        };
+        Ok(passages.into_iter().filter(|p| !p.is_empty()).collect())
+    }
+}
+
+impl ChallengeSource for Passages {
+    fn info(&self) -> SourceInfo {
+        SourceInfo {
+            name: self.name,
+            description: self.description,
+            options: vec![SourceOption {
+                name: "path",
+                description: if self.paragraphs {
+                    "File of passages separated by empty lines"
+                } else {
+                    "File of one passage per line"
+                },
+                value: self.path.clone(),
+            }],
+            ordered: true,
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "path" => {
+                self.path = value.to_string();
+                self.passages = None;
+                self.queue.clear();
+                Ok(())
+            }
+            _ => Err(unknown_option(self.name, name)),
+        }
+    }
+
+    fn words(&mut self, rng: &mut dyn RngCore, n: usize) -> Result<Vec<Word>> {
+        if self.passages.is_none() {
+            let passages = self.load()?;
+            let _ = self.passages.insert(passages);
+        }
+        let passages = self.passages.as_ref().expect("passages are loaded");
+        if passages.is_empty() {
+            return Err(TouchTypingError::FileParseError.into());
+        }
+        while self.queue.len() < n {
+            let passage = &passages[rng.gen_range(0..passages.len())];
+            self.queue.extend(passage.iter().cloned());
+        }
+        Ok(self.queue.drain(..n).collect())
+    }
+}
+
+impl Text {
+    /// Without any text until its path is set.
+    pub fn new() -> Text {
+        Text {
+            path: None,
+            words: None,
+            position: 0,
+        }
+    }
+}
+
+impl Default for Text {
+    fn default() -> Self {
+        Text::new()
+    }
+}
+
+impl ChallengeSource for Text {
+    fn info(&self) -> SourceInfo {
+        SourceInfo {
+            name: "text",
+            description: "A text of your own, from start to end",
+            options: vec![SourceOption {
+                name: "path",
+                description: "File of the text",
+                value: self.path.clone().unwrap_or_default(),
+            }],
+            ordered: true,
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "path" => {
+                self.path = Some(value.to_string());
+                self.words = None;
+                self.position = 0;
+                Ok(())
+            }
+            _ => Err(unknown_option("text", name)),
+        }
+    }
+
+    fn words(&mut self, _rng: &mut dyn RngCore, n: usize) -> Result<Vec<Word>> {
+        if self.words.is_none() {
+            let path = self.path.as_ref().context("the text source needs a path")?;
+            let text =
+                std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path))?;
+            let _ = self.words.insert(split_words(text.as_str()));
+        }
+        let words = self.words.as_ref().expect("text is loaded");
+        if words.is_empty() {
+            return Err(TouchTypingError::FileParseError.into());
+        }
+        // starts over at the end of the text
+        let taken = words
+            .iter()
+            .cycle()
+            .skip(self.position)
+            .take(n)
+            .cloned()
+            .collect();
+        self.position = (self.position + n) % words.len();
+        Ok(taken)
+    }
+}
+
+impl ChallengeSource for Numbers {
+    fn info(&self) -> SourceInfo {
+        SourceInfo {
+            name: "numbers",
+            description: "Numbers, for the top row",
+            options: vec![SourceOption {
+                name: "digits",
+                description: "Maximum number of digits",
+                value: self.digits.to_string(),
+            }],
+            ordered: false,
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "digits" => {
+                self.digits = value
+                    .parse()
+                    .ok()
+                    .filter(|d| (1..=9).contains(d))
+                    .ok_or_else(|| invalid_option(name, value))?;
+                Ok(())
+            }
+            _ => Err(unknown_option("numbers", name)),
+        }
+    }
+
+    fn words(&mut self, rng: &mut dyn RngCore, n: usize) -> Result<Vec<Word>> {
+        Ok((0..n)
+            .map(|_| {
+                let digits = rng.gen_range(1..=self.digits);
+                let number = rng.gen_range(0..10u32.pow(digits));
+                Word::from(number.to_string().as_str())
+            })
+            .collect())
+    }
+}
+
+impl Default for Numbers {
+    fn default() -> Self {
+        Numbers { digits: 4 }
+    }
+}
+
+impl ChallengeSource for Drills {
+    fn info(&self) -> SourceInfo {
+        SourceInfo {
+            name: "drills",
+            description: "Combinations of a few letters, over and over",
+            options: vec![
+                SourceOption {
+                    name: "letters",
+                    description: "Letters combined, unless following lessons",
+                    value: self.letters.iter().collect(),
+                },
+                SourceOption {
+                    name: "size",
+                    description: "Letters in each combination",
+                    value: self.size.to_string(),
+                },
+            ],
+            ordered: false,
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "letters" if !value.is_empty() => self.letters = value.chars().collect(),
+            "size" => {
+                self.size = value
+                    .parse()
+                    .ok()
+                    .filter(|s| (1..=10).contains(s))
+                    .ok_or_else(|| invalid_option(name, value))?;
+            }
+            "letters" => return Err(invalid_option(name, value)),
+            _ => return Err(unknown_option("drills", name)),
+        }
+        Ok(())
+    }
+
+    fn words(&mut self, rng: &mut dyn RngCore, n: usize) -> Result<Vec<Word>> {
+        let letters = self.lesson.as_ref().unwrap_or(&self.letters);
+        Ok((0..n)
+            .map(|_| pseudo_word_of(rng, letters, self.size))
+            .collect())
+    }
+
+    fn set_letters(&mut self, letters: Option<&[char]>) {
+        self.lesson = letters.filter(|l| !l.is_empty()).map(<[char]>::to_vec);
+    }
+}
+
+/// Makes up a word of exactly `size` letters.
+fn pseudo_word_of(rng: &mut dyn RngCore, letters: &[char], size: usize) -> Word {
+    let word: String = (0..size)
+        .map(|_| letters[rng.gen_range(0..letters.len())])
+        .collect();
+    Word::from(word.as_str())
+}
+
+impl Default for Drills {
+    fn default() -> Self {
+        Drills {
+            letters: "fjdksla".chars().collect(),
+            lesson: None,
+            size: 2,
+        }
+    }
+}
+
+impl Registry {
+    /// Returns an empty registry.
+    pub fn new() -> Registry {
+        Registry {
+            constructors: vec![],
+        }
+    }
+
+    /// Returns the sources shipped with the application.
+    pub fn builtin() -> Registry {
+        let mut registry = Registry::new();
+        registry.register(|| Box::new(Frequency::new(FREQUENCY_PATH)));
+        registry.register(|| Box::new(Passages::quotes()));
+        registry.register(|| Box::new(Passages::code()));
+        registry.register(|| Box::new(Text::new()));
+        registry.register(|| Box::<Numbers>::default());
+        registry.register(|| Box::<Drills>::default());
+        registry
+    }
+
+    /// Adds a source, built by `constructor` whenever it is chosen.
+    pub fn register(&mut self, constructor: impl Fn() -> Box<dyn ChallengeSource> + 'static) {
+        self.constructors.push(Box::new(constructor));
+    }
+
+    /// Returns the sources in their default settings.
+    pub fn list(&self) -> Vec<SourceInfo> {
+        self.constructors.iter().map(|c| c().info()).collect()
+    }
+
+    /// Builds the source called `name`.
+    pub fn create(&self, name: &str) -> Result<Box<dyn ChallengeSource>> {
+        self.constructors
+            .iter()
+            .map(|c| c())
+            .find(|s| s.info().name == name)
+            .ok_or_else(|| TouchTypingError::UnknownSource(name.to_string()).into())
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::Registry;
+    use crate::model::Word;
+
+    fn strings(words: Vec<Word>) -> Vec<String> {
+        words.iter().map(|w| w.as_str().to_string()).collect()
+    }
+
+    #[test]
+    pub fn it_creates_sources_by_name() {
+        let registry = Registry::builtin();
+        let names: Vec<&str> = registry.list().iter().map(|i| i.name).collect();
+        assert_eq!(
+            names,
+            ["words", "quotes", "code", "text", "numbers", "drills"]
+        );
+        assert!(registry.create("poems").is_err());
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut numbers = registry.create("numbers").unwrap();
+        numbers.set_option("digits", "2").unwrap();
+        assert!(numbers.set_option("digits", "ten").is_err());
+        assert!(numbers.set_option("base", "8").is_err());
+        for number in strings(numbers.words(&mut rng, 20).unwrap()) {
+            assert!(number.len() <= 2 && number.parse::<u32>().is_ok());
+        }
+
+        let mut drills = registry.create("drills").unwrap();
+        drills.set_letters(Some(&['a', 'b']));
+        for word in strings(drills.words(&mut rng, 20).unwrap()) {
+            assert_eq!(word.len(), 2);
+            assert!(word.chars().all(|c| c == 'a' || c == 'b'));
+        }
+
+        let path = std::env::temp_dir().join(format!("touchtype_text_{}", std::process::id()));
+        std::fs::write(&path, "one two\nthree").unwrap();
+        let mut text = registry.create("text").unwrap();
+        assert!(text.words(&mut rng, 1).is_err());
+        text.set_option("path", path.to_str().unwrap()).unwrap();
+        assert_eq!(strings(text.words(&mut rng, 2).unwrap()), ["one", "two"]);
+        assert_eq!(
+            strings(text.words(&mut rng, 3).unwrap()),
+            ["three", "one", "two"]
+        );
+        assert!(text.info().ordered);
+        std::fs::remove_file(path).unwrap();
+    }
+}
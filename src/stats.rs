@@ -1,33 +1,60 @@
+//! Typing speed as it goes, in keys per second.
+
 use std::time::{Duration, Instant};
 
-pub(crate) struct Stats {
+/// The number of keys typed and the time spent typing them.
+pub struct Stats {
     duration_sum: Duration,
     last_key: Option<Instant>,
     count: u32,
+    /// Longer gaps between keys count as this long, if set.
+    idle: Option<Duration>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats::new()
+    }
 }
 
 impl Stats {
-    pub(crate) fn add(&mut self, ts: Instant) {
+    /// Counts a key typed at `ts`.
+    pub fn add(&mut self, ts: Instant) {
         self.count += 1;
-        self.duration_sum += match self.last_key {
+        let gap = match self.last_key {
             Some(last_key) => ts.duration_since(last_key),
             None => Duration::ZERO,
         };
+        self.duration_sum += self.idle.map_or(gap, |idle| gap.min(idle));
         self.last_key = Some(ts);
     }
 
-    pub(crate) fn avg_key_s(&self) -> f32 {
+    /// Leaves the time until the next key out, after a pause.
+    pub fn pause(&mut self) {
+        self.last_key = None;
+    }
+
+    /// Returns the average number of keys typed per second.
+    pub fn avg_key_s(&self) -> f32 {
         if self.duration_sum.is_zero() {
             0.0
         } else {
             self.count as f32 / self.duration_sum.as_secs_f32()
         }
     }
-    pub(crate) fn new() -> Self {
+    /// Starts without any key, counting the gaps as they are.
+    pub fn new() -> Self {
         Stats {
             duration_sum: Duration::ZERO,
             last_key: None,
             count: 0,
+            idle: None,
         }
     }
+
+    /// Counts the gaps between keys longer than `threshold` as `threshold`.
+    pub fn with_idle(mut self, threshold: Duration) -> Self {
+        self.idle = Some(threshold);
+        self
+    }
 }
@@ -0,0 +1,255 @@
+//! How the practice and the keyboard are drawn.
+//!
+//! Color: an RGB colour, written `#rrggbb` in the theme files.
+//! Font: a family and a size.
+//! Theme: the colours of the touches by state, of the carets and of the
+//! keyboard with a colour per finger, of the charts of the results, and
+//! the fonts. Some themes are
+//! bundled, others are json files in the `themes` directory of the data.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::layout::Finger;
+
+const BUNDLED: &[&str] = &[
+    include_str!("../data/themes/light.json"),
+    include_str!("../data/themes/dark.json"),
+    include_str!("../data/themes/high-contrast.json"),
+    include_str!("../data/themes/colour-blind.json"),
+];
+
+/// A colour, each channel between 0 and 1, `#rrggbb` in json.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color {
+    /// Red.
+    pub r: f64,
+    /// Green.
+    pub g: f64,
+    /// Blue.
+    pub b: f64,
+}
+
+/// A font family, size and weight.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Font {
+    /// Like `Monospace`.
+    pub family: String,
+    /// In pixels.
+    pub size: f64,
+    /// Bold rather than normal weight.
+    #[serde(default)]
+    pub bold: bool,
+}
+
+/// The colours and font of the keyboard.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyboardTheme {
+    /// Behind the keys.
+    pub background: Color,
+    /// The labels of the keys.
+    pub label: Color,
+    /// The key of the last touch.
+    pub pressed: Color,
+    /// Behind the keys typed by each finger, none if missing.
+    #[serde(default)]
+    pub fingers: HashMap<Finger, Color>,
+    /// The font of the labels.
+    pub font: Font,
+}
+
+/// The colours of the charts of the results.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChartTheme {
+    /// The axes and the point under the pointer.
+    pub axes: Color,
+    /// The line of the speed.
+    pub speed: Color,
+    /// The line of the accuracy.
+    pub accuracy: Color,
+    /// The crosses of the mistakes.
+    pub errors: Color,
+}
+
+/// The colours and fonts of the application.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    /// Like `dark`, as listed in the menu.
+    pub name: String,
+    /// Meant for a dark desktop.
+    #[serde(default)]
+    pub dark: bool,
+    /// Behind the practice.
+    pub background: Color,
+    /// The touches not typed yet.
+    pub text: Color,
+    /// The touches typed as expected.
+    pub typed: Color,
+    /// The touches not typed as expected.
+    pub mistyped: Color,
+    /// The last touch, typed as expected or not.
+    pub correct: Color,
+    /// The last touch, when not typed as expected.
+    pub wrong: Color,
+    /// The caret of the user.
+    pub caret: Color,
+    /// The caret of the ghost raced.
+    pub ghost: Color,
+    /// The carets of the other players of a race.
+    pub opponent: Color,
+    /// The font of the practice.
+    pub font: Font,
+    /// The colours and font of the keyboard.
+    pub keyboard: KeyboardTheme,
+    /// The colours of the charts, taken from the practice if missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chart: Option<ChartTheme>,
+}
+
+impl TryFrom<String> for Color {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        let hex = value
+            .strip_prefix('#')
+            .filter(|h| h.len() == 6)
+            .ok_or_else(|| anyhow!("{:?} is not a #rrggbb colour", value))?;
+        let channel = |i: usize| -> Result<f64> {
+            let c = u8::from_str_radix(&hex[i..i + 2], 16)
+                .context(format!("{:?} is not a #rrggbb colour", value))?;
+            Ok(c as f64 / 255.0)
+        };
+        Ok(Color {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        let channel = |c: f64| (c * 255.0).round().clamp(0.0, 255.0) as u8;
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            channel(color.r),
+            channel(color.g),
+            channel(color.b)
+        )
+    }
+}
+
+impl Color {
+    /// Returns the red, green and blue channels.
+    pub fn rgb(&self) -> (f64, f64, f64) {
+        (self.r, self.g, self.b)
+    }
+}
+
+impl Theme {
+    /// Returns the themes shipped with the application, the light one
+    /// first.
+    pub fn bundled() -> Vec<Theme> {
+        BUNDLED
+            .iter()
+            .map(|json| serde_json::from_str(json).expect("the bundled themes are valid"))
+            .collect()
+    }
+
+    /// Reads the theme saved as json at `path`.
+    pub fn load(path: &Path) -> Result<Theme> {
+        let json = fs::read_to_string(path).context(format!("cannot read {:?}", path))?;
+        serde_json::from_str(json.as_str()).context(format!("cannot parse {:?}", path))
+    }
+
+    /// Returns the bundled themes followed by those of the directory
+    /// `dir`, if any. A theme that cannot be loaded is left out, the
+    /// errors are returned along.
+    pub fn all(dir: &Path) -> (Vec<Theme>, Vec<anyhow::Error>) {
+        let mut themes = Theme::bundled();
+        let mut errors = vec![];
+        let Ok(entries) = fs::read_dir(dir) else {
+            return (themes, errors);
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            match Theme::load(&path) {
+                Ok(theme) => themes.push(theme),
+                Err(e) => errors.push(e),
+            }
+        }
+        (themes, errors)
+    }
+
+    /// Returns the first of `themes` matching the dark mode of the desktop.
+    pub fn matching(themes: &[Theme], dark: bool) -> Option<&Theme> {
+        themes.iter().find(|t| t.dark == dark)
+    }
+
+    /// Returns the colours of the charts, those of the practice for a theme
+    /// without any.
+    pub fn chart(&self) -> ChartTheme {
+        self.chart.unwrap_or(ChartTheme {
+            axes: self.typed,
+            speed: self.text,
+            accuracy: self.ghost,
+            errors: self.wrong,
+        })
+    }
+
+    /// Returns the colour behind the keys typed by `finger`, if any.
+    pub fn finger(&self, finger: Finger) -> Option<Color> {
+        self.keyboard.fingers.get(&finger).copied()
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::bundled().remove(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Color, Theme};
+    use crate::layout::Finger;
+
+    #[test]
+    pub fn it_loads_the_bundled_themes() {
+        let themes = Theme::bundled();
+        let names: Vec<&str> = themes.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["light", "dark", "high contrast", "colour blind"]
+        );
+        assert_eq!(Theme::matching(&themes, true).unwrap().name, "dark");
+        assert_eq!(Theme::matching(&themes, false).unwrap().name, "light");
+        for theme in &themes {
+            assert!(theme.finger(Finger::LeftPinky).is_some());
+            assert!(theme.chart.is_some());
+        }
+
+        // a theme without colours for the charts takes those of the practice
+        let theme = Theme {
+            chart: None,
+            ..Theme::default()
+        };
+        let json = serde_json::to_string(&theme).unwrap();
+        assert!(!json.contains("chart"));
+        let theme: Theme = serde_json::from_str(&json).unwrap();
+        assert_eq!(theme.chart().speed, theme.text);
+        assert_eq!(theme.chart().accuracy, theme.ghost);
+
+        let color = Color::try_from("#ff8000".to_string()).unwrap();
+        assert_eq!(color.rgb(), (1.0, 128.0 / 255.0, 0.0));
+        assert_eq!(String::from(color), "#ff8000");
+        assert!(Color::try_from("red".to_string()).is_err());
+    }
+}
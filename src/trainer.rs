@@ -0,0 +1,264 @@
+//! What happens between practices, whatever the interface.
+//!
+//! Trainer: generates the practices and, once one ends, saves it, reports
+//! on it, schedules its mistyped words for review and unlocks the next
+//! lesson when the last one is mastered. The practices leave out the idle
+//! gaps, sessions with a long one are flagged. What fails without stopping
+//! the practices is kept for the interface to show.
+
+use anyhow::Result;
+use rand::Rng;
+use std::path::PathBuf;
+
+use crate::curriculum::{Curriculum, Progress};
+use crate::idle::Idle;
+use crate::model::{Challenge, Practice, PracticeGenerator, Word};
+use crate::review::ReviewQueue;
+use crate::session::{History, Report, Session};
+use crate::source::{Registry, SourceInfo};
+
+/// How many times a mistyped word is repeated when practising missed words.
+const MISSED_REPEAT: usize = 3;
+
+/// Generates the practices and keeps what they leave behind.
+pub struct Trainer<R> {
+    pub(crate) generator: PracticeGenerator<R>,
+    pub(crate) history: History,
+    /// The lessons followed and how far the user is, if practising lessons.
+    pub(crate) lessons: Option<(Curriculum, Progress)>,
+    /// Words mistyped so far, to review from time to time.
+    pub(crate) review: ReviewQueue,
+    /// The sources the words can come from.
+    pub(crate) sources: Registry,
+    /// When the user is away from the keyboard.
+    pub(crate) idle: Idle,
+    /// Where the sessions, the progress and the review queue are saved.
+    dir: PathBuf,
+    /// What failed without stopping the practices, until taken.
+    pub(crate) warnings: Vec<anyhow::Error>,
+}
+
+impl<R> Trainer<R> {
+    /// Loads the history and the review queue saved in `dir`, starting
+    /// afresh when they cannot be read.
+    pub fn load(
+        mut generator: PracticeGenerator<R>,
+        lessons: Option<(Curriculum, Progress)>,
+        dir: PathBuf,
+    ) -> Trainer<R> {
+        let mut warnings = vec![];
+        let history = History::load(dir.as_path()).unwrap_or_else(|e| {
+            warnings.push(e.context("cannot load the history"));
+            History::default()
+        });
+        let review = ReviewQueue::load(dir.as_path()).unwrap_or_else(|e| {
+            warnings.push(e.context("cannot load the review queue"));
+            ReviewQueue::default()
+        });
+        generator.set_review(review.due(crate::session::timestamp()));
+        if let Some((curriculum, progress)) = &lessons {
+            generator.set_letters(Some(curriculum.letters(progress.unlocked(curriculum))));
+        }
+        Trainer {
+            generator,
+            history,
+            lessons,
+            review,
+            sources: Registry::builtin(),
+            idle: Idle::default(),
+            dir,
+            warnings,
+        }
+    }
+
+    /// Leaves out the idle gaps and pauses the practices as told by `idle`.
+    pub fn with_idle(mut self, idle: Idle) -> Trainer<R> {
+        self.idle = idle;
+        self
+    }
+
+    /// Changes how the time away from the keyboard is handled, from the
+    /// next practice.
+    pub fn set_idle(&mut self, idle: Idle) {
+        self.idle = idle;
+    }
+
+    /// Returns when the user is away from the keyboard.
+    pub fn idle(&self) -> &Idle {
+        &self.idle
+    }
+
+    /// Returns the sessions saved so far.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Returns the sources the words can be taken from.
+    pub fn sources(&self) -> Vec<SourceInfo> {
+        self.sources.list()
+    }
+
+    /// Returns the source the words are taken from.
+    pub fn source(&self) -> SourceInfo {
+        self.generator.source()
+    }
+
+    /// Generates the next practice.
+    pub fn generate(&mut self) -> Result<Practice>
+    where
+        R: Rng,
+    {
+        Ok(self.generator.generate()?.with_idle(self.idle.threshold))
+    }
+
+    /// Samples `n` more words for a growing practice.
+    pub fn grow(&mut self, n: usize) -> Result<Vec<Word>>
+    where
+        R: Rng,
+    {
+        self.generator.words(n)
+    }
+
+    /// Takes the words of the next practices from the source called
+    /// `name`, set with `options`.
+    pub fn select_source(&mut self, name: &str, options: &[(String, String)]) -> Result<()>
+    where
+        R: Rng,
+    {
+        let mut source = self.sources.create(name)?;
+        for (option, value) in options {
+            source.set_option(option, value)?;
+        }
+        self.generator.set_source(source)
+    }
+
+    /// Saves the ended `practice` and returns its report.
+    pub fn finish(&mut self, practice: &Practice) -> Result<Report> {
+        let mut session = Session::from(practice);
+        session.suspicious = session.gaps.iter().any(|g| g.idle >= self.idle.suspicious);
+        session.save(self.dir.as_path())?;
+        let mut report = self.history.report(&session);
+        self.schedule(&session);
+        self.history.push(session);
+        report.unlocked = self.progress();
+        Ok(report)
+    }
+
+    /// Keeps `e` with the warnings, for what failed around the trainer.
+    pub fn warn(&mut self, e: anyhow::Error) {
+        self.warnings.push(e);
+    }
+
+    /// Returns what failed since the last call without stopping the
+    /// practices, to be shown to the user.
+    pub fn take_warnings(&mut self) -> Vec<anyhow::Error> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Returns a practice on the words mistyped according to `report`.
+    pub fn missed(&self, report: &Report) -> Option<Practice> {
+        // each word is repeated to build some muscle memory
+        let words: Vec<Word> = report
+            .summary
+            .mistakes
+            .iter()
+            .flat_map(|m| vec![m.word.clone(); MISSED_REPEAT])
+            .collect();
+        if words.is_empty() {
+            return None;
+        }
+        let name = Practice::new_name().ok()?;
+        Some(Practice::new(Challenge::from(words), name).with_idle(self.idle.threshold))
+    }
+
+    /// Returns the lesson followed, as shown in a title.
+    pub fn lesson(&self) -> Option<String> {
+        let (curriculum, progress) = self.lessons.as_ref()?;
+        let unlocked = progress.unlocked(curriculum);
+        let lesson = curriculum.lessons.get(unlocked - 1)?;
+        Some(format!(
+            "lesson {}/{}: {} (keys {})",
+            unlocked,
+            curriculum.lessons.len(),
+            lesson.name,
+            curriculum.letters(unlocked).iter().collect::<String>()
+        ))
+    }
+
+    /// Schedules the words of `session` and gives the words now due to the
+    /// generator.
+    fn schedule(&mut self, session: &Session) {
+        self.review.record(session);
+        if let Err(e) = self.review.save(self.dir.as_path()) {
+            self.warnings
+                .push(e.context("cannot save the review queue"));
+        }
+        self.generator
+            .set_review(self.review.due(session.timestamp));
+    }
+
+    /// Unlocks the next lesson if the last one is mastered, the following
+    /// practices then use its letters. Returns the name of the lesson
+    /// unlocked.
+    fn progress(&mut self) -> Option<String> {
+        let (curriculum, progress) = self.lessons.as_mut()?;
+        let name = progress.update(curriculum, &self.history)?.name.clone();
+        if let Err(e) = progress.save(self.dir.as_path()) {
+            self.warnings.push(e.context("cannot save the progress"));
+        }
+        let letters = curriculum.letters(progress.unlocked(curriculum));
+        self.generator.set_letters(Some(letters));
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::time::{Duration, Instant};
+
+    use super::Trainer;
+    use crate::model::{PracticeGenerator, Touch};
+
+    #[test]
+    pub fn it_saves_and_reports_a_finished_practice() {
+        let dir = std::env::temp_dir().join(format!("touchtype_trainer_{}", std::process::id()));
+        let rng = StdRng::seed_from_u64(1);
+        let generator = PracticeGenerator::new(rng, 3, "./data/t8.shakespeare.freq");
+        let mut trainer = Trainer::load(generator, None, dir.clone());
+        let mut practice = trainer.generate().unwrap();
+        let start = Instant::now();
+        let mut i = 0;
+        // only the very first letter is mistyped
+        while let Some(expected) = practice.challenge().expected_at(i) {
+            let typed = if i == 0 { Touch::Char('#') } else { expected };
+            let ts = start + Duration::from_millis(200 * i as u64);
+            practice.press(&typed, ts);
+            i += 1;
+        }
+
+        let report = trainer.finish(&practice).unwrap();
+        assert_eq!(trainer.history.sessions().len(), 1);
+        assert_eq!(report.summary.mistakes.len(), 1);
+        let missed = trainer.missed(&report).unwrap();
+        assert_eq!(missed.challenge().word_count(), 3);
+        assert!(dir.join("review.json").exists());
+        assert!(trainer.take_warnings().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    pub fn it_keeps_the_warnings_for_the_interface() {
+        let dir = std::env::temp_dir().join(format!("touchtype_warnings_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("review.json"), "not json").unwrap();
+        let rng = StdRng::seed_from_u64(1);
+        let generator = PracticeGenerator::new(rng, 3, "./data/t8.shakespeare.freq");
+        let mut trainer = Trainer::load(generator, None, dir.clone());
+        let warnings = trainer.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(format!("{:#}", warnings[0]).starts_with("cannot load the review queue"));
+        assert!(trainer.take_warnings().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,488 @@
+/// Terminal interface, to practise over SSH or without a display.
+///
+/// Draws the practice with a colour per `TouchState`, the stats line and
+/// the keyboard, then the results between practices. The practices come
+/// from the same trainer as in the GTK interface, so the history, the
+/// lessons and the review queue are shared.
+use anyhow::Result;
+use crossterm::{
+    event::{
+        self, DisableFocusChange, EnableFocusChange, Event, KeyCode, KeyEventKind, KeyModifiers,
+    },
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use rand::Rng;
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame, Terminal,
+};
+use std::io::stdout;
+use std::time::{Duration, Instant};
+
+use crate::driver::{Driver, Effect};
+use crate::layout::Layout;
+use crate::model::{Practice, Touch, TouchState};
+use crate::session::Report;
+use crate::stats::Stats;
+use crate::trainer::Trainer;
+
+const WORDS_PER_LINE: usize = 8;
+/// Lines displayed at once, the text scrolls to keep the cursor in view.
+const VISIBLE_LINES: usize = 6;
+/// How long to wait for a key before checking the time left.
+const TICK: Duration = Duration::from_millis(100);
+/// Columns taken by a key of width 1.
+const KEY_COLUMNS: f64 = 4.0;
+/// Time counted down before a paused practice resumes.
+const RESUME_COUNTDOWN: Duration = Duration::from_secs(3);
+
+struct Tui<R> {
+    trainer: Trainer<R>,
+    driver: Driver,
+    stats: Stats,
+    /// The report of the practice that just ended, while it is shown.
+    report: Option<Report>,
+    layout: Layout,
+    /// The last touch typed, highlighted on the keyboard.
+    last: Option<Touch>,
+    /// When the paused practice resumes, once asked to.
+    resume: Option<Instant>,
+    /// The last problem met, shown on the stats line.
+    warning: Option<String>,
+}
+
+/// Runs the terminal interface until the user quits.
+pub(crate) fn run<R: Rng>(mut trainer: Trainer<R>, layout: Layout) -> Result<()> {
+    let practice = trainer.generate()?;
+    let tui = Tui {
+        stats: Stats::new().with_idle(trainer.idle().threshold),
+        trainer,
+        driver: Driver::new(practice),
+        report: None,
+        layout,
+        last: None,
+        resume: None,
+        warning: None,
+    };
+    terminal::enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen, EnableFocusChange)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+    let result = tui.run(&mut terminal);
+    // the terminal is restored even after an error
+    terminal::disable_raw_mode()?;
+    execute!(stdout(), DisableFocusChange, LeaveAlternateScreen)?;
+    result
+}
+
+/// Returns how to show a touch in `state`.
+fn style(state: &TouchState) -> Style {
+    match state {
+        TouchState::Attempted(true) => Style::default().fg(Color::DarkGray),
+        TouchState::Attempted(false) => Style::default().fg(Color::Red),
+        TouchState::Current(_) => Style::default().add_modifier(Modifier::REVERSED),
+        TouchState::Next => Style::default().add_modifier(Modifier::UNDERLINED),
+        TouchState::Future => Style::default(),
+    }
+}
+
+/// Returns the lines of the practice around the cursor.
+fn practice_lines(practice: &Practice) -> Vec<Line<'static>> {
+    // keep the line before the cursor in view
+    let first = (practice.cursor_word() / WORDS_PER_LINE).saturating_sub(1);
+    let mut lines: Vec<Vec<Span>> = vec![vec![]; VISIBLE_LINES];
+    for (touch, state, w) in practice.iter() {
+        let Some(spans) = (w / WORDS_PER_LINE)
+            .checked_sub(first)
+            .and_then(|i| lines.get_mut(i))
+        else {
+            continue;
+        };
+        let text = match (touch, &state) {
+            // a mistyped space must show
+            (Touch::Space, TouchState::Attempted(false)) => "·".to_string(),
+            (Touch::Space, _) => " ".to_string(),
+            (Touch::Char(c), _) => c.to_string(),
+        };
+        spans.push(Span::styled(text, style(&state)));
+    }
+    lines.into_iter().map(Line::from).collect()
+}
+
+impl<R: Rng> Tui<R> {
+    fn run<B: Backend>(mut self, terminal: &mut Terminal<B>) -> Result<()> {
+        loop {
+            if let Some(e) = self.trainer.take_warnings().pop() {
+                self.warning = Some(format!("{:#}", e));
+            }
+            terminal.draw(|f| self.draw(f))?;
+            let showing = self.report.is_some();
+            if !event::poll(TICK)? {
+                let now = Instant::now();
+                if self.resume.is_some_and(|at| at <= now) {
+                    self.resume = None;
+                    let effects = self.driver.resume(now);
+                    self.apply(effects)?;
+                }
+                self.tick(now)?;
+                continue;
+            }
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                Event::FocusLost if self.practice().is_started() => {
+                    self.pause();
+                    continue;
+                }
+                _ => continue,
+            };
+            let paused = self.practice().is_paused();
+            let quit = (key.code == KeyCode::Esc && showing)
+                || (key.code == KeyCode::Char('c')
+                    && key.modifiers.contains(KeyModifiers::CONTROL))
+                || (key.code == KeyCode::Char('q') && paused);
+            if quit {
+                return self.quit();
+            }
+            if key.code == KeyCode::Esc {
+                if paused && self.resume.is_none() {
+                    self.resume = Some(Instant::now() + RESUME_COUNTDOWN);
+                } else {
+                    self.pause();
+                }
+                continue;
+            }
+            let KeyCode::Char(c) = key.code else {
+                continue;
+            };
+            match c {
+                'r' | ' ' if paused => {
+                    self.resume.get_or_insert(Instant::now() + RESUME_COUNTDOWN);
+                    continue;
+                }
+                't' if paused => self.retry()?,
+                'a' if paused => self.abandon()?,
+                _ if paused => continue,
+                _ if !showing => {
+                    let touch = Touch::from(c);
+                    self.last = Some(touch);
+                    self.press(touch, Instant::now())?;
+                }
+                'q' => return Ok(()),
+                'r' => self.retry()?,
+                'm' => self.missed(),
+                'n' | ' ' => self.next_practice()?,
+                's' => {
+                    self.next_source();
+                    self.next_practice()?;
+                }
+                _ => continue,
+            }
+            if (self.report.is_none() && showing) || !self.practice().is_paused() {
+                self.resume = None;
+            }
+            if self.report.is_none() && showing {
+                self.last = None;
+            }
+        }
+    }
+
+    fn practice(&self) -> &Practice {
+        self.driver.practice()
+    }
+
+    /// Starts `practice`, the report shown is dismissed.
+    fn start(&mut self, practice: Practice) {
+        self.driver = Driver::new(practice);
+        self.stats = Stats::new().with_idle(self.trainer.idle().threshold);
+        self.report = None;
+    }
+
+    /// Types `touch` at `ts`.
+    fn press(&mut self, touch: Touch, ts: Instant) -> Result<()> {
+        let typed = self.practice().keystrokes().len();
+        let effects = self.driver.press(&touch, ts);
+        if self.practice().keystrokes().len() > typed {
+            self.stats.add(ts);
+        }
+        self.apply(effects)
+    }
+
+    /// Ends a timed practice whose time is up at `now`, and pauses the
+    /// practice left idle long enough.
+    fn tick(&mut self, now: Instant) -> Result<()> {
+        let effects = self.driver.tick(now);
+        self.apply(effects)?;
+        let idle = self.trainer.idle().auto_pause.is_some_and(|after| {
+            self.practice()
+                .since_last_touch(now)
+                .is_some_and(|idle| idle >= after)
+        });
+        if idle && !self.driver.is_ended() && !self.practice().is_paused() {
+            self.pause();
+        }
+        Ok(())
+    }
+
+    /// Stops the clock of the practice until resumed.
+    fn pause(&mut self) {
+        self.resume = None;
+        if self.report.is_none() {
+            self.driver.pause(Instant::now());
+            self.stats.pause();
+        }
+    }
+
+    /// Ends the practice before its end, it is saved as incomplete.
+    fn abandon(&mut self) -> Result<()> {
+        let effects = self.driver.abandon();
+        self.apply(effects)
+    }
+
+    /// Starts the same practice again.
+    fn retry(&mut self) -> Result<()> {
+        let practice = self.practice().retry()?;
+        self.start(practice);
+        Ok(())
+    }
+
+    /// Practises the words missed in the practice whose report is shown.
+    fn missed(&mut self) {
+        let missed = self.report.as_ref().and_then(|r| self.trainer.missed(r));
+        if let Some(practice) = missed {
+            self.start(practice);
+        }
+    }
+
+    fn next_practice(&mut self) -> Result<()> {
+        let practice = self.trainer.generate()?;
+        self.start(practice);
+        Ok(())
+    }
+
+    fn apply(&mut self, effects: Vec<Effect>) -> Result<()> {
+        for effect in effects {
+            match effect {
+                Effect::Grow(missing) => {
+                    let words = self.trainer.grow(missing)?;
+                    self.driver.extend(words);
+                }
+                Effect::End => self.report = Some(self.trainer.finish(self.driver.practice())?),
+                Effect::Skip => self.next_practice()?,
+                // the loop ticks often enough
+                Effect::Wake(_) => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the practice under way as incomplete before quitting.
+    fn quit(&mut self) -> Result<()> {
+        if self.report.is_none() && self.practice().is_started() {
+            self.abandon()?;
+        }
+        Ok(())
+    }
+
+    /// Takes the words from the next source of the registry that can give
+    /// some.
+    fn next_source(&mut self) {
+        let names: Vec<&str> = self.trainer.sources().iter().map(|s| s.name).collect();
+        let current = self.trainer.source().name;
+        let i = names.iter().position(|n| *n == current).unwrap_or_default();
+        for name in names.iter().cycle().skip(i + 1).take(names.len() - 1) {
+            match self.trainer.select_source(name, &[]) {
+                Ok(()) => return,
+                Err(e) => {
+                    self.warning = Some(format!("cannot take the words from {}: {:#}", name, e))
+                }
+            }
+        }
+    }
+
+    fn stats_line(&self) -> String {
+        let source = self.trainer.source().name;
+        let mut line = format!("{:.1}/s  ·  {}", self.stats.avg_key_s(), source);
+        if let Some(left) = self.practice().time_left(Instant::now()) {
+            line += &format!("  ·  {} s left", left.as_secs());
+        }
+        if let Some(lesson) = self.trainer.lesson() {
+            line += &format!("  ·  {}", lesson);
+        }
+        if let Some(warning) = &self.warning {
+            line += &format!("  ·  {}", warning);
+        }
+        line
+    }
+
+    fn keyboard_lines(&self) -> Vec<Line<'static>> {
+        let pressed = Style::default().fg(Color::Black).bg(Color::Green);
+        self.layout
+            .rows
+            .iter()
+            .map(|row| {
+                let spans: Vec<Span> = row
+                    .iter()
+                    .map(|key| {
+                        let columns = (key.width * KEY_COLUMNS).round() as usize;
+                        let text = format!("{:^1$}", key.label, columns);
+                        if self.last.is_some_and(|t| key.types(&t)) {
+                            Span::styled(text, pressed)
+                        } else {
+                            Span::raw(text)
+                        }
+                    })
+                    .collect();
+                Line::from(spans)
+            })
+            .collect()
+    }
+
+    fn results_lines(report: &Report) -> Vec<Line<'static>> {
+        let summary = &report.summary;
+        let mut lines = vec![
+            Line::styled(
+                format!("{:.0} wpm", summary.wpm),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Line::raw(format!(
+                "accuracy {:.1}%  ·  consistency {:.0}%  ·  rhythm {:.0}%",
+                summary.accuracy * 100.0,
+                summary.consistency * 100.0,
+                summary.rhythm.consistency * 100.0
+            )),
+        ];
+        if let Some(best) = report.best_wpm {
+            lines.push(Line::raw(format!("best {:.0} wpm", best)));
+        }
+        if let Some(lesson) = &report.unlocked {
+            lines.push(Line::raw(format!("lesson unlocked: {}", lesson)));
+        }
+        if summary.idle_s > 0.0 {
+            let flag = if summary.suspicious {
+                " (long pause)"
+            } else {
+                ""
+            };
+            lines.push(Line::raw(format!(
+                "{:.0} s idle left out{}",
+                summary.idle_s, flag
+            )));
+        }
+        let hesitant: Vec<String> = summary
+            .rhythm
+            .hesitant_touches()
+            .iter()
+            .map(|(t, n)| format!("{} ({})", t, n))
+            .collect();
+        if !hesitant.is_empty() {
+            lines.push(Line::raw(format!(
+                "hesitated before {}",
+                hesitant.join(", ")
+            )));
+        }
+        lines.push(Line::raw(""));
+        for m in &summary.mistakes {
+            lines.push(Line::from(vec![
+                Span::raw(format!("{}  ", m.word.as_str())),
+                Span::styled(m.typed.clone(), Style::default().fg(Color::Red)),
+            ]));
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::styled(
+            "[n]ext  [r]etry  [m]issed words  [s]ource  [q]uit",
+            Style::default().fg(Color::DarkGray),
+        ));
+        lines
+    }
+
+    fn pause_lines(&self) -> Vec<Line<'static>> {
+        let title = match self.resume {
+            Some(at) => {
+                let left = at.saturating_duration_since(Instant::now());
+                format!("Resuming in {}", left.as_secs() + 1)
+            }
+            None => "Paused".to_string(),
+        };
+        vec![
+            Line::styled(title, Style::default().add_modifier(Modifier::BOLD)),
+            Line::raw(""),
+            Line::styled(
+                "[r]esume  res[t]art  [a]bandon  [q]uit",
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]
+    }
+
+    fn draw(&self, f: &mut Frame) {
+        if let Some(report) = &self.report {
+            let block = Block::default().borders(Borders::ALL).title("Results");
+            let mut lines = Tui::<R>::results_lines(report);
+            if let Some(warning) = &self.warning {
+                lines.push(Line::styled(
+                    warning.clone(),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+            f.render_widget(Paragraph::new(lines).block(block), f.size());
+            return;
+        }
+        let areas = ratatui::layout::Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(VISIBLE_LINES as u16 + 2),
+            Constraint::Min(0),
+        ])
+        .split(f.size());
+        f.render_widget(
+            Paragraph::new(self.stats_line()).style(Style::default().fg(Color::DarkGray)),
+            areas[0],
+        );
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Type Touching");
+        let lines = if self.practice().is_paused() {
+            self.pause_lines()
+        } else {
+            practice_lines(self.practice())
+        };
+        f.render_widget(Paragraph::new(lines).block(block), areas[1]);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.layout.name.as_str());
+        f.render_widget(Paragraph::new(self.keyboard_lines()).block(block), areas[2]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::{Color, Modifier};
+    use std::str::FromStr;
+    use std::time::Instant;
+
+    use super::practice_lines;
+    use crate::model::{Challenge, Practice, Touch};
+
+    #[test]
+    pub fn it_colours_touches_by_state() {
+        let challenge = Challenge::from_str("ab cd").unwrap();
+        let mut practice = Practice::new(challenge, "test".to_string());
+        let now = Instant::now();
+        practice.press(&Touch::Char('a'), now);
+        practice.press(&Touch::Char('x'), now);
+        practice.press(&Touch::Char('x'), now);
+
+        let lines = practice_lines(&practice);
+        let spans = &lines[0].spans;
+        let text: String = spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(text, "ab·cd");
+        assert_eq!(spans[0].style.fg, Some(Color::DarkGray));
+        assert_eq!(spans[1].style.fg, Some(Color::Red));
+        assert_eq!(spans[2].style.fg, Some(Color::Red));
+        assert!(spans[3].style.add_modifier.contains(Modifier::REVERSED));
+        assert!(spans[4].style.add_modifier.contains(Modifier::UNDERLINED));
+        assert!(lines[1].spans.is_empty());
+    }
+}
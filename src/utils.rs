@@ -1,5 +1,12 @@
 use relm4::drawing::DrawHandler;
 use relm4::gtk;
+use relm4::gtk::pango;
+use std::time::{Duration, Instant};
+
+use crate::theme::{Color, Font};
+
+/// A key event older than that is from a clock other than the monotonic one.
+const MAX_EVENT_AGE: Duration = Duration::from_secs(10);
 
 pub trait Clear<'a> {
     fn clear(&'a mut self);
@@ -20,3 +27,69 @@ impl<'a, T: HasDrawHandler<'a>> Clear<'a> for T {
         cx.set_operator(op);
     }
 }
+
+/// Draws with the colours and the fonts of a theme.
+pub trait Themed {
+    fn set_color(&self, color: &Color);
+    fn set_font(&self, font: &Font);
+    /// Draws `layout` with its top left corner at the current point.
+    fn show_layout(&self, layout: &pango::Layout);
+}
+
+/// Returns the Pango description of a font of a theme.
+pub fn font_description(font: &Font) -> pango::FontDescription {
+    let mut description = pango::FontDescription::new();
+    description.set_family(&font.family);
+    description.set_absolute_size(font.size * pango::SCALE as f64);
+    if font.bold {
+        description.set_weight(pango::Weight::Bold);
+    }
+    description
+}
+
+/// Returns the time since the key event at `time`, in milliseconds as given
+/// by GDK, None if unknown. GDK takes the times of the events from the
+/// monotonic clock.
+pub fn event_age(time: u32) -> Option<Duration> {
+    if time == 0 {
+        return None;
+    }
+    let now = (gtk::glib::monotonic_time() / 1000) as u32;
+    let age = Duration::from_millis(now.wrapping_sub(time) as u64);
+    // from another clock
+    (age < MAX_EVENT_AGE).then_some(age)
+}
+
+/// Returns when the key event at `time` happened, now if unknown.
+pub fn event_instant(time: u32) -> Instant {
+    let now = Instant::now();
+    event_age(time)
+        .and_then(|age| now.checked_sub(age))
+        .unwrap_or(now)
+}
+
+/// Returns the color of a theme as a Pango attribute.
+pub fn foreground(color: &Color) -> pango::Attribute {
+    let channel = |c: f64| (c * u16::MAX as f64).round() as u16;
+    pango::AttrColor::new_foreground(channel(color.r), channel(color.g), channel(color.b)).into()
+}
+
+impl Themed for gtk::cairo::Context {
+    fn set_color(&self, color: &Color) {
+        self.set_source_rgb(color.r, color.g, color.b);
+    }
+
+    fn set_font(&self, font: &Font) {
+        let weight = if font.bold {
+            gtk::cairo::FontWeight::Bold
+        } else {
+            gtk::cairo::FontWeight::Normal
+        };
+        self.select_font_face(&font.family, gtk::cairo::FontSlant::Normal, weight);
+        self.set_font_size(font.size);
+    }
+
+    fn show_layout(&self, layout: &pango::Layout) {
+        pangocairo::functions::show_layout(self, layout)
+    }
+}
@@ -0,0 +1,830 @@
+//! Imports keyboard layouts from the XKB symbols files of Linux systems.
+//!
+//! Geometry: where the keys of a physical keyboard are, to lay out the
+//! symbols on.
+//! Symbols: the characters typed by each key without and with shift, read
+//! from a section of a symbols file along with the sections it includes.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::layout::{Finger, Key, Layout};
+
+/// Where the symbols files are installed.
+pub const XKB_DIR: &str = "/usr/share/X11/xkb/symbols";
+/// Sections include each other, a deeper nesting is a loop.
+const MAX_INCLUDES: usize = 10;
+
+/// The physical arrangement of the keys of a keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Geometry {
+    /// The staggered rows of US keyboards.
+    Ansi,
+    /// Staggered rows with a key left of Z and a tall enter.
+    Iso,
+    /// Keys in a grid.
+    Ortholinear,
+    /// A grid split between the hands.
+    Split,
+}
+
+/// Keys row by row, as XKB key codes or as labels in lowercase for the keys
+/// typing nothing, with their widths. An empty name leaves a gap.
+type Rows = &'static [&'static [(&'static str, f64)]];
+
+const ANSI: Rows = &[
+    &[
+        ("TLDE", 1.0),
+        ("AE01", 1.0),
+        ("AE02", 1.0),
+        ("AE03", 1.0),
+        ("AE04", 1.0),
+        ("AE05", 1.0),
+        ("AE06", 1.0),
+        ("AE07", 1.0),
+        ("AE08", 1.0),
+        ("AE09", 1.0),
+        ("AE10", 1.0),
+        ("AE11", 1.0),
+        ("AE12", 1.0),
+        ("del", 1.5),
+    ],
+    &[
+        ("tab", 1.5),
+        ("AD01", 1.0),
+        ("AD02", 1.0),
+        ("AD03", 1.0),
+        ("AD04", 1.0),
+        ("AD05", 1.0),
+        ("AD06", 1.0),
+        ("AD07", 1.0),
+        ("AD08", 1.0),
+        ("AD09", 1.0),
+        ("AD10", 1.0),
+        ("AD11", 1.0),
+        ("AD12", 1.0),
+        ("BKSL", 1.0),
+    ],
+    &[
+        ("caps", 2.0),
+        ("AC01", 1.0),
+        ("AC02", 1.0),
+        ("AC03", 1.0),
+        ("AC04", 1.0),
+        ("AC05", 1.0),
+        ("AC06", 1.0),
+        ("AC07", 1.0),
+        ("AC08", 1.0),
+        ("AC09", 1.0),
+        ("AC10", 1.0),
+        ("AC11", 1.0),
+        ("enter", 2.0),
+    ],
+    &[
+        ("shift", 3.0),
+        ("AB01", 1.0),
+        ("AB02", 1.0),
+        ("AB03", 1.0),
+        ("AB04", 1.0),
+        ("AB05", 1.0),
+        ("AB06", 1.0),
+        ("AB07", 1.0),
+        ("AB08", 1.0),
+        ("AB09", 1.0),
+        ("AB10", 1.0),
+        ("shift", 3.0),
+    ],
+    &[("space", 10.0)],
+];
+
+const ISO: Rows = &[
+    ANSI[0],
+    &[
+        ("tab", 1.5),
+        ("AD01", 1.0),
+        ("AD02", 1.0),
+        ("AD03", 1.0),
+        ("AD04", 1.0),
+        ("AD05", 1.0),
+        ("AD06", 1.0),
+        ("AD07", 1.0),
+        ("AD08", 1.0),
+        ("AD09", 1.0),
+        ("AD10", 1.0),
+        ("AD11", 1.0),
+        ("AD12", 1.0),
+        ("enter", 1.5),
+    ],
+    &[
+        ("caps", 2.0),
+        ("AC01", 1.0),
+        ("AC02", 1.0),
+        ("AC03", 1.0),
+        ("AC04", 1.0),
+        ("AC05", 1.0),
+        ("AC06", 1.0),
+        ("AC07", 1.0),
+        ("AC08", 1.0),
+        ("AC09", 1.0),
+        ("AC10", 1.0),
+        ("AC11", 1.0),
+        ("BKSL", 1.0),
+    ],
+    &[
+        ("shift", 2.0),
+        ("LSGT", 1.0),
+        ("AB01", 1.0),
+        ("AB02", 1.0),
+        ("AB03", 1.0),
+        ("AB04", 1.0),
+        ("AB05", 1.0),
+        ("AB06", 1.0),
+        ("AB07", 1.0),
+        ("AB08", 1.0),
+        ("AB09", 1.0),
+        ("AB10", 1.0),
+        ("shift", 3.0),
+    ],
+    ANSI[4],
+];
+
+const ORTHOLINEAR: Rows = &[
+    &[
+        ("TLDE", 1.0),
+        ("AE01", 1.0),
+        ("AE02", 1.0),
+        ("AE03", 1.0),
+        ("AE04", 1.0),
+        ("AE05", 1.0),
+        ("AE06", 1.0),
+        ("AE07", 1.0),
+        ("AE08", 1.0),
+        ("AE09", 1.0),
+        ("AE10", 1.0),
+        ("AE11", 1.0),
+        ("AE12", 1.0),
+    ],
+    &[
+        ("tab", 1.0),
+        ("AD01", 1.0),
+        ("AD02", 1.0),
+        ("AD03", 1.0),
+        ("AD04", 1.0),
+        ("AD05", 1.0),
+        ("AD06", 1.0),
+        ("AD07", 1.0),
+        ("AD08", 1.0),
+        ("AD09", 1.0),
+        ("AD10", 1.0),
+        ("AD11", 1.0),
+        ("AD12", 1.0),
+        ("BKSL", 1.0),
+    ],
+    &[
+        ("caps", 1.0),
+        ("AC01", 1.0),
+        ("AC02", 1.0),
+        ("AC03", 1.0),
+        ("AC04", 1.0),
+        ("AC05", 1.0),
+        ("AC06", 1.0),
+        ("AC07", 1.0),
+        ("AC08", 1.0),
+        ("AC09", 1.0),
+        ("AC10", 1.0),
+        ("AC11", 1.0),
+        ("enter", 1.0),
+    ],
+    &[
+        ("shift", 1.0),
+        ("AB01", 1.0),
+        ("AB02", 1.0),
+        ("AB03", 1.0),
+        ("AB04", 1.0),
+        ("AB05", 1.0),
+        ("AB06", 1.0),
+        ("AB07", 1.0),
+        ("AB08", 1.0),
+        ("AB09", 1.0),
+        ("AB10", 1.0),
+        ("shift", 1.0),
+    ],
+    &[("", 3.0), ("space", 6.0)],
+];
+
+const SPLIT: Rows = &[
+    &[
+        ("TLDE", 1.0),
+        ("AE01", 1.0),
+        ("AE02", 1.0),
+        ("AE03", 1.0),
+        ("AE04", 1.0),
+        ("AE05", 1.0),
+        ("", 1.0),
+        ("AE06", 1.0),
+        ("AE07", 1.0),
+        ("AE08", 1.0),
+        ("AE09", 1.0),
+        ("AE10", 1.0),
+        ("AE11", 1.0),
+        ("AE12", 1.0),
+    ],
+    &[
+        ("tab", 1.0),
+        ("AD01", 1.0),
+        ("AD02", 1.0),
+        ("AD03", 1.0),
+        ("AD04", 1.0),
+        ("AD05", 1.0),
+        ("", 1.0),
+        ("AD06", 1.0),
+        ("AD07", 1.0),
+        ("AD08", 1.0),
+        ("AD09", 1.0),
+        ("AD10", 1.0),
+        ("AD11", 1.0),
+        ("AD12", 1.0),
+        ("BKSL", 1.0),
+    ],
+    &[
+        ("caps", 1.0),
+        ("AC01", 1.0),
+        ("AC02", 1.0),
+        ("AC03", 1.0),
+        ("AC04", 1.0),
+        ("AC05", 1.0),
+        ("", 1.0),
+        ("AC06", 1.0),
+        ("AC07", 1.0),
+        ("AC08", 1.0),
+        ("AC09", 1.0),
+        ("AC10", 1.0),
+        ("AC11", 1.0),
+        ("enter", 1.0),
+    ],
+    &[
+        ("shift", 1.0),
+        ("AB01", 1.0),
+        ("AB02", 1.0),
+        ("AB03", 1.0),
+        ("AB04", 1.0),
+        ("AB05", 1.0),
+        ("", 1.0),
+        ("AB06", 1.0),
+        ("AB07", 1.0),
+        ("AB08", 1.0),
+        ("AB09", 1.0),
+        ("AB10", 1.0),
+        ("shift", 1.0),
+    ],
+    &[("", 3.0), ("space", 3.0), ("", 1.0), ("space", 3.0)],
+];
+
+/// Names of the keysyms typing a character, with its code point, one per
+/// line.
+const KEYSYMS: &str = include_str!("../data/keysyms.txt");
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    /// A key code like `<AE01>`.
+    Code(String),
+    Punct(char),
+}
+
+/// A section of a symbols file.
+#[derive(Debug)]
+struct Section {
+    name: String,
+    default: bool,
+    body: Vec<Token>,
+}
+
+/// What a level of a key types.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Level {
+    Char(char),
+    /// A dead key or a keysym typing nothing.
+    Nothing,
+    /// `NoSymbol`, the level defined before is kept.
+    #[default]
+    Keep,
+}
+
+#[derive(Debug, Default)]
+struct Symbols {
+    /// Like `French (AZERTY)`.
+    name: Option<String>,
+    /// The levels of each key code, without and with shift.
+    keys: HashMap<String, [Level; 2]>,
+}
+
+impl Geometry {
+    /// Every geometry, in the order offered.
+    pub const ALL: [Geometry; 4] = [
+        Geometry::Ansi,
+        Geometry::Iso,
+        Geometry::Ortholinear,
+        Geometry::Split,
+    ];
+
+    /// Returns the name of the geometry, as given on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Geometry::Ansi => "ansi",
+            Geometry::Iso => "iso",
+            Geometry::Ortholinear => "ortholinear",
+            Geometry::Split => "split",
+        }
+    }
+
+    fn rows(&self) -> Rows {
+        match self {
+            Geometry::Ansi => ANSI,
+            Geometry::Iso => ISO,
+            Geometry::Ortholinear => ORTHOLINEAR,
+            Geometry::Split => SPLIT,
+        }
+    }
+}
+
+impl FromStr for Geometry {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Geometry::ALL
+            .into_iter()
+            .find(|g| g.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                anyhow!(
+                    "unknown geometry {}, expected ansi, iso, ortholinear or split",
+                    s
+                )
+            })
+    }
+}
+
+/// Returns the finger typing the key with this code, the pinky of the
+/// closest side for the keys on the edges.
+fn finger(code: &str, first: bool) -> Finger {
+    use Finger::*;
+    let column = code.get(2..).and_then(|n| n.parse::<u32>().ok());
+    match (code, column) {
+        ("space", _) => Thumb,
+        (_, Some(1)) => LeftPinky,
+        (_, Some(2)) => LeftRing,
+        (_, Some(3)) => LeftMiddle,
+        (_, Some(4 | 5)) => LeftIndex,
+        (_, Some(6 | 7)) => RightIndex,
+        (_, Some(8)) => RightMiddle,
+        (_, Some(9)) => RightRing,
+        (_, Some(_)) => RightPinky,
+        _ if first => LeftPinky,
+        _ => RightPinky,
+    }
+}
+
+/// Returns the character typed by the keysym `name`, None for the dead keys
+/// and the keysyms typing nothing.
+fn keysym(name: &str) -> Option<char> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(c);
+    }
+    let named = KEYSYMS
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(n, _)| *n == name);
+    if let Some((_, hex)) = named {
+        return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+    }
+    // like U00E9, or 0x10000e9 for the Unicode keysyms
+    let code = match (name.strip_prefix('U'), name.strip_prefix("0x")) {
+        (Some(hex), _) if hex.len() >= 4 => u32::from_str_radix(hex, 16).ok()?,
+        (_, Some(hex)) => match u32::from_str_radix(hex, 16).ok()? {
+            k @ 0x20..=0xff => k,
+            k @ 0x100_0000.. => k - 0x100_0000,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    char::from_u32(code).filter(|c| !c.is_control())
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => (),
+            '#' => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '"' => {
+                let s: String = chars.by_ref().take_while(|c| *c != '"').collect();
+                tokens.push(Token::Str(s));
+            }
+            '<' => {
+                let s: String = chars.by_ref().take_while(|c| *c != '>').collect();
+                tokens.push(Token::Code(s));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut s = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || "_.".contains(*c)) {
+                    s.push(c);
+                }
+                tokens.push(Token::Ident(s));
+            }
+            c if "{}[](),=;+-!".contains(c) => tokens.push(Token::Punct(c)),
+            c => bail!("unexpected {:?}", c),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Returns the index of the token closing the one opened at `start`.
+fn closing(tokens: &[Token], start: usize) -> Result<usize> {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token {
+            Token::Punct('{' | '[') => depth += 1,
+            Token::Punct('}' | ']') => depth -= 1,
+            _ => (),
+        }
+        if depth == 0 {
+            return Ok(i);
+        }
+    }
+    bail!("missing closing brace")
+}
+
+fn sections(tokens: &[Token]) -> Result<Vec<Section>> {
+    let mut sections = vec![];
+    let mut default = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        match (&tokens[i], tokens.get(i + 1), tokens.get(i + 2)) {
+            (Token::Ident(s), Some(Token::Str(name)), Some(Token::Punct('{')))
+                if s == "xkb_symbols" =>
+            {
+                let end = closing(tokens, i + 2)?;
+                sections.push(Section {
+                    name: name.clone(),
+                    default,
+                    body: tokens[i + 3..end].to_vec(),
+                });
+                default = false;
+                i = end + 1;
+            }
+            (Token::Ident(s), _, _) if s == "default" => {
+                default = true;
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    Ok(sections)
+}
+
+/// Splits `us(intl)` into the file name and the variant.
+fn split_variant(spec: &str) -> (&str, Option<&str>) {
+    match spec.split_once('(') {
+        Some((file, variant)) => (file, Some(variant.trim_end_matches(')'))),
+        None => (spec, None),
+    }
+}
+
+/// Returns the file and the variant named by `spec` like
+/// `/usr/share/X11/xkb/symbols/fr(bepo)`, or just `fr(bepo)` for those in
+/// `XKB_DIR`.
+pub fn resolve(spec: &str) -> (PathBuf, Option<String>) {
+    let (file, variant) = split_variant(spec);
+    let path = if file.contains('/') {
+        PathBuf::from(file)
+    } else {
+        Path::new(XKB_DIR).join(file)
+    };
+    (path, variant.map(String::from))
+}
+
+/// Returns the characters typed by the first two levels of the first group
+/// of a key, from the tokens between its braces, None if they don't list its
+/// symbols.
+fn levels(tokens: &[Token]) -> Result<Option<[Level; 2]>> {
+    for (i, token) in tokens.iter().enumerate() {
+        if *token != Token::Punct('[') {
+            continue;
+        }
+        // `[ a, A ]` or `symbols[Group1] = [ a, A ]`, not `type[Group1]`
+        let symbols = match i.checked_sub(1).map(|j| &tokens[j]) {
+            None | Some(Token::Punct('{' | ',')) => true,
+            Some(Token::Punct('=')) => {
+                matches!(i.checked_sub(5).map(|j| &tokens[j]), Some(Token::Ident(s)) if s == "symbols")
+            }
+            _ => false,
+        };
+        if !symbols {
+            continue;
+        }
+        let end = closing(tokens, i)?;
+        let mut syms = tokens[i + 1..end]
+            .split(|t| *t == Token::Punct(','))
+            .map(|sym| match sym {
+                [Token::Ident(name)] if name == "NoSymbol" => Level::Keep,
+                [Token::Ident(name)] => keysym(name).map_or(Level::Nothing, Level::Char),
+                _ => Level::Nothing,
+            });
+        let mut level = || syms.next().unwrap_or(Level::Nothing);
+        return Ok(Some([level(), level()]));
+    }
+    Ok(None)
+}
+
+impl Level {
+    fn char(self) -> Option<char> {
+        match self {
+            Level::Char(c) => Some(c),
+            _ => None,
+        }
+    }
+}
+
+impl Symbols {
+    /// Reads the section `variant` of the symbols file at `path`, the
+    /// default one if None.
+    fn read(path: &Path, variant: Option<&str>, depth: usize) -> Result<Symbols> {
+        if depth > MAX_INCLUDES {
+            bail!("too many nested includes at {:?}", path);
+        }
+        let text = fs::read_to_string(path).context(format!("cannot read {:?}", path))?;
+        let sections = sections(&tokenize(&text).context(format!("cannot parse {:?}", path))?)?;
+        let section = match variant {
+            Some(v) => sections.iter().find(|s| s.name == v),
+            None => sections.iter().find(|s| s.default).or(sections.first()),
+        }
+        .ok_or_else(|| anyhow!("no variant {:?} in {:?}", variant, path))?;
+
+        let mut symbols = Symbols::default();
+        let tokens = &section.body;
+        let mut i = 0;
+        while i < tokens.len() {
+            match (&tokens[i], tokens.get(i + 1)) {
+                (Token::Ident(s), Some(Token::Str(spec))) if s == "include" => {
+                    let dir = path.parent().unwrap_or(Path::new("."));
+                    // `+` overrides the keys already defined, `|` augments them
+                    let mut augment = false;
+                    for part in spec.split_inclusive(['+', '|']) {
+                        let spec = part.trim_end_matches(['+', '|']);
+                        if !spec.is_empty() {
+                            let (file, variant) = split_variant(spec);
+                            let mut path = dir.join(file);
+                            if !path.exists() {
+                                path = Path::new(XKB_DIR).join(file);
+                            }
+                            symbols.merge(Symbols::read(&path, variant, depth + 1)?, augment);
+                        }
+                        augment = part.ends_with('|');
+                    }
+                    i += 2;
+                }
+                (Token::Ident(s), Some(Token::Punct('['))) if s == "name" => {
+                    if let Some(Token::Str(name)) = tokens.get(i + 5) {
+                        symbols.name = Some(name.clone());
+                    }
+                    i += 6;
+                }
+                (Token::Ident(s), Some(Token::Code(code))) if s == "key" => {
+                    let start = i + 2;
+                    if tokens.get(start) != Some(&Token::Punct('{')) {
+                        bail!("expected a brace after key <{}> in {:?}", code, path);
+                    }
+                    let end = closing(tokens, start)?;
+                    // even the dead keys hide those included
+                    if let Some(levels) = levels(&tokens[start + 1..end])? {
+                        symbols.set(code, levels, false);
+                    }
+                    i = end + 1;
+                }
+                _ => i += 1,
+            }
+        }
+        Ok(symbols)
+    }
+
+    /// Overrides the levels of the key `code`, except those left to
+    /// `NoSymbol`, or only sets those still undefined if `augment`.
+    fn set(&mut self, code: &str, levels: [Level; 2], augment: bool) {
+        let key = self.keys.entry(code.to_string()).or_default();
+        for (old, new) in key.iter_mut().zip(levels) {
+            if new != Level::Keep && (!augment || *old == Level::Keep) {
+                *old = new;
+            }
+        }
+    }
+
+    /// Overrides the keys with those of `other`, which is included, or only
+    /// adds the missing ones if `augment`.
+    fn merge(&mut self, other: Symbols, augment: bool) {
+        for (code, levels) in other.keys {
+            self.set(&code, levels, augment);
+        }
+        if other.name.is_some() && (!augment || self.name.is_none()) {
+            self.name = other.name;
+        }
+    }
+}
+
+/// Imports the section `variant` of the XKB symbols file at `path` (the
+/// default one if None), laid out on `geometry`.
+pub fn import(path: &Path, variant: Option<&str>, geometry: Geometry) -> Result<Layout> {
+    let symbols = Symbols::read(path, variant, 0)?;
+    let file = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = symbols.name.clone().unwrap_or_else(|| match variant {
+        Some(v) => format!("{}({})", file, v),
+        None => file.to_string(),
+    });
+    let rows = geometry
+        .rows()
+        .iter()
+        .map(|row| {
+            let mut keys = vec![];
+            let mut gap = 0.0;
+            for (code, width) in row.iter() {
+                if code.is_empty() {
+                    gap += width;
+                    continue;
+                }
+                let (base, shift) = match *code {
+                    "space" => (Some(' '), None),
+                    code => match symbols.keys.get(code) {
+                        Some(levels) => (levels[0].char(), levels[1].char()),
+                        None => (None, None),
+                    },
+                };
+                let label = match (base, *code) {
+                    (_, "space") => "space".to_string(),
+                    (Some(c), _) => c.to_uppercase().to_string(),
+                    (None, code) if code.chars().all(|c| c.is_lowercase()) => code.to_string(),
+                    (None, _) => String::new(),
+                };
+                keys.push(Key {
+                    label,
+                    base,
+                    shift,
+                    width: *width,
+                    gap,
+                    finger: finger(code, keys.is_empty()),
+                });
+                gap = 0.0;
+            }
+            keys
+        })
+        .collect();
+    Ok(Layout { name, rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{import, Geometry};
+    use crate::layout::{ErrorKind, Finger};
+    use crate::model::Touch;
+
+    const LATIN: &str = r#"
+default partial alphanumeric_keys
+xkb_symbols "basic" {
+    key <AD01> { [ q, Q ] };
+    key <AD02> { [ w, W ] };
+    key <AC01> { [ a, A ] };
+    key <AB01> { [ z, Z ] };
+    key <AE01> { [ 1, exclam ] };
+    key <AD11> { [ bracketleft, braceleft ] };
+    key <AC11> { [ apostrophe, quotedbl ] };
+};
+"#;
+
+    const EXTRA: &str = r#"
+xkb_symbols "basic" {
+    key <AD01> { [ x, X ] };
+    key <AB02> { [ slash, question ] };
+};
+"#;
+
+    const FR: &str = r#"
+// the letters moved by AZERTY
+default partial alphanumeric_keys
+xkb_symbols "basic" {
+    include "latin"
+    name[Group1]="French";
+    key <AD01> { [ a, A, ae, AE ] };
+    key <AC01> { [ q, Q ] };
+    key <AB01> { [ w, W ] };
+    key <AE01> { type[Group1]="FOUR_LEVEL", symbols[Group1]= [ ampersand, 1 ] };
+    key <AE02> { [ eacute, 2, asciitilde ] };
+    key <AD11> { [ dead_circumflex, dead_diaeresis ] };
+    key <AB08> { [ semicolon, period, U2022 ] }; # bullet
+    key <AB08> { [ NoSymbol, colon ] };
+    key <AC11> { [ VoidSymbol, dead_acute ] };
+    include "level3(ralt_switch)|extra"
+};
+
+xkb_symbols "bepo" {
+    name[Group1]="French (BEPO)";
+    key <AD01> { [ b, B ] };
+};
+"#;
+
+    const RU: &str = r#"
+default partial alphanumeric_keys
+xkb_symbols "basic" {
+    name[Group1]="Russian";
+    key <AD01> { [ Cyrillic_shorti, Cyrillic_SHORTI ] };
+    key <AC01> { [ Cyrillic_ef, Cyrillic_EF ] };
+    key <AE01> { [ 1, exclam ] };
+    key <AB10> { [ period, comma ] };
+};
+
+xkb_symbols "cz" {
+    key <AE02> { [ ecaron, 2 ] };
+    key <AE03> { [ scaron, 3 ] };
+    key <AD03> { [ e, E, EuroSign ] };
+    key <AD08> { [ idotless, I ] };
+};
+"#;
+
+    #[test]
+    pub fn it_reads_the_keysym_names() {
+        let dir = std::env::temp_dir().join(format!("touchtype_keysyms_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("ru"), RU).unwrap();
+
+        let ru = import(&dir.join("ru"), None, Geometry::Ansi).unwrap();
+        assert_eq!(ru.name, "Russian");
+        assert_eq!(ru.rows[1][1].base, Some('й'));
+        assert_eq!(ru.rows[1][1].shift, Some('Й'));
+        assert_eq!(ru.rows[1][1].label, "Й");
+        assert_eq!(ru.rows[2][1].base, Some('ф'));
+        assert!(ru.find(&Touch::Char('.')).is_some());
+
+        let cz = import(&dir.join("ru"), Some("cz"), Geometry::Ansi).unwrap();
+        assert_eq!(cz.rows[0][2].base, Some('ě'));
+        assert_eq!(cz.rows[0][3].base, Some('š'));
+        assert_eq!(cz.rows[1][3].base, Some('e'));
+        assert_eq!(cz.rows[1][8].base, Some('ı'));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    pub fn it_imports_xkb_symbols() {
+        let dir = std::env::temp_dir().join(format!("touchtype_xkb_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("latin"), LATIN).unwrap();
+        fs::write(dir.join("fr"), FR).unwrap();
+        fs::write(dir.join("extra"), EXTRA).unwrap();
+        fs::write(dir.join("level3"), "xkb_symbols \"ralt_switch\" {\n};\n").unwrap();
+
+        let fr = import(&dir.join("fr"), None, Geometry::Iso).unwrap();
+        assert_eq!(fr.name, "French");
+        let key = |c: char| fr.find(&Touch::Char(c)).unwrap();
+        assert_eq!(key('a').0.label, "A");
+        assert_eq!(key('A').0.finger, Finger::LeftPinky);
+        // included from latin
+        assert_eq!(key('W').0.base, Some('w'));
+        assert_eq!(key('&').0.shift, Some('1'));
+        assert_eq!(key('é').0.shift, Some('2'));
+        // AE01 of latin is overridden
+        assert_eq!(key('1').0.label, "&");
+        assert!(fr.find(&Touch::Char('!')).is_none());
+        assert_eq!(key(';').0.shift, Some(':'));
+        // the dead keys hide those of latin
+        assert!(fr.find(&Touch::Char('^')).is_none());
+        assert!(fr.find(&Touch::Char('[')).is_none());
+        assert!(fr.find(&Touch::Char('\'')).is_none());
+        // extra only adds the keys missing
+        assert!(fr.find(&Touch::Char('x')).is_none());
+        assert_eq!(key('/').0.shift, Some('?'));
+        assert!(fr.find(&Touch::Space).is_some());
+        assert_eq!(
+            fr.classify(&Touch::Char('a'), &Touch::Char('q')),
+            ErrorKind::Neighbour
+        );
+
+        let bepo = import(&dir.join("fr"), Some("bepo"), Geometry::Split).unwrap();
+        assert_eq!(bepo.name, "French (BEPO)");
+        assert_eq!(bepo.rows[1][1].base, Some('b'));
+        // the halves are apart
+        assert_eq!(bepo.rows[1][6].gap, 1.0);
+        assert!(import(&dir.join("fr"), Some("dvorak"), Geometry::Ansi).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
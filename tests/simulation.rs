@@ -0,0 +1,227 @@
+use rand::{rngs::StdRng, SeedableRng};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use touchtype::flow::Event;
+use touchtype::headless::{Headless, Script};
+use touchtype::idle::Idle;
+use touchtype::model::{Challenge, Mode, Practice, PracticeGenerator, Touch};
+use touchtype::session::History;
+use touchtype::trainer::Trainer;
+
+const INTERVAL: Duration = Duration::from_millis(200);
+
+/// Returns a trainer saving in a directory of its own.
+fn trainer(name: &str, mode: Mode) -> (Trainer<StdRng>, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("touchtype_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let rng = StdRng::seed_from_u64(7);
+    let generator = PracticeGenerator::new(rng, 3, "./data/t8.shakespeare.freq").with_mode(mode);
+    (Trainer::load(generator, None, dir.clone()), dir)
+}
+
+fn practice(text: &str) -> Practice {
+    Practice::new(Challenge::from_str(text).unwrap(), "practice_1".to_string())
+}
+
+#[test]
+pub fn it_saves_a_scripted_practice() {
+    let (trainer, dir) = trainer("saves", Mode::Words(3));
+    let mut app = Headless::new(trainer, practice("the quick fox"));
+    let script = Script::typing("the quick fox", INTERVAL).typo(4, Touch::Char('w'));
+    app.type_script(&script, Instant::now()).unwrap();
+
+    assert!(app.handled(|e| matches!(e, Event::End(_))));
+    let report = app.report().cloned().unwrap();
+    assert_eq!(report.summary.mistakes.len(), 1);
+    assert_eq!(report.summary.mistakes[0].word.as_str(), "quick");
+    // 13 keys over 12 intervals
+    assert!((app.stats.avg_key_s() - 13.0 / 2.4).abs() < 1e-3);
+    assert_eq!(app.practice().keystrokes().len(), script.len());
+
+    let history = History::load(dir.as_path()).unwrap();
+    assert_eq!(history.sessions().len(), 1);
+    assert_eq!(history.sessions()[0].text, "the quick fox");
+    assert!(dir.join("practice_1.json").exists());
+    assert!(dir.join("review.json").exists());
+
+    // the keys are for the results while they are shown
+    let keystrokes = app.practice().keystrokes().len();
+    app.press(Touch::Char('x'), Instant::now()).unwrap();
+    assert_eq!(app.practice().keystrokes().len(), keystrokes);
+    assert!(app.report().is_some());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+pub fn it_grows_a_timed_practice_until_the_time_is_up() {
+    let (mut trainer, dir) = trainer("timed", Mode::Timed(Duration::from_secs(2)));
+    let practice = trainer.generate().unwrap();
+    let text = practice.challenge().text();
+    let mut app = Headless::new(trainer, practice);
+    let start = Instant::now();
+    let script = Script::typing(&text[..5], INTERVAL);
+    app.type_script(&script, start).unwrap();
+
+    assert!(app.handled(|e| matches!(e, Event::Grow(_))));
+    assert!(app.practice().challenge().word_count() > 3);
+    assert!(app.report().is_none());
+    app.tick(start + Duration::from_secs(1)).unwrap();
+    assert!(app.report().is_none());
+    app.tick(start + Duration::from_secs(3)).unwrap();
+    assert!(app.handled(|e| matches!(e, Event::End(_))));
+    assert!(app.report().is_some());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+pub fn it_ends_a_practice_that_cannot_grow() {
+    let dir = std::env::temp_dir().join(format!("touchtype_grow_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let rng = StdRng::seed_from_u64(7);
+    let generator = PracticeGenerator::new(rng, 3, "./data/missing.freq").with_mode(Mode::Endless);
+    let trainer = Trainer::load(generator, None, dir.clone());
+    let mut app = Headless::new(trainer, practice("ab cd").with_mode(Mode::Endless));
+    app.type_script(&Script::typing("ab", INTERVAL), Instant::now())
+        .unwrap();
+
+    assert!(app.handled(|e| matches!(e, Event::Grow(_))));
+    let report = app.report().cloned().unwrap();
+    assert!(report.summary.incomplete);
+    let warnings = app.flow_mut().trainer_mut().take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].to_string().contains("more words"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+pub fn it_retries_and_practises_the_missed_words() {
+    let (trainer, dir) = trainer("missed", Mode::Words(2));
+    let mut app = Headless::new(trainer, practice("ab cd"));
+    let script = Script::typing("ab cd", INTERVAL)
+        .typo(0, Touch::Char('b'))
+        .pause(2, Duration::from_secs(5));
+    app.type_script(&script, Instant::now()).unwrap();
+    let report = app.report().cloned().unwrap();
+    // the pause counts as much as the keys typed
+    assert!(report.summary.wpm < 10.0);
+
+    app.missed().unwrap();
+    assert!(app.report().is_none());
+    assert_eq!(app.practice().challenge().text(), "ab ab ab");
+
+    // the current practice starts again
+    app.press(Touch::Char('a'), Instant::now()).unwrap();
+    app.retry().unwrap();
+    assert_eq!(app.practice().challenge().text(), "ab ab ab");
+    assert!(!app.practice().is_started());
+
+    app.next_practice().unwrap();
+    assert_eq!(app.practice().challenge().word_count(), 2);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+pub fn it_leaves_pauses_out_and_saves_an_abandoned_practice() {
+    let (trainer, dir) = trainer("abandon", Mode::Words(3));
+    let mut app = Headless::new(trainer, practice("the quick fox"));
+    let start = Instant::now();
+    app.type_script(&Script::typing("the", INTERVAL), start)
+        .unwrap();
+    let paused = start + INTERVAL * 2;
+    app.pause(paused).unwrap();
+    // the keys typed while paused are ignored
+    app.press(Touch::Space, paused + INTERVAL).unwrap();
+    assert_eq!(app.practice().keystrokes().len(), 3);
+    let resumed = paused + Duration::from_secs(60);
+    app.resume(resumed).unwrap();
+    app.press(Touch::Space, resumed + INTERVAL).unwrap();
+    assert_eq!(app.practice().keystrokes()[3].at, INTERVAL * 3);
+
+    app.abandon().unwrap();
+    let report = app.report().cloned().unwrap();
+    assert!(report.summary.incomplete);
+    assert!(report.summary.wpm > 10.0);
+    let history = History::load(dir.as_path()).unwrap();
+    assert!(history.sessions()[0].incomplete);
+
+    // incomplete sessions are not the best
+    app.retry().unwrap();
+    let script = Script::typing("the quick fox", INTERVAL * 10);
+    app.type_script(&script, Instant::now()).unwrap();
+    assert_eq!(app.report().unwrap().best_wpm, None);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+pub fn it_moves_on_when_abandoned_before_the_first_key() {
+    let (trainer, dir) = trainer("skip", Mode::Words(3));
+    let mut app = Headless::new(trainer, practice("the quick fox"));
+    app.abandon().unwrap();
+    // nothing is saved
+    assert!(app.handled(|e| matches!(e, Event::Next)));
+    assert!(!app.handled(|e| matches!(e, Event::End(_))));
+    assert!(app.report().is_none());
+    assert_ne!(app.practice().challenge().text(), "the quick fox");
+    assert_eq!(app.practice().challenge().word_count(), 3);
+    assert!(!dir.exists());
+}
+
+#[test]
+pub fn it_pauses_when_idle_and_flags_long_gaps() {
+    let (trainer, dir) = trainer("idle", Mode::Words(3));
+    let idle = Idle {
+        auto_pause: Some(Duration::from_secs(10)),
+        ..Idle::default()
+    };
+    let trainer = trainer.with_idle(idle);
+    let practice = practice("the quick fox").with_idle(idle.threshold);
+    let mut app = Headless::new(trainer, practice);
+    let start = Instant::now();
+    app.type_script(&Script::typing("the", INTERVAL), start)
+        .unwrap();
+    app.tick(start + Duration::from_secs(5)).unwrap();
+    assert!(!app.practice().is_paused());
+    app.tick(start + Duration::from_secs(11)).unwrap();
+    assert!(app.practice().is_paused());
+    assert!(app.flow().is_paused());
+
+    // a long gap without the auto pause
+    app.flow_mut().trainer_mut().set_idle(Idle::default());
+    let resumed = start + Duration::from_secs(20);
+    app.resume(resumed).unwrap();
+    let script = Script::typing(" quick fox", INTERVAL).pause(0, Duration::from_secs(90));
+    app.type_script(&script, resumed).unwrap();
+
+    let report = app.report().cloned().unwrap();
+    assert!(report.summary.suspicious);
+    // 10.6 s around the pause, then 90.2 s, 5 s of each count as typing
+    assert!((report.summary.idle_s - 91.0).abs() < 0.5);
+    assert!(report.summary.wpm > 10.0);
+    let history = History::load(dir.as_path()).unwrap();
+    assert_eq!(history.suspicious().count(), 1);
+    assert_eq!(history.sessions()[0].segments(), vec![0..3, 3..4, 4..13]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+pub fn it_saves_how_long_the_keys_were_held() {
+    let (trainer, dir) = trainer("dwell", Mode::Words(1));
+    let mut app = Headless::new(trainer, practice("ab"));
+    let start = Instant::now();
+    app.press(Touch::Char('a'), start).unwrap();
+    app.release(Touch::Char('a'), start + INTERVAL / 2).unwrap();
+    app.press(Touch::Char('b'), start + INTERVAL).unwrap();
+    // saved once the last key is released
+    assert!(app.report().is_none());
+    app.release(Touch::Char('b'), start + INTERVAL * 2).unwrap();
+    assert!(app.report().is_some());
+
+    let history = History::load(dir.as_path()).unwrap();
+    let keystrokes = &history.sessions()[0].keystrokes;
+    assert_eq!(keystrokes[0].dwell, Some(INTERVAL / 2));
+    assert_eq!(keystrokes[1].flight, Some(INTERVAL / 2));
+    assert_eq!(keystrokes[1].dwell, Some(INTERVAL));
+    std::fs::remove_dir_all(dir).unwrap();
+}